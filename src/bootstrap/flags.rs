@@ -180,7 +180,7 @@ To learn more about a subcommand, run `./x.py <subcommand> -h`"
                 opts.optflag(
                     "",
                     "bless",
-                    "update all stderr/stdout files of failing ui tests",
+                    "update the expected output of failing ui, pretty and mir-opt tests",
                 );
                 opts.optopt(
                     "",
@@ -285,6 +285,7 @@ Arguments:
         ./x.py test src/libstd --test-args hash_map
         ./x.py test src/libstd --stage 0 --no-doc
         ./x.py test src/test/ui --bless
+        ./x.py test src/test/mir-opt --bless
         ./x.py test src/test/ui --compare-mode nll
 
     Note that `test src/test/* --stage N` does NOT depend on `build src/rustc --stage N`;
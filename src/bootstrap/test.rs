@@ -1061,6 +1061,10 @@ impl Step for Compiletest {
             cmd.arg("--bless");
         }
 
+        // A compare mode run below writes its own report next to this one.
+        cmd.arg("--report")
+            .arg(testdir(builder, compiler.host).join(suite).join("report.json"));
+
         let compare_mode = builder.config.cmd.compare_mode().or_else(|| {
             if builder.config.test_compare_mode {
                 self.compare_mode
@@ -1281,6 +1285,11 @@ impl Step for Compiletest {
             cmd.env("PROFILER_SUPPORT", "1");
         }
 
+        if suite == "run-make-fulldeps" {
+            // Used to check that the tool can read what `-Z self-profile` writes.
+            cmd.env("SELF_PROFILE_SUMMARY", builder.tool_exe(Tool::SelfProfileSummary));
+        }
+
         cmd.env("RUST_TEST_TMPDIR", builder.out.join("tmp"));
 
         cmd.arg("--adb-path").arg("adb");
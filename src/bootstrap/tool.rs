@@ -361,6 +361,8 @@ tool!(
     RustInstaller, "src/tools/rust-installer", "fabricate", Mode::ToolBootstrap,
         is_external_tool = true;
     RustdocTheme, "src/tools/rustdoc-themes", "rustdoc-themes", Mode::ToolBootstrap;
+    SelfProfileSummary, "src/tools/self-profile-summary", "self-profile-summary",
+        Mode::ToolBootstrap;
 );
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
@@ -48,6 +48,28 @@ impl DepNodeColor {
     }
 }
 
+/// Why a node from the previous compilation session could not be marked
+/// green, and had to be re-executed instead.
+#[derive(Copy, Clone, Debug)]
+pub enum RedReason {
+    /// The result of this dependency changed.
+    DependencyChanged(DepNode),
+    /// This dependency no longer exists.
+    DependencyRemoved(DepNode),
+    /// This dependency could not be forced to find out whether it changed.
+    DependencyNotForced(DepNode),
+}
+
+impl RedReason {
+    pub fn dependency(&self) -> &DepNode {
+        match *self {
+            RedReason::DependencyChanged(ref dep_node) |
+            RedReason::DependencyRemoved(ref dep_node) |
+            RedReason::DependencyNotForced(ref dep_node) => dep_node,
+        }
+    }
+}
+
 struct DepGraphData {
     /// The new encoding of the dependency graph, optimized for red/green
     /// tracking. The `current` field is the dependency graph of only the
@@ -77,6 +99,10 @@ struct DepGraphData {
 
     // Used for testing, only populated when -Zquery-dep-graph is specified.
     loaded_from_cache: Lock<FxHashMap<DepNodeIndex, bool>>,
+
+    // Why nodes could not be marked green, only populated when
+    // -Zincremental-explain is specified.
+    red_reasons: Lock<FxHashMap<DepNode, RedReason>>,
 }
 
 pub fn hash_result<R>(hcx: &mut StableHashingContext<'_>, result: &R) -> Option<Fingerprint>
@@ -105,6 +131,7 @@ impl DepGraph {
                 previous: prev_graph,
                 colors: DepNodeColorMap::new(prev_graph_node_count),
                 loaded_from_cache: Default::default(),
+                red_reasons: Default::default(),
             })),
         }
     }
@@ -604,6 +631,36 @@ impl DepGraph {
         }
     }
 
+    #[inline(never)]
+    #[cold]
+    fn record_red_reason(
+        &self,
+        tcx: TyCtxt<'_, '_, '_>,
+        data: &DepGraphData,
+        dep_node: &DepNode,
+        reason: RedReason,
+    ) {
+        if tcx.sess.opts.debugging_opts.incremental_explain.is_some() {
+            data.red_reasons.lock().insert(*dep_node, reason);
+        }
+    }
+
+    /// Returns `true` if there is no dependency graph from a previous
+    /// compilation session to reuse results from.
+    pub fn prev_graph_is_empty(&self) -> bool {
+        self.data.as_ref().map_or(true, |data| data.previous.node_count() == 0)
+    }
+
+    /// Returns why the nodes from the previous compilation session that could
+    /// not be marked green had to be re-executed. Only recorded when
+    /// `-Z incremental-explain` is specified.
+    pub fn red_reasons(&self) -> FxHashMap<DepNode, RedReason> {
+        match self.data {
+            Some(ref data) => data.red_reasons.lock().clone(),
+            None => FxHashMap::default(),
+        }
+    }
+
     /// Try to mark a dep-node which existed in the previous compilation session as green.
     fn try_mark_previous_green<'tcx>(
         &self,
@@ -652,6 +709,11 @@ impl DepGraph {
                             immediately red",
                             dep_node,
                             data.previous.index_to_node(dep_dep_node_index));
+                    let dep_dep_node = data.previous.index_to_node(dep_dep_node_index);
+                    self.record_red_reason(tcx,
+                                           data,
+                                           dep_node,
+                                           RedReason::DependencyChanged(dep_dep_node));
                     return None
                 }
                 None => {
@@ -684,6 +746,11 @@ impl DepGraph {
                                 if dep_dep_node.extract_def_id(tcx).is_none() {
                                     // If the node does not exist anymore, we
                                     // just fail to mark green.
+                                    self.record_red_reason(tcx,
+                                                           data,
+                                                           dep_node,
+                                                           RedReason::DependencyRemoved(
+                                                               *dep_dep_node));
                                     return None
                                 } else {
                                     // If the node does exist, it should have
@@ -718,6 +785,11 @@ impl DepGraph {
                                         dependency {:?} was red after forcing",
                                        dep_node,
                                        dep_dep_node);
+                                self.record_red_reason(tcx,
+                                                       data,
+                                                       dep_node,
+                                                       RedReason::DependencyChanged(
+                                                           *dep_dep_node));
                                 return None
                             }
                             None => {
@@ -736,6 +808,10 @@ impl DepGraph {
                         // The DepNode could not be forced.
                         debug!("try_mark_previous_green({:?}) - END - dependency {:?} \
                                 could not be forced", dep_node, dep_dep_node);
+                        self.record_red_reason(tcx,
+                                               data,
+                                               dep_node,
+                                               RedReason::DependencyNotForced(*dep_dep_node));
                         return None
                     }
                 }
@@ -12,6 +12,7 @@ use crate::hir;
 use crate::hir::def_id::DefId;
 use crate::hir::intravisit::{self, Visitor, NestedVisitorMap};
 use std::fmt::{self, Display};
+use syntax::attr;
 use syntax_pos::Span;
 
 #[derive(Copy, Clone, PartialEq)]
@@ -113,6 +114,7 @@ impl<'a, 'tcx> CheckAttrVisitor<'a, 'tcx> {
 
         self.check_repr(item, target);
         self.check_used(item, target);
+        self.check_test_timeout(item);
     }
 
     /// Checks if an `#[inline]` is applied to a function or a closure.
@@ -319,6 +321,22 @@ impl<'a, 'tcx> CheckAttrVisitor<'a, 'tcx> {
             }
         }
     }
+
+    /// Checks that `#[test_timeout]` is only applied to tests. The test harness marks the
+    /// attribute as used when it reads the timeout of a test, so any other `#[test_timeout]`
+    /// would be ignored.
+    fn check_test_timeout(&self, item: &hir::Item) {
+        for attr in &item.attrs {
+            if attr.name() == "test_timeout" && !attr::is_used(attr) {
+                self.tcx.sess
+                    .struct_span_err(attr.span,
+                                     "the `#[test_timeout]` attribute can only be applied to \
+                                      `#[test]` functions")
+                    .span_label(item.span, "not a test function")
+                    .emit();
+            }
+        }
+    }
 }
 
 impl<'a, 'tcx> Visitor<'tcx> for CheckAttrVisitor<'a, 'tcx> {
@@ -20,7 +20,7 @@ use syntax::parse;
 use syntax::symbol::Symbol;
 use syntax::feature_gate::UnstableFeatures;
 
-use errors::{ColorConfig, FatalError, Handler};
+use errors::{Applicability, ColorConfig, DiagnosticTreatment, FatalError, Handler};
 
 use getopts;
 use std::collections::{BTreeMap, BTreeSet};
@@ -207,6 +207,8 @@ pub enum ErrorOutputType {
     HumanReadable(ColorConfig),
     Json(bool),
     Short(ColorConfig),
+    /// A single SARIF log of all diagnostics, written when compilation finishes.
+    Sarif,
 }
 
 impl Default for ErrorOutputType {
@@ -375,6 +377,8 @@ top_level_options!(
 
         test: bool [TRACKED],
         error_format: ErrorOutputType [UNTRACKED],
+        // Print a summary of the long explanation under each error with an error code.
+        explain_inline: bool [UNTRACKED],
 
         // if Some, enable incremental compilation, using the given
         // directory to store intermediate results
@@ -607,6 +611,7 @@ impl Default for Options {
             borrowck_mode: BorrowckMode::Ast,
             cg: basic_codegen_options(),
             error_format: ErrorOutputType::default(),
+            explain_inline: false,
             externs: Externs(BTreeMap::new()),
             crate_name: None,
             alt_std_name: None,
@@ -827,12 +832,21 @@ macro_rules! options {
                   or the path to the linker plugin");
         pub const parse_merge_functions: Option<&str> =
             Some("one of: `disabled`, `trampolines`, or `aliases`");
+        pub const parse_opt_filter: Option<&str> =
+            Some("either no value or a filter like `a & b`");
+        pub const parse_opt_size: Option<&str> =
+            Some("a size in bytes, optionally followed by `K`, `M` or `G`");
+        pub const parse_apply_suggestions: Option<&str> =
+            Some("either no value, `machine-applicable` or `maybe-incorrect`");
+        pub const parse_diagnostic_treatment: Option<&str> =
+            Some("`<slug>:allow`, `<slug>:warn` or `<slug>:deny`");
     }
 
     #[allow(dead_code)]
     mod $mod_set {
         use super::{$struct_name, Passes, Sanitizer, LtoCli, LinkerPluginLto};
         use rustc_target::spec::{LinkerFlavor, MergeFunctions, PanicStrategy, RelroLevel};
+        use errors::{Applicability, DiagnosticTreatment};
         use std::path::PathBuf;
         use std::str::FromStr;
 
@@ -1081,6 +1095,52 @@ macro_rules! options {
             }
             true
         }
+
+        fn parse_opt_filter(slot: &mut Option<String>, v: Option<&str>) -> bool {
+            *slot = Some(v.unwrap_or("").to_string());
+            true
+        }
+
+        fn parse_opt_size(slot: &mut Option<u64>, v: Option<&str>) -> bool {
+            let s = match v {
+                Some(s) => s,
+                None => return false,
+            };
+            let (digits, shift) = match s.as_bytes().last() {
+                Some(b'K') | Some(b'k') => (&s[..s.len() - 1], 10),
+                Some(b'M') | Some(b'm') => (&s[..s.len() - 1], 20),
+                Some(b'G') | Some(b'g') => (&s[..s.len() - 1], 30),
+                _ => (s, 0),
+            };
+            *slot = digits.parse::<u64>().ok().and_then(|n| n.checked_mul(1 << shift));
+            slot.is_some()
+        }
+
+        fn parse_apply_suggestions(slot: &mut Option<Applicability>, v: Option<&str>) -> bool {
+            *slot = match v {
+                None | Some("machine-applicable") => Some(Applicability::MachineApplicable),
+                Some("maybe-incorrect") => Some(Applicability::MaybeIncorrect),
+                Some(_) => return false,
+            };
+            true
+        }
+
+        fn parse_diagnostic_treatment(slot: &mut Vec<(String, DiagnosticTreatment)>,
+                                      v: Option<&str>) -> bool {
+            // Slugs contain `::`, so split at the last colon.
+            let (slug, treatment) = match v.and_then(|s| s.rfind(':').map(|i| s.split_at(i))) {
+                Some((slug, treatment)) if !slug.is_empty() => (slug, &treatment[1..]),
+                _ => return false,
+            };
+            let treatment = match treatment {
+                "allow" => DiagnosticTreatment::Allow,
+                "warn" => DiagnosticTreatment::Warn,
+                "deny" => DiagnosticTreatment::Deny,
+                _ => return false,
+            };
+            slot.push((slug.to_string(), treatment));
+            true
+        }
     }
 ) }
 
@@ -1254,6 +1314,21 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
         "show macro backtraces even for non-local macros"),
     teach: bool = (false, parse_bool, [TRACKED],
         "show extended diagnostic help"),
+    apply_suggestions: Option<Applicability> = (None, parse_apply_suggestions, [UNTRACKED],
+        "rewrite the source files with the suggestions of the emitted diagnostics that are \
+         at least as certain as the given applicability (default: `machine-applicable`)"),
+    apply_suggestions_diff: Option<PathBuf> = (None, parse_opt_pathbuf, [UNTRACKED],
+        "with `-Z apply-suggestions`, write the changes as a unified diff to this file \
+         instead of rewriting the source files"),
+    group_similar_diagnostics: bool = (false, parse_bool, [UNTRACKED],
+        "only show the first of several errors or warnings with the same code, message and \
+         primary labels, and list where the others were reported at the end"),
+    treat_diagnostic: Vec<(String, DiagnosticTreatment)> = (Vec::new(),
+        parse_diagnostic_treatment, [UNTRACKED],
+        "allow, warn about or deny the diagnostics with a slug, like \
+         `-Z treat-diagnostic=lint::unused_variables:deny` (errors cannot be downgraded). \
+         Slugs are `lint::<name>`, `error::<code>` or one given where the diagnostic is \
+         created, like `expand::empty_derive`; other diagnostics cannot be matched"),
     continue_parse_after_error: bool = (false, parse_bool, [TRACKED],
         "attempt to recover from parse errors (experimental)"),
     dep_tasks: bool = (false, parse_bool, [UNTRACKED],
@@ -1264,6 +1339,15 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
         "enable incremental compilation support for queries (experimental)"),
     incremental_info: bool = (false, parse_bool, [UNTRACKED],
         "print high-level information about incremental reuse (or the lack thereof)"),
+    incremental_cache_limit: Option<u64> = (None, parse_opt_size, [UNTRACKED],
+        "evict the least recently used caches from the incremental compilation directory \
+         until it is smaller than this size (e.g. `500M` or `2G`)"),
+    incremental_cache_max_age: Option<usize> = (None, parse_opt_uint, [UNTRACKED],
+        "evict the caches from the incremental compilation directory that have not been \
+         used for this many days"),
+    incremental_explain: Option<String> = (None, parse_opt_filter, [UNTRACKED],
+        "write a report explaining why queries matching the optional dep-node filter \
+         were re-executed (`-Z incremental-explain[=a & b]`)"),
     incremental_dump_hash: bool = (false, parse_bool, [UNTRACKED],
         "dump hash information in textual format to stdout"),
     incremental_verify_ich: bool = (false, parse_bool, [UNTRACKED],
@@ -1325,7 +1409,8 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
     perf_stats: bool = (false, parse_bool, [UNTRACKED],
         "print some performance-related statistics"),
     query_stats: bool = (false, parse_bool, [UNTRACKED],
-        "print some statistics about the query system"),
+        "print how often each query was executed and how long that took, and some \
+         other statistics about the query system"),
     hir_stats: bool = (false, parse_bool, [UNTRACKED],
         "print some statistics about AST and HIR"),
     always_encode_mir: bool = (false, parse_bool, [TRACKED],
@@ -1417,7 +1502,11 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
     crate_attr: Vec<String> = (Vec::new(), parse_string_push, [TRACKED],
         "inject the given attribute in the crate"),
     self_profile: bool = (false, parse_bool, [UNTRACKED],
-        "run the self profiler and output the raw event data"),
+        "run the self profiler and output the events in the Chrome trace-event format"),
+    self_profile_folded: bool = (false, parse_bool, [UNTRACKED],
+        "run the self profiler and output folded stacks for flamegraph tools"),
+    self_profile_raw: bool = (false, parse_bool, [UNTRACKED],
+        "run the self profiler and only output the binary event stream"),
     emit_stack_sizes: bool = (false, parse_bool, [UNTRACKED],
         "emits a section containing stack size metadata"),
     plt: Option<bool> = (None, parse_opt_bool, [TRACKED],
@@ -1773,7 +1862,12 @@ pub fn rustc_optgroups() -> Vec<RustcOptGroup> {
             "",
             "error-format",
             "How errors and other messages are produced",
-            "human|json|short",
+            "human|json|short|sarif",
+        ),
+        opt::flag(
+            "",
+            "explain-inline",
+            "Print a summary of the explanation of the error code under each error",
         ),
         opt::opt_s(
             "",
@@ -1931,13 +2025,14 @@ pub fn build_session_options_and_crate_config(
             Some("json") => ErrorOutputType::Json(false),
             Some("pretty-json") => ErrorOutputType::Json(true),
             Some("short") => ErrorOutputType::Short(color),
+            Some("sarif") => ErrorOutputType::Sarif,
             None => ErrorOutputType::HumanReadable(color),
 
             Some(arg) => early_error(
                 ErrorOutputType::HumanReadable(color),
                 &format!(
-                    "argument for --error-format must be `human`, `json` or \
-                     `short` (instead was `{}`)",
+                    "argument for --error-format must be `human`, `json`, `short` or \
+                     `sarif` (instead was `{}`)",
                     arg
                 ),
             ),
@@ -1961,6 +2056,12 @@ pub fn build_session_options_and_crate_config(
             "--error-format=pretty-json is unstable",
         );
     }
+    if !debugging_opts.unstable_options && error_format == ErrorOutputType::Sarif {
+        early_error(
+            ErrorOutputType::Json(false),
+            "--error-format=sarif is unstable",
+        );
+    }
 
     if debugging_opts.pgo_gen.is_some() && !debugging_opts.pgo_use.is_empty() {
         early_error(
@@ -1969,6 +2070,14 @@ pub fn build_session_options_and_crate_config(
         );
     }
 
+    if debugging_opts.apply_suggestions_diff.is_some() &&
+       debugging_opts.apply_suggestions.is_none() {
+        early_error(
+            error_format,
+            "option `-Z apply-suggestions-diff` requires `-Z apply-suggestions`",
+        );
+    }
+
     let mut output_types = BTreeMap::new();
     if !debugging_opts.parse_only {
         for list in matches.opt_strs("emit") {
@@ -2332,6 +2441,7 @@ pub fn build_session_options_and_crate_config(
             borrowck_mode,
             cg,
             error_format,
+            explain_inline: matches.opt_present("explain-inline"),
             externs: Externs(externs),
             crate_name,
             alt_std_name: None,
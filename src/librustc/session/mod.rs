@@ -23,6 +23,7 @@ use rustc_data_structures::sync::{
 
 use errors::{DiagnosticBuilder, DiagnosticId, Applicability};
 use errors::emitter::{Emitter, EmitterWriter};
+use errors::sarif::SarifEmitter;
 use syntax::ast::{self, NodeId};
 use syntax::edition::Edition;
 use syntax::feature_gate::{self, AttributeType};
@@ -46,7 +47,6 @@ use std::path::PathBuf;
 use std::time::Duration;
 use std::sync::{Arc, mpsc};
 
-use parking_lot::Mutex as PlMutex;
 
 mod code_stats;
 pub mod config;
@@ -130,7 +130,7 @@ pub struct Session {
     pub profile_channel: Lock<Option<mpsc::Sender<ProfileQueriesMsg>>>,
 
     /// Used by -Z self-profile
-    pub self_profiling: Option<Arc<PlMutex<SelfProfiler>>>,
+    pub self_profiling: Option<Arc<SelfProfiler>>,
 
     /// Some measurements that are being gathered during compilation.
     pub perf_stats: PerfStats,
@@ -832,19 +832,15 @@ impl Session {
 
     #[inline(never)]
     #[cold]
-    fn profiler_active<F: FnOnce(&mut SelfProfiler) -> ()>(&self, f: F) {
+    fn profiler_active<F: FnOnce(&SelfProfiler) -> ()>(&self, f: F) {
         match &self.self_profiling {
             None => bug!("profiler_active() called but there was no profiler active"),
-            Some(profiler) => {
-                let mut p = profiler.lock();
-
-                f(&mut p);
-            }
+            Some(profiler) => f(profiler),
         }
     }
 
     #[inline(always)]
-    pub fn profiler<F: FnOnce(&mut SelfProfiler) -> ()>(&self, f: F) {
+    pub fn profiler<F: FnOnce(&SelfProfiler) -> ()>(&self, f: F) {
         if unlikely!(self.self_profiling.is_some()) {
             self.profiler_active(f)
         }
@@ -1037,11 +1033,13 @@ fn default_emitter(
                 Some(source_map.clone()),
                 false,
                 sopts.debugging_opts.teach,
-            ).ui_testing(sopts.debugging_opts.ui_testing),
+            ).ui_testing(sopts.debugging_opts.ui_testing)
+                .explain_inline(Some(registry).filter(|_| sopts.explain_inline)),
         ),
         (config::ErrorOutputType::HumanReadable(_), Some(dst)) => Box::new(
             EmitterWriter::new(dst, Some(source_map.clone()), false, false)
-                .ui_testing(sopts.debugging_opts.ui_testing),
+                .ui_testing(sopts.debugging_opts.ui_testing)
+                .explain_inline(Some(registry).filter(|_| sopts.explain_inline)),
         ),
         (config::ErrorOutputType::Json(pretty), None) => Box::new(
             JsonEmitter::stderr(
@@ -1064,6 +1062,12 @@ fn default_emitter(
         (config::ErrorOutputType::Short(_), Some(dst)) => {
             Box::new(EmitterWriter::new(dst, Some(source_map.clone()), true, false))
         }
+        (config::ErrorOutputType::Sarif, None) => Box::new(
+            SarifEmitter::stderr(Some(registry), Some(source_map.clone())),
+        ),
+        (config::ErrorOutputType::Sarif, Some(dst)) => Box::new(
+            SarifEmitter::new(dst, Some(registry), Some(source_map.clone())),
+        ),
     }
 }
 
@@ -1100,6 +1104,10 @@ pub fn build_session_with_source_map(
     let report_delayed_bugs = sopts.debugging_opts.report_delayed_bugs;
 
     let external_macro_backtrace = sopts.debugging_opts.external_macro_backtrace;
+    let collect_suggestions = sopts.debugging_opts.apply_suggestions.is_some();
+    let group_similar_diagnostics = sopts.debugging_opts.group_similar_diagnostics;
+    // Later occurrences of `-Z treat-diagnostic` override earlier ones.
+    let diagnostic_treatments = sopts.debugging_opts.treat_diagnostic.iter().cloned().collect();
 
     let emitter = match diagnostics_output {
         DiagnosticOutput::Default => default_emitter(&sopts, registry, &source_map, None),
@@ -1117,6 +1125,9 @@ pub fn build_session_with_source_map(
             report_delayed_bugs,
             dont_buffer_diagnostics,
             external_macro_backtrace,
+            collect_suggestions,
+            group_similar_diagnostics,
+            diagnostic_treatments,
             ..Default::default()
         },
     );
@@ -1132,8 +1143,13 @@ fn build_session_(
     driver_lint_caps: FxHashMap<lint::LintId, lint::Level>,
 ) -> Session {
     let self_profiler =
-        if sopts.debugging_opts.self_profile { Some(Arc::new(PlMutex::new(SelfProfiler::new()))) }
-        else { None };
+        if sopts.debugging_opts.self_profile ||
+           sopts.debugging_opts.self_profile_folded ||
+           sopts.debugging_opts.self_profile_raw {
+            Some(Arc::new(SelfProfiler::new()))
+        } else {
+            None
+        };
 
     let host_triple = TargetTriple::from_triple(config::host_triple());
     let host = Target::search(&host_triple).unwrap_or_else(|e|
@@ -1305,6 +1321,10 @@ pub enum IncrCompSession {
     InvalidBecauseOfErrors { session_directory: PathBuf },
 }
 
+/// Reports a fatal error in the options, before the session is created.
+///
+/// With `--error-format=sarif` the error is written as a SARIF log of its own, so the output
+/// holds one log per line for every early diagnostic.
 pub fn early_error(output: config::ErrorOutputType, msg: &str) -> ! {
     let emitter: Box<dyn Emitter + sync::Send> = match output {
         config::ErrorOutputType::HumanReadable(color_config) => {
@@ -1314,12 +1334,17 @@ pub fn early_error(output: config::ErrorOutputType, msg: &str) -> ! {
         config::ErrorOutputType::Short(color_config) => {
             Box::new(EmitterWriter::stderr(color_config, None, true, false))
         }
+        config::ErrorOutputType::Sarif => Box::new(SarifEmitter::stderr(None, None)),
     };
     let handler = errors::Handler::with_emitter(true, None, emitter);
     handler.emit(&MultiSpan::new(), msg, errors::Level::Fatal);
     errors::FatalError.raise();
 }
 
+/// Reports a warning about the options, before the session is created.
+///
+/// With `--error-format=sarif` the warning is written as a SARIF log of its own, on the line
+/// before the log of the session.
 pub fn early_warn(output: config::ErrorOutputType, msg: &str) {
     let emitter: Box<dyn Emitter + sync::Send> = match output {
         config::ErrorOutputType::HumanReadable(color_config) => {
@@ -1329,6 +1354,7 @@ pub fn early_warn(output: config::ErrorOutputType, msg: &str) {
         config::ErrorOutputType::Short(color_config) => {
             Box::new(EmitterWriter::stderr(color_config, None, true, false))
         }
+        config::ErrorOutputType::Sarif => Box::new(SarifEmitter::stderr(None, None)),
     };
     let handler = errors::Handler::with_emitter(true, None, emitter);
     handler.emit(&MultiSpan::new(), msg, errors::Level::Warning);
@@ -9,6 +9,7 @@ use crate::traits::query::{
     CanonicalTypeOpProvePredicateGoal, CanonicalTypeOpSubtypeGoal,
 };
 use crate::ty::{self, ParamEnvAnd, Ty, TyCtxt};
+use crate::ty::layout::{LayoutDetails, LayoutError};
 use crate::ty::subst::SubstsRef;
 use crate::ty::query::queries;
 use crate::ty::query::Query;
@@ -51,20 +52,31 @@ pub(super) trait QueryAccessors<'tcx>: QueryConfig<'tcx> {
     ) -> Option<Fingerprint>;
 
     fn handle_cycle_error(tcx: TyCtxt<'_, 'tcx, '_>, error: CycleError<'tcx>) -> Self::Value;
+
+    // Whether the query has the `cache` modifier and `key` belongs to the local
+    // crate, and loading such a result. These are the defaults of
+    // `QueryDescription::cache_on_disk` and `try_load_from_disk`.
+    fn cache_on_disk_by_modifier(key: &Self::Key) -> bool;
+
+    fn load_cached_result(tcx: TyCtxt<'_, 'tcx, 'tcx>,
+                          id: SerializedDepNodeIndex)
+                          -> Option<Self::Value>;
 }
 
 pub(super) trait QueryDescription<'tcx>: QueryAccessors<'tcx> {
     fn describe(tcx: TyCtxt<'_, '_, '_>, key: Self::Key) -> Cow<'static, str>;
 
+    /// Whether the result for `key` is cached on disk. By default, this is the
+    /// case for the local results of queries with the `cache` modifier.
     #[inline]
-    fn cache_on_disk(_: TyCtxt<'_, 'tcx, 'tcx>, _: Self::Key) -> bool {
-        false
+    fn cache_on_disk(_: TyCtxt<'_, 'tcx, 'tcx>, key: Self::Key) -> bool {
+        Self::cache_on_disk_by_modifier(&key)
     }
 
-    fn try_load_from_disk(_: TyCtxt<'_, 'tcx, 'tcx>,
-                          _: SerializedDepNodeIndex)
+    fn try_load_from_disk(tcx: TyCtxt<'_, 'tcx, 'tcx>,
+                          id: SerializedDepNodeIndex)
                           -> Option<Self::Value> {
-        bug!("QueryDescription::load_from_disk() called for an unsupported query.")
+        Self::load_cached_result(tcx, id)
     }
 }
 
@@ -296,6 +308,19 @@ impl<'tcx> QueryDescription<'tcx> for queries::layout_raw<'tcx> {
                 -> Cow<'static, str> {
         format!("computing layout of `{}`", env.value).into()
     }
+
+    #[inline]
+    fn cache_on_disk(_: TyCtxt<'_, 'tcx, 'tcx>, _: Self::Key) -> bool {
+        true
+    }
+
+    fn try_load_from_disk<'a>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                              id: SerializedDepNodeIndex)
+                              -> Option<Self::Value> {
+        let layout: Option<Result<LayoutDetails, LayoutError<'tcx>>> =
+            tcx.queries.on_disk_cache.try_load_query_result(tcx, id);
+        layout.map(|layout| layout.map(|layout| tcx.intern_layout(layout)))
+    }
 }
 
 impl<'tcx> QueryDescription<'tcx> for queries::super_predicates_of<'tcx> {
@@ -361,6 +386,19 @@ impl<'tcx> QueryDescription<'tcx> for queries::mir_shims<'tcx> {
         format!("generating MIR shim for `{}`",
                 tcx.item_path_str(def.def_id())).into()
     }
+
+    #[inline]
+    fn cache_on_disk(_: TyCtxt<'_, 'tcx, 'tcx>, _: Self::Key) -> bool {
+        true
+    }
+
+    fn try_load_from_disk<'a>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                              id: SerializedDepNodeIndex)
+                              -> Option<Self::Value> {
+        let mir: Option<crate::mir::Mir<'tcx>> = tcx.queries.on_disk_cache
+                                               .try_load_query_result(tcx, id);
+        mir.map(|x| tcx.alloc_mir(x))
+    }
 }
 
 impl<'tcx> QueryDescription<'tcx> for queries::privacy_access_levels<'tcx> {
@@ -1022,13 +1060,6 @@ impl_disk_cacheable_query!(mir_borrowck, |tcx, def_id| {
     def_id.is_local() && tcx.is_closure(def_id)
 });
 
-impl_disk_cacheable_query!(unsafety_check_result, |_, def_id| def_id.is_local());
-impl_disk_cacheable_query!(borrowck, |_, def_id| def_id.is_local());
-impl_disk_cacheable_query!(mir_const_qualif, |_, def_id| def_id.is_local());
-impl_disk_cacheable_query!(check_match, |_, def_id| def_id.is_local());
 impl_disk_cacheable_query!(def_symbol_name, |_, _| true);
-impl_disk_cacheable_query!(type_of, |_, def_id| def_id.is_local());
-impl_disk_cacheable_query!(predicates_of, |_, def_id| def_id.is_local());
-impl_disk_cacheable_query!(used_trait_imports, |_, def_id| def_id.is_local());
 impl_disk_cacheable_query!(codegen_fn_attrs, |_, _| true);
 impl_disk_cacheable_query!(specialization_graph_of, |_, _| true);
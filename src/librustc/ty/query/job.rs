@@ -2,6 +2,7 @@
 
 use std::mem;
 use std::process;
+use std::time::Duration;
 use std::{fmt, ptr};
 
 use rustc_data_structures::fx::FxHashSet;
@@ -57,6 +58,10 @@ pub struct QueryJob<'tcx> {
     /// The parent query job which created this job and is implicitly waiting on it.
     pub parent: Option<Lrc<QueryJob<'tcx>>>,
 
+    /// The time spent executing the queries this job invoked, tracked for
+    /// `-Z query-stats`.
+    pub(super) child_time: Lock<Duration>,
+
     /// The latch that is used to wait on this job.
     #[cfg(parallel_compiler)]
     latch: QueryLatch<'tcx>,
@@ -68,6 +73,7 @@ impl<'tcx> QueryJob<'tcx> {
         QueryJob {
             info,
             parent,
+            child_time: Lock::new(Duration::new(0, 0)),
             #[cfg(parallel_compiler)]
             latch: QueryLatch::new(),
         }
@@ -97,13 +97,17 @@ pub use self::on_disk_cache::OnDiskCache;
 // (error) value if the query resulted in a query cycle.
 // Queries marked with `fatal_cycle` do not need the latter implementation,
 // as they will raise an fatal error on query cycles instead.
+//
+// Queries marked with `cache` store their results for keys of the local crate
+// in the incremental on-disk cache, so the result type must also implement
+// `Encodable` and `Decodable`. See `OnDiskCache` for other ways of caching.
 define_queries! { <'tcx>
     Other {
         /// Run analysis passes on the crate
         [] fn analysis: Analysis(CrateNum) -> Result<(), ErrorReported>,
 
         /// Records the type of every item.
-        [] fn type_of: TypeOfItem(DefId) -> Ty<'tcx>,
+        [cache] fn type_of: TypeOfItem(DefId) -> Ty<'tcx>,
 
         /// Maps from the `DefId` of an item (trait/struct/enum/fn) to its
         /// associated generics.
@@ -124,7 +128,7 @@ define_queries! { <'tcx>
         /// predicate gets in the way of some checks, which are intended
         /// to operate over only the actual where-clauses written by the
         /// user.)
-        [] fn predicates_of: PredicatesOfItem(DefId) -> Lrc<ty::GenericPredicates<'tcx>>,
+        [cache] fn predicates_of: PredicatesOfItem(DefId) -> Lrc<ty::GenericPredicates<'tcx>>,
 
         /// Maps from the `DefId` of an item (trait/struct/enum/fn) to the
         /// predicates (where-clauses) directly defined on it. This is
@@ -231,12 +235,12 @@ define_queries! { <'tcx>
         /// Set of all the `DefId`s in this crate that have MIR associated with
         /// them. This includes all the body owners, but also things like struct
         /// constructors.
-        [] fn mir_keys: mir_keys(CrateNum) -> Lrc<DefIdSet>,
+        [cache] fn mir_keys: mir_keys(CrateNum) -> Lrc<DefIdSet>,
 
         /// Maps DefId's that have an associated Mir to the result
         /// of the MIR qualify_consts pass. The actual meaning of
         /// the value isn't known except to the pass itself.
-        [] fn mir_const_qualif: MirConstQualif(DefId) -> (u8, Lrc<BitSet<mir::Local>>),
+        [cache] fn mir_const_qualif: MirConstQualif(DefId) -> (u8, Lrc<BitSet<mir::Local>>),
 
         /// Fetch the MIR for a given `DefId` right after it's built - this includes
         /// unreachable code.
@@ -257,13 +261,13 @@ define_queries! { <'tcx>
 
     TypeChecking {
         /// The result of unsafety-checking this `DefId`.
-        [] fn unsafety_check_result: UnsafetyCheckResult(DefId) -> mir::UnsafetyCheckResult,
+        [cache] fn unsafety_check_result: UnsafetyCheckResult(DefId) -> mir::UnsafetyCheckResult,
 
         /// HACK: when evaluated, this reports a "unsafe derive on repr(packed)" error
         [] fn unsafe_derive_on_repr_packed: UnsafeDeriveOnReprPacked(DefId) -> (),
 
         /// The signature of functions and closures.
-        [] fn fn_sig: FnSignature(DefId) -> ty::PolyFnSig<'tcx>,
+        [cache] fn fn_sig: FnSignature(DefId) -> ty::PolyFnSig<'tcx>,
     },
 
     Other {
@@ -300,17 +304,17 @@ define_queries! { <'tcx>
     },
 
     Other {
-        [] fn used_trait_imports: UsedTraitImports(DefId) -> Lrc<DefIdSet>,
+        [cache] fn used_trait_imports: UsedTraitImports(DefId) -> Lrc<DefIdSet>,
     },
 
     TypeChecking {
-        [] fn has_typeck_tables: HasTypeckTables(DefId) -> bool,
+        [cache] fn has_typeck_tables: HasTypeckTables(DefId) -> bool,
 
         [] fn coherent_trait: CoherenceCheckTrait(DefId) -> (),
     },
 
     BorrowChecking {
-        [] fn borrowck: BorrowCheck(DefId) -> Lrc<BorrowCheckResult>,
+        [cache] fn borrowck: BorrowCheck(DefId) -> Lrc<BorrowCheckResult>,
 
         /// Borrow checks the function body. If this is a closure, returns
         /// additional requirements that the closure's creator must verify.
@@ -347,7 +351,7 @@ define_queries! { <'tcx>
     },
 
     TypeChecking {
-        [] fn check_match: CheckMatch(DefId)
+        [cache] fn check_match: CheckMatch(DefId)
             -> Result<(), ErrorReported>,
 
         /// Performs part of the privacy check and computes "access levels".
@@ -410,7 +414,7 @@ define_queries! { <'tcx>
         [] fn trait_impls_of: TraitImpls(DefId) -> Lrc<ty::trait_def::TraitImpls>,
         [] fn specialization_graph_of: SpecializationGraph(DefId)
             -> Lrc<specialization_graph::Graph>,
-        [] fn is_object_safe: ObjectSafety(DefId) -> bool,
+        [cache] fn is_object_safe: ObjectSafety(DefId) -> bool,
 
         /// Gets the ParameterEnvironment for a given item; this environment
         /// will be in "user-facing" mode, meaning that it is suitabe for
@@ -422,16 +426,16 @@ define_queries! { <'tcx>
 
         /// Trait selection queries. These are best used by invoking `ty.is_copy_modulo_regions()`,
         /// `ty.is_copy()`, etc, since that will prune the environment where possible.
-        [] fn is_copy_raw: is_copy_dep_node(ty::ParamEnvAnd<'tcx, Ty<'tcx>>) -> bool,
-        [] fn is_sized_raw: is_sized_dep_node(ty::ParamEnvAnd<'tcx, Ty<'tcx>>) -> bool,
-        [] fn is_freeze_raw: is_freeze_dep_node(ty::ParamEnvAnd<'tcx, Ty<'tcx>>) -> bool,
+        [cache] fn is_copy_raw: is_copy_dep_node(ty::ParamEnvAnd<'tcx, Ty<'tcx>>) -> bool,
+        [cache] fn is_sized_raw: is_sized_dep_node(ty::ParamEnvAnd<'tcx, Ty<'tcx>>) -> bool,
+        [cache] fn is_freeze_raw: is_freeze_dep_node(ty::ParamEnvAnd<'tcx, Ty<'tcx>>) -> bool,
 
         // The cycle error here should be reported as an error by `check_representable`.
         // We consider the type as not needing drop in the meanwhile to avoid
         // further errors (done in impl Value for NeedsDrop).
         // Use `cycle_delay_bug` to delay the cycle error here to be emitted later
         // in case we accidentally otherwise don't emit an error.
-        [cycle_delay_bug] fn needs_drop_raw: needs_drop_dep_node(
+        [cycle_delay_bug, cache] fn needs_drop_raw: needs_drop_dep_node(
             ty::ParamEnvAnd<'tcx, Ty<'tcx>>
         ) -> NeedsDrop,
 
@@ -459,7 +463,7 @@ define_queries! { <'tcx>
     },
 
     TypeChecking {
-        [] fn specializes: specializes_node((DefId, DefId)) -> bool,
+        [cache] fn specializes: specializes_node((DefId, DefId)) -> bool,
         [] fn in_scope_traits_map: InScopeTraits(DefIndex)
             -> Option<Lrc<FxHashMap<ItemLocalId, Lrc<StableVec<TraitCandidate>>>>>,
     },
@@ -643,7 +647,7 @@ define_queries! { <'tcx>
 
         /// Do not call this query directly: invoke `infcx.predicate_may_hold()` or
         /// `infcx.predicate_must_hold()` instead.
-        [] fn evaluate_obligation: EvaluateObligation(
+        [cache] fn evaluate_obligation: EvaluateObligation(
             CanonicalPredicateGoal<'tcx>
         ) -> Result<traits::EvaluationResult, traits::OverflowError>,
 
@@ -17,9 +17,10 @@ use crate::util::common::time;
 use errors::Diagnostic;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::thin_vec::ThinVec;
-use rustc_data_structures::sync::{Lrc, Lock, HashMapExt, Once};
+use rustc_data_structures::sync::{Lrc, Lock, HashMapExt, Once, AtomicUsize};
 use rustc_data_structures::indexed_vec::{IndexVec, Idx};
 use std::mem;
+use std::sync::atomic::Ordering;
 use syntax::ast::NodeId;
 use syntax::source_map::{SourceMap, StableSourceFileId};
 use syntax_pos::{BytePos, Span, DUMMY_SP, SourceFile};
@@ -41,6 +42,22 @@ const TAG_INVALID_SPAN: u8 = 1;
 /// previous compilation session. This data will eventually include the results
 /// of a few selected queries (like `typeck_tables_of` and `mir_optimized`) and
 /// any diagnostics that have been emitted during a query.
+///
+/// To cache the results of another query, its result type has to implement
+/// `Encodable` and `Decodable`. If its results for keys of the local crate are
+/// to be cached, it only needs the `cache` modifier in `define_queries!`.
+/// Otherwise, or if its result is allocated in an arena:
+///
+/// - its `QueryDescription` has to override `cache_on_disk` and
+///   `try_load_from_disk`, e.g. with `impl_disk_cacheable_query!`,
+/// - and it has to be added to the queries encoded in `OnDiskCache::serialize`.
+///
+/// Either way, if its key is a `DefId`, it also has to be added to
+/// `impl_load_from_cache!` so that unused results are carried over to the next
+/// session.
+///
+/// Running with `-Z query-stats` shows which green results were recomputed
+/// because they were not cached, and how long that took.
 pub struct OnDiskCache<'sess> {
 
     // The complete cache data in serialized form.
@@ -54,7 +71,7 @@ pub struct OnDiskCache<'sess> {
     cnum_map: Once<IndexVec<CrateNum, Option<CrateNum>>>,
 
     source_map: &'sess SourceMap,
-    file_index_to_stable_id: FxHashMap<SourceFileIndex, StableSourceFileId>,
+    file_index_to_stable_id: FxHashMap<SourceFileIndex, EncodedSourceFileId>,
 
     // These two fields caches that are populated lazily during decoding.
     file_index_to_file: Lock<FxHashMap<SourceFileIndex, Lrc<SourceFile>>>,
@@ -69,12 +86,15 @@ pub struct OnDiskCache<'sess> {
     prev_diagnostics_index: FxHashMap<SerializedDepNodeIndex, AbsoluteBytePos>,
 
     alloc_decoding_state: AllocDecodingState,
+
+    // The number of bytes of cached data decoded so far, for `-Z query-stats`.
+    bytes_loaded: AtomicUsize,
 }
 
 // This type is used only for (de-)serialization.
 #[derive(RustcEncodable, RustcDecodable)]
 struct Footer {
-    file_index_to_stable_id: FxHashMap<SourceFileIndex, StableSourceFileId>,
+    file_index_to_stable_id: FxHashMap<SourceFileIndex, EncodedSourceFileId>,
     prev_cnums: Vec<(u32, String, CrateDisambiguator)>,
     query_result_index: EncodedQueryResultIndex,
     diagnostics_index: EncodedQueryResultIndex,
@@ -82,15 +102,39 @@ struct Footer {
     interpret_alloc_index: Vec<u32>,
 }
 
-type EncodedQueryResultIndex = Vec<(SerializedDepNodeIndex, AbsoluteBytePos)>;
+pub(super) type EncodedQueryResultIndex = Vec<(SerializedDepNodeIndex, AbsoluteBytePos)>;
 type EncodedDiagnosticsIndex = Vec<(SerializedDepNodeIndex, AbsoluteBytePos)>;
 type EncodedDiagnostics = Vec<Diagnostic>;
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, RustcEncodable, RustcDecodable)]
 struct SourceFileIndex(u32);
 
+// Identifies a `SourceFile` of the previous compilation session. Files of the
+// local crate whose paths were remapped with `--remap-path-prefix` also get an
+// id that does not depend on the directory they were compiled in, so that the
+// cache can be reused after being moved to a different checkout.
+#[derive(Copy, Clone, Debug, RustcEncodable, RustcDecodable)]
+struct EncodedSourceFileId {
+    stable_id: StableSourceFileId,
+    relocatable_id: Option<StableSourceFileId>,
+}
+
+impl EncodedSourceFileId {
+    fn new(file: &SourceFile) -> EncodedSourceFileId {
+        let is_local = CrateNum::from_u32(file.crate_of_origin) == LOCAL_CRATE;
+        EncodedSourceFileId {
+            stable_id: StableSourceFileId::new(file),
+            relocatable_id: if is_local && file.name_was_remapped {
+                Some(StableSourceFileId::new_relocatable(file))
+            } else {
+                None
+            },
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, RustcEncodable, RustcDecodable)]
-struct AbsoluteBytePos(u32);
+pub(super) struct AbsoluteBytePos(u32);
 
 impl AbsoluteBytePos {
     fn new(pos: usize) -> AbsoluteBytePos {
@@ -137,6 +181,7 @@ impl<'sess> OnDiskCache<'sess> {
             prev_diagnostics_index: footer.diagnostics_index.into_iter().collect(),
             synthetic_expansion_infos: Default::default(),
             alloc_decoding_state: AllocDecodingState::new(footer.interpret_alloc_index),
+            bytes_loaded: AtomicUsize::new(0),
         }
     }
 
@@ -153,6 +198,7 @@ impl<'sess> OnDiskCache<'sess> {
             prev_diagnostics_index: Default::default(),
             synthetic_expansion_infos: Default::default(),
             alloc_decoding_state: AllocDecodingState::new(Vec::new()),
+            bytes_loaded: AtomicUsize::new(0),
         }
     }
 
@@ -176,7 +222,7 @@ impl<'sess> OnDiskCache<'sess> {
                     let index = SourceFileIndex(index as u32);
                     let file_ptr: *const SourceFile = &**file as *const _;
                     file_to_file_index.insert(file_ptr, index);
-                    file_index_to_stable_id.insert(index, StableSourceFileId::new(&file));
+                    file_index_to_stable_id.insert(index, EncodedSourceFileId::new(&file));
                 }
 
                 (file_to_file_index, file_index_to_stable_id)
@@ -207,23 +253,19 @@ impl<'sess> OnDiskCache<'sess> {
                 let enc = &mut encoder;
                 let qri = &mut query_result_index;
 
-                encode_query_results::<type_of<'_>, _>(tcx, enc, qri)?;
+                super::encode_cached_query_results(tcx, enc, qri)?;
                 encode_query_results::<generics_of<'_>, _>(tcx, enc, qri)?;
-                encode_query_results::<predicates_of<'_>, _>(tcx, enc, qri)?;
-                encode_query_results::<used_trait_imports<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<typeck_tables_of<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<codegen_fulfill_obligation<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<optimized_mir<'_>, _>(tcx, enc, qri)?;
-                encode_query_results::<unsafety_check_result<'_>, _>(tcx, enc, qri)?;
-                encode_query_results::<borrowck<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<mir_borrowck<'_>, _>(tcx, enc, qri)?;
-                encode_query_results::<mir_const_qualif<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<def_symbol_name<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<const_is_rvalue_promotable_to_static<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<symbol_name<'_>, _>(tcx, enc, qri)?;
-                encode_query_results::<check_match<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<codegen_fn_attrs<'_>, _>(tcx, enc, qri)?;
                 encode_query_results::<specialization_graph_of<'_>, _>(tcx, enc, qri)?;
+                encode_query_results::<layout_raw<'_>, _>(tcx, enc, qri)?;
+                encode_query_results::<mir_shims<'_>, _>(tcx, enc, qri)?;
 
                 // const eval is special, it only encodes successfully evaluated constants
                 use crate::ty::query::QueryAccessors;
@@ -367,6 +409,11 @@ impl<'sess> OnDiskCache<'sess> {
                           "query result")
     }
 
+    /// Returns the number of bytes of cached data decoded so far.
+    pub fn bytes_loaded(&self) -> usize {
+        self.bytes_loaded.load(Ordering::Relaxed)
+    }
+
     /// Stores a diagnostic emitted during computation of an anonymous query.
     /// Since many anonymous queries can share the same `DepNode`, we aggregate
     /// them -- as opposed to regular queries where we assume that there is a
@@ -412,6 +459,8 @@ impl<'sess> OnDiskCache<'sess> {
 
         match decode_tagged(&mut decoder, dep_node_index) {
             Ok(value) => {
+                let len = decoder.position() - pos.to_usize();
+                self.bytes_loaded.fetch_add(len, Ordering::Relaxed);
                 Some(value)
             }
             Err(e) => {
@@ -465,7 +514,7 @@ struct CacheDecoder<'a, 'tcx: 'a, 'x> {
     cnum_map: &'x IndexVec<CrateNum, Option<CrateNum>>,
     synthetic_expansion_infos: &'x Lock<FxHashMap<AbsoluteBytePos, SyntaxContext>>,
     file_index_to_file: &'x Lock<FxHashMap<SourceFileIndex, Lrc<SourceFile>>>,
-    file_index_to_stable_id: &'x FxHashMap<SourceFileIndex, StableSourceFileId>,
+    file_index_to_stable_id: &'x FxHashMap<SourceFileIndex, EncodedSourceFileId>,
     alloc_decoding_session: AllocDecodingSession<'x>,
 }
 
@@ -479,8 +528,14 @@ impl<'a, 'tcx, 'x> CacheDecoder<'a, 'tcx, 'x> {
         } = *self;
 
         file_index_to_file.borrow_mut().entry(index).or_insert_with(|| {
-            let stable_id = file_index_to_stable_id[&index];
-            source_map.source_file_by_stable_id(stable_id)
+            let id = file_index_to_stable_id[&index];
+            source_map.source_file_by_stable_id(id.stable_id)
+                .or_else(|| {
+                    // The cache may have been produced in a different directory.
+                    id.relocatable_id.and_then(|relocatable_id| {
+                        source_map.local_source_file_by_relocatable_id(relocatable_id)
+                    })
+                })
                 .expect("Failed to lookup SourceFile in new context.")
         }).clone()
     }
@@ -746,7 +801,7 @@ for CacheDecoder<'a, 'tcx, 'x> {
 
 //- ENCODING -------------------------------------------------------------------
 
-struct CacheEncoder<'enc, 'a, 'tcx, E>
+pub(super) struct CacheEncoder<'enc, 'a, 'tcx, E>
     where E: 'enc + ty_codec::TyEncoder,
              'tcx: 'a,
 {
@@ -1071,10 +1126,11 @@ impl<'enc> SpecializedDecoder<IntEncodedWithFixedSize> for opaque::Decoder<'enc>
     }
 }
 
-fn encode_query_results<'enc, 'a, 'tcx, Q, E>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
-                                              encoder: &mut CacheEncoder<'enc, 'a, 'tcx, E>,
-                                              query_result_index: &mut EncodedQueryResultIndex)
-                                              -> Result<(), E::Error>
+pub(super) fn encode_query_results<'enc, 'a, 'tcx, Q, E>(
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    encoder: &mut CacheEncoder<'enc, 'a, 'tcx, E>,
+    query_result_index: &mut EncodedQueryResultIndex,
+) -> Result<(), E::Error>
     where Q: super::config::QueryDescription<'tcx>,
           E: 'enc + TyEncoder,
           Q::Value: Encodable,
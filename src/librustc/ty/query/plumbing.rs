@@ -24,6 +24,7 @@ use rustc_data_structures::cold_path;
 use std::mem;
 use std::ptr;
 use std::collections::hash_map::Entry;
+use std::time::{Duration, Instant};
 use syntax_pos::Span;
 use syntax::source_map::DUMMY_SP;
 
@@ -32,6 +33,47 @@ pub struct QueryCache<'tcx, D: QueryConfig<'tcx> + ?Sized> {
     pub(super) active: FxHashMap<D::Key, QueryResult<'tcx>>,
     #[cfg(debug_assertions)]
     pub(super) cache_hits: usize,
+    pub(super) stats: QueryExecutionStats,
+}
+
+/// How often a query was executed and how long that took, collected when
+/// `-Z query-stats` is passed.
+#[derive(Clone, Default)]
+pub(super) struct QueryExecutionStats {
+    /// The number of times the query was computed or loaded from the
+    /// incremental cache.
+    pub(super) executions: usize,
+    /// The number of times the result was found in the in-memory cache.
+    pub(super) cache_hits: usize,
+    /// The number of results loaded from the incremental cache, and their
+    /// encoded size in bytes.
+    pub(super) disk_loads: usize,
+    pub(super) bytes_loaded: usize,
+    /// The time spent executing the query, including the queries it invoked.
+    pub(super) total_time: Duration,
+    /// The time spent executing the query, excluding the queries it invoked.
+    pub(super) self_time: Duration,
+    /// The number of results that were recomputed although the query was
+    /// marked green, because they were not found in the incremental cache, and
+    /// the time spent recomputing them. This is the time that could be saved by
+    /// caching more results on disk.
+    pub(super) green_recomputations: usize,
+    pub(super) green_recompute_time: Duration,
+}
+
+impl QueryExecutionStats {
+    #[inline(never)]
+    #[cold]
+    fn record_execution(&mut self, job: &QueryJob<'_>, start: Instant) {
+        let total_time = start.elapsed();
+        let child_time = mem::replace(&mut *job.child_time.lock(), Duration::new(0, 0));
+        self.executions += 1;
+        self.total_time += total_time;
+        self.self_time += total_time.checked_sub(child_time).unwrap_or(Duration::new(0, 0));
+        if let Some(ref parent) = job.parent {
+            *parent.child_time.lock() += total_time;
+        }
+    }
 }
 
 pub(super) struct QueryValue<T> {
@@ -57,6 +99,7 @@ impl<'tcx, M: QueryConfig<'tcx>> Default for QueryCache<'tcx, M> {
             active: FxHashMap::default(),
             #[cfg(debug_assertions)]
             cache_hits: 0,
+            stats: QueryExecutionStats::default(),
         }
     }
 }
@@ -94,6 +137,8 @@ pub(super) struct JobOwner<'a, 'tcx: 'a, Q: QueryDescription<'tcx> + 'a> {
     cache: &'a Lock<QueryCache<'tcx, Q>>,
     key: Q::Key,
     job: Lrc<QueryJob<'tcx>>,
+    /// When the job was started, if `-Z query-stats` is enabled.
+    start: Option<Instant>,
 }
 
 impl<'a, 'tcx, Q: QueryDescription<'tcx>> JobOwner<'a, 'tcx, Q> {
@@ -121,6 +166,9 @@ impl<'a, 'tcx, Q: QueryDescription<'tcx>> JobOwner<'a, 'tcx, Q> {
                 {
                     lock.cache_hits += 1;
                 }
+                if unlikely!(tcx.sess.opts.debugging_opts.query_stats) {
+                    lock.stats.cache_hits += 1;
+                }
                 return TryGetJob::JobCompleted(result);
             }
             let job = match lock.active.entry((*key).clone()) {
@@ -149,10 +197,16 @@ impl<'a, 'tcx, Q: QueryDescription<'tcx>> JobOwner<'a, 'tcx, Q> {
                             query: Q::query(key.clone()),
                         };
                         let job = Lrc::new(QueryJob::new(info, parent));
+                        let start = if unlikely!(tcx.sess.opts.debugging_opts.query_stats) {
+                            Some(Instant::now())
+                        } else {
+                            None
+                        };
                         let owner = JobOwner {
                             cache,
                             job: job.clone(),
                             key: (*key).clone(),
+                            start,
                         };
                         entry.insert(QueryResult::Started(job));
                         TryGetJob::NotYetStarted(owner)
@@ -190,6 +244,7 @@ impl<'a, 'tcx, Q: QueryDescription<'tcx>> JobOwner<'a, 'tcx, Q> {
         let key = unsafe { ptr::read(&self.key) };
         let job = unsafe { ptr::read(&self.job) };
         let cache = self.cache;
+        let start = self.start;
 
         // Forget ourself so our destructor won't poison the query
         mem::forget(self);
@@ -199,6 +254,9 @@ impl<'a, 'tcx, Q: QueryDescription<'tcx>> JobOwner<'a, 'tcx, Q> {
             let mut lock = cache.borrow_mut();
             lock.active.remove(&key);
             lock.results.insert(key, value);
+            if let Some(start) = start {
+                lock.stats.record_execution(&job, start);
+            }
         }
 
         job.signal_complete();
@@ -458,9 +516,17 @@ impl<'a, 'gcx, 'tcx> TyCtxt<'a, 'gcx, 'tcx> {
         let result = if Q::cache_on_disk(self.global_tcx(), key.clone()) &&
                         self.sess.opts.debugging_opts.incremental_queries {
             self.sess.profiler(|p| p.incremental_load_result_start(Q::NAME));
+            let bytes_loaded = self.queries.on_disk_cache.bytes_loaded();
             let result = Q::try_load_from_disk(self.global_tcx(), prev_dep_node_index);
             self.sess.profiler(|p| p.incremental_load_result_end(Q::NAME));
 
+            if unlikely!(self.sess.opts.debugging_opts.query_stats) && result.is_some() {
+                let bytes_loaded = self.queries.on_disk_cache.bytes_loaded() - bytes_loaded;
+                let mut cache = Q::query_cache(self).borrow_mut();
+                cache.stats.disk_loads += 1;
+                cache.stats.bytes_loaded += bytes_loaded;
+            }
+
             // We always expect to find a cached result for things that
             // can be forced from DepNode.
             debug_assert!(!dep_node.kind.can_reconstruct_query_key() ||
@@ -483,6 +549,11 @@ impl<'a, 'gcx, 'tcx> TyCtxt<'a, 'gcx, 'tcx> {
             // recompute.
 
             self.sess.profiler(|p| p.start_query(Q::NAME, Q::CATEGORY));
+            let start = if unlikely!(self.sess.opts.debugging_opts.query_stats) {
+                Some(Instant::now())
+            } else {
+                None
+            };
 
             // The dep-graph for this computation is already in
             // place
@@ -490,6 +561,12 @@ impl<'a, 'gcx, 'tcx> TyCtxt<'a, 'gcx, 'tcx> {
                 Q::compute(self, key)
             });
 
+            if let Some(start) = start {
+                let mut cache = Q::query_cache(self).borrow_mut();
+                cache.stats.green_recomputations += 1;
+                cache.stats.green_recompute_time += start.elapsed();
+            }
+
             self.sess.profiler(|p| p.end_query(Q::NAME, Q::CATEGORY));
             result
         };
@@ -676,6 +753,40 @@ macro_rules! hash_result {
     };
 }
 
+macro_rules! cache_on_disk {
+    ([][$key:expr]) => {{
+        false
+    }};
+    ([cache$(, $modifiers:ident)*][$key:expr]) => {{
+        $key.query_crate() == LOCAL_CRATE
+    }};
+    ([$other:ident$(, $modifiers:ident)*][$($args:tt)*]) => {
+        cache_on_disk!([$($modifiers),*][$($args)*])
+    };
+}
+
+macro_rules! load_cached_result {
+    ([][$tcx:expr, $id:expr]) => {{
+        bug!("QueryDescription::load_from_disk() called for an unsupported query.")
+    }};
+    ([cache$(, $modifiers:ident)*][$tcx:expr, $id:expr]) => {{
+        $tcx.queries.on_disk_cache.try_load_query_result($tcx, $id)
+    }};
+    ([$other:ident$(, $modifiers:ident)*][$($args:tt)*]) => {
+        load_cached_result!([$($modifiers),*][$($args)*])
+    };
+}
+
+macro_rules! encode_cached_results {
+    ([][$($args:tt)*]) => {};
+    ([cache$(, $modifiers:ident)*][$name:ident, $tcx:expr, $encoder:expr, $index:expr]) => {
+        on_disk_cache::encode_query_results::<queries::$name<'_>, _>($tcx, $encoder, $index)?;
+    };
+    ([$other:ident$(, $modifiers:ident)*][$($args:tt)*]) => {
+        encode_cached_results!([$($modifiers),*][$($args)*])
+    };
+}
+
 macro_rules! define_queries {
     (<$tcx:tt> $($category:tt {
         $($(#[$attr:meta])* [$($modifiers:tt)*] fn $name:ident: $node:ident($K:ty) -> $V:ty,)*
@@ -692,6 +803,7 @@ macro_rules! define_queries_inner {
         [$($modifiers:tt)*] fn $name:ident: $node:ident($K:ty) -> $V:ty,)*) => {
 
         use std::mem;
+        use std::time::Duration;
         #[cfg(parallel_compiler)]
         use ty::query::job::QueryResult;
         use rustc_data_structures::sync::Lock;
@@ -701,7 +813,11 @@ macro_rules! define_queries_inner {
             rustc_data_structures::stable_hasher::StableHasher,
             ich::StableHashingContext
         };
+        use crate::util::common::{duration_to_secs_str, to_readable_str};
         use crate::util::profiling::ProfileCategory;
+        use crate::dep_graph::SerializedDepNodeIndex;
+        use crate::hir::def_id::LOCAL_CRATE;
+        use crate::ty::codec::TyEncoder;
 
         define_queries_struct! {
             tcx: $tcx,
@@ -767,6 +883,7 @@ macro_rules! define_queries_inner {
                     value_size: usize,
                     value_type: &'static str,
                     entry_count: usize,
+                    execution: QueryExecutionStats,
                 }
 
                 fn stats<'tcx, Q: QueryConfig<'tcx>>(
@@ -784,6 +901,7 @@ macro_rules! define_queries_inner {
                         value_size: mem::size_of::<Q::Value>(),
                         value_type: unsafe { type_name::<Q::Value>() },
                         entry_count: map.results.len(),
+                        execution: map.stats.clone(),
                     }
                 }
 
@@ -794,6 +912,65 @@ macro_rules! define_queries_inner {
                     ));
                 )*
 
+                let mut query_times: Vec<_> = queries.iter()
+                    .filter(|q| q.execution.executions + q.execution.cache_hits > 0)
+                    .collect();
+                query_times.sort_by_key(|q| q.execution.self_time);
+                let name_width = query_times.iter().map(|q| q.name.len()).max().unwrap_or(0);
+                let self_time: Duration = query_times.iter().map(|q| q.execution.self_time).sum();
+                println!("\nQuery execution times (total self time: {}s):",
+                         duration_to_secs_str(self_time));
+                println!(
+                    "   {:<width$} {:>10} {:>10} {:>10} {:>10} {:>10} {:>12}",
+                    "query",
+                    "executions",
+                    "cache hits",
+                    "disk loads",
+                    "total (s)",
+                    "self (s)",
+                    "loaded (B)",
+                    width = name_width
+                );
+                for q in query_times.iter().rev() {
+                    println!(
+                        "   {:<width$} {:>10} {:>10} {:>10} {:>10} {:>10} {:>12}",
+                        q.name,
+                        q.execution.executions,
+                        q.execution.cache_hits,
+                        q.execution.disk_loads,
+                        duration_to_secs_str(q.execution.total_time),
+                        duration_to_secs_str(q.execution.self_time),
+                        to_readable_str(q.execution.bytes_loaded),
+                        width = name_width
+                    );
+                }
+
+                let mut recomputed: Vec<_> = queries.iter()
+                    .filter(|q| q.execution.green_recomputations > 0)
+                    .collect();
+                if !recomputed.is_empty() {
+                    recomputed.sort_by_key(|q| q.execution.green_recompute_time);
+                    let name_width = recomputed.iter().map(|q| q.name.len()).max().unwrap_or(0);
+                    println!("\nGreen results recomputed instead of loaded from the \
+                              incremental cache:");
+                    println!(
+                        "   {:<width$} {:>10} {:>10}",
+                        "query",
+                        "count",
+                        "total (s)",
+                        width = name_width
+                    );
+                    for q in recomputed.iter().rev() {
+                        println!(
+                            "   {:<width$} {:>10} {:>10}",
+                            q.name,
+                            q.execution.green_recomputations,
+                            duration_to_secs_str(q.execution.green_recompute_time),
+                            width = name_width
+                        );
+                    }
+                }
+
                 if cfg!(debug_assertions) {
                     let hits: usize = queries.iter().map(|s| s.cache_hits).sum();
                     let results: usize = queries.iter().map(|s| s.entry_count).sum();
@@ -977,8 +1154,37 @@ macro_rules! define_queries_inner {
             ) -> Self::Value {
                 handle_cycle_error!([$($modifiers)*][tcx, error])
             }
+
+            #[allow(unused)]
+            #[inline]
+            fn cache_on_disk_by_modifier(key: &Self::Key) -> bool {
+                cache_on_disk!([$($modifiers)*][key])
+            }
+
+            #[allow(unused)]
+            fn load_cached_result(
+                tcx: TyCtxt<'_, $tcx, $tcx>,
+                id: SerializedDepNodeIndex
+            ) -> Option<Self::Value> {
+                load_cached_result!([$($modifiers)*][tcx, id])
+            }
         })*
 
+        /// Encodes the results of the queries with the `cache` modifier into
+        /// the on-disk cache.
+        fn encode_cached_query_results<'enc, 'a, $tcx, E>(
+            tcx: TyCtxt<'a, $tcx, $tcx>,
+            encoder: &mut on_disk_cache::CacheEncoder<'enc, 'a, $tcx, E>,
+            query_result_index: &mut on_disk_cache::EncodedQueryResultIndex,
+        ) -> Result<(), E::Error>
+            where E: 'enc + TyEncoder
+        {
+            $(encode_cached_results!(
+                [$($modifiers)*][$name, tcx, encoder, query_result_index]
+            );)*
+            Ok(())
+        }
+
         #[derive(Copy, Clone)]
         pub struct TyCtxtEnsure<'a, 'gcx: 'a+'tcx, 'tcx: 'a> {
             pub tcx: TyCtxt<'a, 'gcx, 'tcx>,
@@ -1494,7 +1700,10 @@ impl_load_from_cache!(
     SymbolName => def_symbol_name,
     ConstIsRvaluePromotableToStatic => const_is_rvalue_promotable_to_static,
     CheckMatch => check_match,
+    HasTypeckTables => has_typeck_tables,
+    ObjectSafety => is_object_safe,
     TypeOfItem => type_of,
+    FnSignature => fn_sig,
     GenericsOfItem => generics_of,
     PredicatesOfItem => predicates_of,
     UsedTraitImports => used_trait_imports,
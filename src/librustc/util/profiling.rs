@@ -1,9 +1,28 @@
+//! The self-profiler, enabled with `-Z self-profile`.
+//!
+//! Every thread records its events into its own buffer, found through a
+//! thread-local, so threads do not contend on a lock while recording. The
+//! buffers are memory-mapped temporary files holding fixed-size binary
+//! records, which are removed once the profiler is dropped. At the end of the
+//! session they are written out as the event stream described in
+//! `write_event_stream`, and can additionally be converted to a Chrome trace
+//! or to folded stacks.
+
+use std::cell::RefCell;
 use std::fs;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 use std::mem;
+use std::path::Path;
 use std::process;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread::ThreadId;
-use std::time::{Duration, Instant, SystemTime};
+use std::time::{Instant, SystemTime};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use memmap::MmapMut;
+use parking_lot::Mutex;
+use tempfile::NamedTempFile;
 
 use crate::session::config::Options;
 
@@ -20,6 +39,31 @@ pub enum ProfileCategory {
     Other,
 }
 
+impl ProfileCategory {
+    fn name(&self) -> &'static str {
+        match *self {
+            ProfileCategory::Parsing => "Parsing",
+            ProfileCategory::Expansion => "Expansion",
+            ProfileCategory::TypeChecking => "TypeChecking",
+            ProfileCategory::BorrowChecking => "BorrowChecking",
+            ProfileCategory::Codegen => "Codegen",
+            ProfileCategory::Linking => "Linking",
+            ProfileCategory::Other => "Other",
+        }
+    }
+}
+
+/// All categories, indexed by their value in the event stream.
+const CATEGORIES: [ProfileCategory; 7] = [
+    ProfileCategory::Parsing,
+    ProfileCategory::Expansion,
+    ProfileCategory::TypeChecking,
+    ProfileCategory::BorrowChecking,
+    ProfileCategory::Codegen,
+    ProfileCategory::Linking,
+    ProfileCategory::Other,
+];
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ProfilerEvent {
     QueryStart { query_name: &'static str, category: ProfileCategory, time: u64 },
@@ -57,8 +101,127 @@ fn thread_id_to_u64(tid: ThreadId) -> u64 {
     unsafe { mem::transmute::<ThreadId, u64>(tid) }
 }
 
+/// The kinds of events in the event stream.
+mod event_kind {
+    pub const QUERY_START: u8 = 0;
+    pub const QUERY_END: u8 = 1;
+    pub const GENERIC_ACTIVITY_START: u8 = 2;
+    pub const GENERIC_ACTIVITY_END: u8 = 3;
+    pub const INCREMENTAL_LOAD_RESULT_START: u8 = 4;
+    pub const INCREMENTAL_LOAD_RESULT_END: u8 = 5;
+    pub const QUERY_CACHE_HIT: u8 = 6;
+    pub const QUERY_COUNT: u8 = 7;
+    pub const QUERY_BLOCKED_START: u8 = 8;
+    pub const QUERY_BLOCKED_END: u8 = 9;
+}
+
+const EVENTS_MAGIC: &[u8; 8] = b"RSPEVT\0\0";
+const INDEX_MAGIC: &[u8; 8] = b"RSPIDX\0\0";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 24;
+const EVENT_SIZE: usize = 24;
+/// The initial size of a thread's buffer, which is doubled whenever it fills
+/// up.
+const INITIAL_BUFFER_SIZE: usize = 1 << 22;
+
+/// The events of one thread, in a memory-mapped temporary file.
+struct EventBuffer {
+    // The map is declared first so that it is unmapped before the file is
+    // removed, which would fail on Windows otherwise.
+    map: MmapMut,
+    file: NamedTempFile,
+    /// The number of bytes used, including the header.
+    len: usize,
+}
+
+impl EventBuffer {
+    fn new() -> io::Result<EventBuffer> {
+        let file = tempfile::Builder::new()
+            .prefix("rustc-self-profile")
+            .suffix(".events.tmp")
+            .tempfile()?;
+        file.as_file().set_len(INITIAL_BUFFER_SIZE as u64)?;
+        let mut map = unsafe { MmapMut::map_mut(file.as_file())? };
+        map[..8].copy_from_slice(EVENTS_MAGIC);
+        LittleEndian::write_u32(&mut map[8..12], FORMAT_VERSION);
+
+        Ok(EventBuffer { map, file, len: HEADER_SIZE })
+    }
+
+    #[inline]
+    fn push(&mut self, event: &[u8; EVENT_SIZE]) -> io::Result<()> {
+        if self.len + EVENT_SIZE > self.map.len() {
+            self.grow()?;
+        }
+        self.map[self.len..self.len + EVENT_SIZE].copy_from_slice(event);
+        self.len += EVENT_SIZE;
+        Ok(())
+    }
+
+    #[cold]
+    fn grow(&mut self) -> io::Result<()> {
+        self.file.as_file().set_len(self.map.len() as u64 * 2)?;
+        self.map = unsafe { MmapMut::map_mut(self.file.as_file())? };
+        Ok(())
+    }
+
+    fn events(&self) -> impl Iterator<Item = &[u8]> {
+        self.map[HEADER_SIZE..self.len].chunks(EVENT_SIZE)
+    }
+
+    /// Writes the event count and copies the header and the events to `path`.
+    /// The temporary file may be on another file system, so it is not moved.
+    fn finish(&mut self, path: &Path) -> io::Result<()> {
+        let count = ((self.len - HEADER_SIZE) / EVENT_SIZE) as u64;
+        LittleEndian::write_u64(&mut self.map[16..24], count);
+        fs::write(path, &self.map[..self.len])
+    }
+}
+
+/// The interned query and activity names.
+#[derive(Default)]
+struct StringTable {
+    /// The names, indexed by their string id.
+    strings: Vec<&'static str>,
+    ids: FxHashMap<&'static str, u32>,
+}
+
+impl StringTable {
+    fn intern(&mut self, s: &'static str) -> u32 {
+        let strings = &mut self.strings;
+        *self.ids.entry(s).or_insert_with(|| {
+            strings.push(s);
+            (strings.len() - 1) as u32
+        })
+    }
+}
+
+/// The state of the current thread for the profiler with the id `profiler`.
+struct LocalState {
+    profiler: usize,
+    /// The buffer of this thread, which is owned by the profiler. It is dangling
+    /// if creating it failed or once the profiler wrote out its events.
+    buffer: Weak<Mutex<EventBuffer>>,
+    /// The ids of the strings this thread used, so that the string table only
+    /// has to be locked the first time a thread uses a string.
+    string_ids: FxHashMap<&'static str, u32>,
+}
+
+thread_local! {
+    static LOCAL_STATE: RefCell<Option<LocalState>> = RefCell::new(None);
+}
+
+/// Tells the profilers of sessions that run one after another on a thread
+/// apart.
+static NEXT_PROFILER_ID: AtomicUsize = AtomicUsize::new(0);
+
 pub struct SelfProfiler {
-    events: FxHashMap<ThreadId, Vec<ProfilerEvent>>,
+    id: usize,
+    /// The buffers of the threads that recorded events, with their thread ids.
+    buffers: Mutex<Vec<(u64, Arc<Mutex<EventBuffer>>)>>,
+    strings: Mutex<StringTable>,
+    /// The first error that occurred while recording events.
+    error: Mutex<Option<io::Error>>,
     start_time: SystemTime,
     start_instant: Instant,
 }
@@ -66,16 +229,23 @@ pub struct SelfProfiler {
 impl SelfProfiler {
     pub fn new() -> SelfProfiler {
         let profiler = SelfProfiler {
-            events: Default::default(),
+            id: NEXT_PROFILER_ID.fetch_add(1, Ordering::Relaxed),
+            buffers: Default::default(),
+            strings: Default::default(),
+            error: Default::default(),
             start_time: SystemTime::now(),
             start_instant: Instant::now(),
         };
 
+        // Create the buffer of the main thread up front, so that this does not
+        // count towards the first activity.
+        LOCAL_STATE.with(|state| *state.borrow_mut() = Some(profiler.local_state()));
+
         profiler
     }
 
     #[inline]
-    pub fn start_activity(&mut self, category: ProfileCategory) {
+    pub fn start_activity(&self, category: ProfileCategory) {
         self.record(ProfilerEvent::GenericActivityStart {
             category,
             time: self.get_time_from_start(),
@@ -83,7 +253,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn end_activity(&mut self, category: ProfileCategory) {
+    pub fn end_activity(&self, category: ProfileCategory) {
         self.record(ProfilerEvent::GenericActivityEnd {
             category,
             time: self.get_time_from_start(),
@@ -92,7 +262,7 @@ impl SelfProfiler {
 
     #[inline]
     pub fn record_computed_queries(
-        &mut self,
+        &self,
         query_name: &'static str,
         category: ProfileCategory,
         count: usize)
@@ -106,7 +276,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn record_query_hit(&mut self, query_name: &'static str, category: ProfileCategory) {
+    pub fn record_query_hit(&self, query_name: &'static str, category: ProfileCategory) {
         self.record(ProfilerEvent::QueryCacheHit {
             query_name,
             category,
@@ -115,7 +285,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn start_query(&mut self, query_name: &'static str, category: ProfileCategory) {
+    pub fn start_query(&self, query_name: &'static str, category: ProfileCategory) {
         self.record(ProfilerEvent::QueryStart {
             query_name,
             category,
@@ -124,7 +294,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn end_query(&mut self, query_name: &'static str, category: ProfileCategory) {
+    pub fn end_query(&self, query_name: &'static str, category: ProfileCategory) {
         self.record(ProfilerEvent::QueryEnd {
             query_name,
             category,
@@ -133,7 +303,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn incremental_load_result_start(&mut self, query_name: &'static str) {
+    pub fn incremental_load_result_start(&self, query_name: &'static str) {
         self.record(ProfilerEvent::IncrementalLoadResultStart {
             query_name,
             time: self.get_time_from_start(),
@@ -141,7 +311,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn incremental_load_result_end(&mut self, query_name: &'static str) {
+    pub fn incremental_load_result_end(&self, query_name: &'static str) {
         self.record(ProfilerEvent::IncrementalLoadResultEnd {
             query_name,
             time: self.get_time_from_start(),
@@ -149,7 +319,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn query_blocked_start(&mut self, query_name: &'static str, category: ProfileCategory) {
+    pub fn query_blocked_start(&self, query_name: &'static str, category: ProfileCategory) {
         self.record(ProfilerEvent::QueryBlockedStart {
             query_name,
             category,
@@ -158,7 +328,7 @@ impl SelfProfiler {
     }
 
     #[inline]
-    pub fn query_blocked_end(&mut self, query_name: &'static str, category: ProfileCategory) {
+    pub fn query_blocked_end(&self, query_name: &'static str, category: ProfileCategory) {
         self.record(ProfilerEvent::QueryBlockedEnd {
             query_name,
             category,
@@ -167,11 +337,110 @@ impl SelfProfiler {
     }
 
     #[inline]
-    fn record(&mut self, event: ProfilerEvent) {
-        let thread_id = std::thread::current().id();
-        let events = self.events.entry(thread_id).or_default();
+    fn record(&self, event: ProfilerEvent) {
+        LOCAL_STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            if state.as_ref().map(|state| state.profiler) != Some(self.id) {
+                *state = Some(self.local_state());
+            }
+            let state = state.as_mut().unwrap();
+
+            let buffer = match state.buffer.upgrade() {
+                Some(buffer) => buffer,
+                None => return,
+            };
+            let event = self.encode(&event, &mut state.string_ids);
+            let result = buffer.lock().push(&event);
+            if let Err(e) = result {
+                self.error.lock().get_or_insert(e);
+            }
+        })
+    }
 
-        events.push(event);
+    /// Creates the buffer of the current thread and its state.
+    #[cold]
+    fn local_state(&self) -> LocalState {
+        let buffer = match EventBuffer::new() {
+            Ok(buffer) => {
+                let buffer = Arc::new(Mutex::new(buffer));
+                let thread_id = thread_id_to_u64(std::thread::current().id());
+                self.buffers.lock().push((thread_id, buffer.clone()));
+                Arc::downgrade(&buffer)
+            }
+            Err(e) => {
+                self.error.lock().get_or_insert(e);
+                Weak::new()
+            }
+        };
+        LocalState { profiler: self.id, buffer, string_ids: Default::default() }
+    }
+
+    #[inline]
+    fn string_id(&self, string_ids: &mut FxHashMap<&'static str, u32>, s: &'static str) -> u32 {
+        *string_ids.entry(s).or_insert_with(|| self.strings.lock().intern(s))
+    }
+
+    fn encode(&self,
+              event: &ProfilerEvent,
+              string_ids: &mut FxHashMap<&'static str, u32>)
+              -> [u8; EVENT_SIZE] {
+        use self::ProfilerEvent::*;
+        use self::event_kind::*;
+
+        let other = ProfileCategory::Other;
+        let (kind, category, name, count) = match *event {
+            QueryStart { query_name, category, .. } => (QUERY_START, category, query_name, 0),
+            QueryEnd { query_name, category, .. } => (QUERY_END, category, query_name, 0),
+            GenericActivityStart { category, .. } =>
+                (GENERIC_ACTIVITY_START, category, category.name(), 0),
+            GenericActivityEnd { category, .. } =>
+                (GENERIC_ACTIVITY_END, category, category.name(), 0),
+            IncrementalLoadResultStart { query_name, .. } =>
+                (INCREMENTAL_LOAD_RESULT_START, other, query_name, 0),
+            IncrementalLoadResultEnd { query_name, .. } =>
+                (INCREMENTAL_LOAD_RESULT_END, other, query_name, 0),
+            QueryCacheHit { query_name, category, .. } =>
+                (QUERY_CACHE_HIT, category, query_name, 0),
+            QueryCount { query_name, category, count, .. } =>
+                (QUERY_COUNT, category, query_name, count as u64),
+            QueryBlockedStart { query_name, category, .. } =>
+                (QUERY_BLOCKED_START, category, query_name, 0),
+            QueryBlockedEnd { query_name, category, .. } =>
+                (QUERY_BLOCKED_END, category, query_name, 0),
+        };
+
+        let mut raw = [0; EVENT_SIZE];
+        raw[0] = kind;
+        raw[1] = category as u8;
+        LittleEndian::write_u32(&mut raw[4..8], self.string_id(string_ids, name));
+        LittleEndian::write_u64(&mut raw[8..16], event.timestamp());
+        LittleEndian::write_u64(&mut raw[16..24], count);
+        raw
+    }
+
+    fn decode(strings: &[&'static str], raw: &[u8]) -> ProfilerEvent {
+        use self::ProfilerEvent::*;
+        use self::event_kind::*;
+
+        let category = CATEGORIES[raw[1] as usize];
+        let query_name = strings[LittleEndian::read_u32(&raw[4..8]) as usize];
+        let time = LittleEndian::read_u64(&raw[8..16]);
+        match raw[0] {
+            QUERY_START => QueryStart { query_name, category, time },
+            QUERY_END => QueryEnd { query_name, category, time },
+            GENERIC_ACTIVITY_START => GenericActivityStart { category, time },
+            GENERIC_ACTIVITY_END => GenericActivityEnd { category, time },
+            INCREMENTAL_LOAD_RESULT_START => IncrementalLoadResultStart { query_name, time },
+            INCREMENTAL_LOAD_RESULT_END => IncrementalLoadResultEnd { query_name, time },
+            QUERY_CACHE_HIT => QueryCacheHit { query_name, category, time },
+            QUERY_COUNT => {
+                let count = LittleEndian::read_u64(&raw[16..24]) as usize;
+                QueryCount { query_name, category, count, time }
+            }
+            QUERY_BLOCKED_START => QueryBlockedStart { query_name, category, time },
+            QUERY_BLOCKED_END => QueryBlockedEnd { query_name, category, time },
+            kind => bug!("invalid self-profile event kind {}", kind),
+        }
     }
 
     #[inline]
@@ -180,249 +449,264 @@ impl SelfProfiler {
         duration.as_nanos() as u64
     }
 
-    pub fn dump_raw_events(&self, opts: &Options) {
-        use self::ProfilerEvent::*;
+    /// Returns the recorded events grouped by thread, ordered by thread id so
+    /// that the output is deterministic.
+    fn events_by_thread(&self) -> Vec<(u64, Vec<ProfilerEvent>)> {
+        let strings = self.strings.lock();
+        let mut threads: Vec<_> = self.buffers
+            .lock()
+            .iter()
+            .map(|&(tid, ref buffer)| {
+                let buffer = buffer.lock();
+                let events = buffer.events().map(|raw| Self::decode(&strings.strings, raw));
+                (tid, events.collect())
+            })
+            .collect();
+        threads.sort_by_key(|&(tid, _)| tid);
+        threads
+    }
 
-        let pid = process::id();
+    /// Finishes the event stream, which consists of the per-thread files
+    /// `<crate>-<pid>-<thread id>.events` and `<crate>-<pid>.profile_index`.
+    /// All integers are little-endian.
+    ///
+    /// An `.events` file starts with a 24 byte header:
+    ///
+    /// - magic: `RSPEVT\0\0`
+    /// - format version: `u32`
+    /// - padding: `u32`
+    /// - number of events: `u64`
+    ///
+    /// followed by the events, 24 bytes each:
+    ///
+    /// - kind: `u8`, see `event_kind`
+    /// - category: `u8`, the index in `CATEGORIES`
+    /// - padding: `u16`
+    /// - string id of the query, or of the category for generic activities: `u32`
+    /// - time since the start of the session in nanoseconds: `u64`
+    /// - the count of `QUERY_COUNT` events, 0 otherwise: `u64`
+    ///
+    /// The `.profile_index` file holds:
+    ///
+    /// - magic: `RSPIDX\0\0`
+    /// - format version: `u32`
+    /// - start of the session in nanoseconds since the Unix epoch: `u64`
+    /// - crate name: string
+    /// - number of strings: `u32`, followed by the strings in id order
+    /// - number of threads: `u32`, followed by their ids as `u64`s
+    ///
+    /// where a string is its length in bytes as a `u32` followed by its UTF-8
+    /// bytes. `src/tools/self-profile-summary` decodes these files.
+    ///
+    /// Events recorded after this are dropped.
+    pub fn write_event_stream(&self, opts: &Options) -> io::Result<()> {
+        let prefix = format!("{}-{}", opts.crate_name.clone().unwrap_or_default(), process::id());
+
+        let mut buffers = mem::replace(&mut *self.buffers.lock(), Vec::new());
+        buffers.sort_by_key(|&(tid, _)| tid);
+        let threads: Vec<u64> = buffers.iter().map(|&(tid, _)| tid).collect();
+        for (tid, buffer) in buffers {
+            buffer.lock().finish(Path::new(&format!("{}-{}.events", prefix, tid)))?;
+        }
 
-        let filename =
-            format!("{}.profile_events.json", opts.crate_name.clone().unwrap_or_default());
+        let start_time = self.start_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+
+        let filename = format!("{}.profile_index", prefix);
+        let mut file = BufWriter::new(fs::File::create(filename)?);
+        file.write_all(INDEX_MAGIC)?;
+        file.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+        file.write_u64::<LittleEndian>(start_time)?;
+        write_string(&mut file, &opts.crate_name.clone().unwrap_or_default())?;
+        let strings = self.strings.lock();
+        file.write_u32::<LittleEndian>(strings.strings.len() as u32)?;
+        for s in &strings.strings {
+            write_string(&mut file, s)?;
+        }
+        file.write_u32::<LittleEndian>(threads.len() as u32)?;
+        for tid in threads {
+            file.write_u64::<LittleEndian>(tid)?;
+        }
+        file.flush()?;
 
-        let mut file = BufWriter::new(fs::File::create(filename).unwrap());
+        match self.error.lock().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
 
-        let threads: Vec<_> =
-            self.events
-                .keys()
-                .into_iter()
-                .map(|tid| format!("{}", thread_id_to_u64(*tid)))
-                .collect();
+    /// Writes the events in the Chrome trace-event format to
+    /// `<crate>.profile_events.json`. The file can be opened with
+    /// `chrome://tracing` or the Perfetto UI, which show every thread on its
+    /// own track.
+    pub fn dump_raw_events(&self, opts: &Options) {
+        let pid = process::id();
+        let crate_name = opts.crate_name.clone().unwrap_or_default();
+
+        let filename = format!("{}.profile_events.json", crate_name);
+        let mut file = BufWriter::new(fs::File::create(filename).unwrap());
 
+        let start_time_us = self.start_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
         write!(file,
             "{{\
-                \"processes\": {{\
-                    \"{}\": {{\
-                        \"threads\": [{}],\
-                        \"crate_name\": \"{}\",\
-                        \"opt_level\": \"{:?}\",\
-                        \"incremental\": {}\
-                    }}\
+                \"displayTimeUnit\": \"ns\",\
+                \"otherData\": {{\
+                    \"crate_name\": \"{}\",\
+                    \"opt_level\": \"{:?}\",\
+                    \"incremental\": {},\
+                    \"start_time_us\": {}\
                 }},\
-                \"events\": [\
-             ",
-            pid,
-            threads.join(","),
-            opts.crate_name.clone().unwrap_or_default(),
+                \"traceEvents\": [\n",
+            crate_name,
             opts.optimize,
-            if opts.incremental.is_some() { "true" } else { "false" },
+            opts.incremental.is_some(),
+            start_time_us,
         ).unwrap();
 
-        let mut is_first = true;
-        for (thread_id, events) in &self.events {
-            let thread_id = thread_id_to_u64(*thread_id);
+        write!(file,
+            "{{\"ph\": \"M\", \"name\": \"process_name\", \"pid\": {}, \
+               \"args\": {{\"name\": \"rustc {}\"}}}}",
+            pid,
+            crate_name,
+        ).unwrap();
 
-            for event in events {
-                if is_first {
-                    is_first = false;
-                } else {
-                    writeln!(file, ",").unwrap();
+        for (tid, events) in self.events_by_thread() {
+            write!(file,
+                ",\n{{\"ph\": \"M\", \"name\": \"thread_name\", \"pid\": {}, \"tid\": {}, \
+                   \"args\": {{\"name\": \"thread {}\"}}}}",
+                pid,
+                tid,
+                tid,
+            ).unwrap();
+
+            for event in &events {
+                let trace_event = TraceEvent::new(event);
+                let time = event.timestamp();
+                write!(file,
+                    ",\n{{\"ph\": \"{}\", \"name\": \"{}\", \"cat\": \"{}\", \
+                       \"ts\": {}.{:03}, \"pid\": {}, \"tid\": {}",
+                    trace_event.phase,
+                    trace_event.name,
+                    trace_event.category,
+                    time / 1000,
+                    time % 1000,
+                    pid,
+                    tid,
+                ).unwrap();
+                match *event {
+                    ProfilerEvent::QueryCacheHit { .. } => {
+                        // Instant events are drawn on their thread's track.
+                        write!(file, ", \"s\": \"t\"").unwrap();
+                    }
+                    ProfilerEvent::QueryCount { count, .. } => {
+                        write!(file, ", \"args\": {{\"count\": {}}}", count).unwrap();
+                    }
+                    _ => {}
                 }
+                write!(file, "}}").unwrap();
+            }
+        }
 
-                let (secs, nanos) = {
-                    let time = self.start_time + Duration::from_nanos(event.timestamp());
-                    let time_since_unix =
-                        time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
-                    (time_since_unix.as_secs(), time_since_unix.subsec_nanos())
-                };
-
-                match event {
-                    QueryStart { query_name, category, time: _ } =>
-                        write!(file,
-                            "{{ \
-                                \"QueryStart\": {{ \
-                                    \"query_name\": \"{}\",\
-                                    \"category\": \"{:?}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            category,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    QueryEnd { query_name, category, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"QueryEnd\": {{\
-                                    \"query_name\": \"{}\",\
-                                    \"category\": \"{:?}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            category,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    GenericActivityStart { category, time: _ } =>
-                        write!(file,
-                            "{{
-                                \"GenericActivityStart\": {{\
-                                    \"category\": \"{:?}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            category,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    GenericActivityEnd { category, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"GenericActivityEnd\": {{\
-                                    \"category\": \"{:?}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            category,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    QueryCacheHit { query_name, category, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"QueryCacheHit\": {{\
-                                    \"query_name\": \"{}\",\
-                                    \"category\": \"{:?}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            category,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    QueryCount { query_name, category, count, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"QueryCount\": {{\
-                                    \"query_name\": \"{}\",\
-                                    \"category\": \"{:?}\",\
-                                    \"count\": {},\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            category,
-                            count,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    IncrementalLoadResultStart { query_name, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"IncrementalLoadResultStart\": {{\
-                                    \"query_name\": \"{}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    IncrementalLoadResultEnd { query_name, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"IncrementalLoadResultEnd\": {{\
-                                    \"query_name\": \"{}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    QueryBlockedStart { query_name, category, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"QueryBlockedStart\": {{\
-                                    \"query_name\": \"{}\",\
-                                    \"category\": \"{:?}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            category,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap(),
-                    QueryBlockedEnd { query_name, category, time: _ } =>
-                        write!(file,
-                            "{{\
-                                \"QueryBlockedEnd\": {{\
-                                    \"query_name\": \"{}\",\
-                                    \"category\": \"{:?}\",\
-                                    \"time\": {{\
-                                        \"secs\": {},\
-                                        \"nanos\": {}\
-                                    }},\
-                                    \"thread_id\": {}\
-                                }}\
-                            }}",
-                            query_name,
-                            category,
-                            secs,
-                            nanos,
-                            thread_id,
-                        ).unwrap()
+        writeln!(file, "\n] }}").unwrap();
+    }
+
+    /// Writes the events as folded stacks to `<crate>.profile_events.folded`,
+    /// one line per distinct stack with the self time spent in it in
+    /// nanoseconds. This is the input format of `flamegraph.pl` and
+    /// `inferno-flamegraph`.
+    pub fn dump_folded_stacks(&self, opts: &Options) {
+        let crate_name = opts.crate_name.clone().unwrap_or_default();
+        let mut self_times: FxHashMap<String, u64> = Default::default();
+
+        for (_, events) in self.events_by_thread() {
+            // The frames on the stack, with the time they were entered at and
+            // the time spent in their callees.
+            let mut stack: Vec<(String, u64, u64)> = Vec::new();
+
+            for event in &events {
+                let trace_event = TraceEvent::new(event);
+                let time = event.timestamp();
+                match trace_event.phase {
+                    "B" => {
+                        let name = match *event {
+                            ProfilerEvent::QueryBlockedStart { .. } |
+                            ProfilerEvent::IncrementalLoadResultStart { .. } =>
+                                format!("{} ({})", trace_event.name, trace_event.category),
+                            _ => trace_event.name.to_string(),
+                        };
+                        stack.push((name, time, 0));
+                    }
+                    "E" => {
+                        let (start, child_time) = match stack.last() {
+                            Some(&(_, start, child_time)) => (start, child_time),
+                            None => continue,
+                        };
+                        let total = time.saturating_sub(start);
+                        let folded = stack
+                            .iter()
+                            .map(|&(ref name, _, _)| &name[..])
+                            .collect::<Vec<_>>()
+                            .join(";");
+                        *self_times.entry(folded).or_default() +=
+                            total.saturating_sub(child_time);
+                        stack.pop();
+                        if let Some(parent) = stack.last_mut() {
+                            parent.2 += total;
+                        }
+                    }
+                    _ => {}
                 }
             }
         }
 
-        write!(file, "] }}").unwrap();
+        let mut stacks: Vec<_> = self_times.into_iter().collect();
+        stacks.sort();
+
+        let filename = format!("{}.profile_events.folded", crate_name);
+        let mut file = BufWriter::new(fs::File::create(filename).unwrap());
+        for (stack, self_time) in stacks {
+            writeln!(file, "{} {}", stack, self_time).unwrap();
+        }
+    }
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    w.write_u32::<LittleEndian>(s.len() as u32)?;
+    w.write_all(s.as_bytes())
+}
+
+/// How a `ProfilerEvent` is represented in a trace.
+struct TraceEvent {
+    /// `B` and `E` begin and end a span, `i` is an instant event and `C` a
+    /// counter.
+    phase: &'static str,
+    name: &'static str,
+    category: &'static str,
+}
+
+impl TraceEvent {
+    fn new(event: &ProfilerEvent) -> TraceEvent {
+        use self::ProfilerEvent::*;
+
+        let (phase, name, category) = match *event {
+            QueryStart { query_name, .. } => ("B", query_name, "Query"),
+            QueryEnd { query_name, .. } => ("E", query_name, "Query"),
+            GenericActivityStart { category, .. } => ("B", category.name(), "Activity"),
+            GenericActivityEnd { category, .. } => ("E", category.name(), "Activity"),
+            IncrementalLoadResultStart { query_name, .. } =>
+                ("B", query_name, "IncrementalLoad"),
+            IncrementalLoadResultEnd { query_name, .. } => ("E", query_name, "IncrementalLoad"),
+            QueryCacheHit { query_name, .. } => ("i", query_name, "QueryCacheHit"),
+            QueryCount { query_name, .. } => ("C", query_name, "QueryCount"),
+            QueryBlockedStart { query_name, .. } => ("B", query_name, "QueryBlocked"),
+            QueryBlockedEnd { query_name, .. } => ("E", query_name, "QueryBlocked"),
+        };
+
+        TraceEvent { phase, name, category }
     }
 }
@@ -14,6 +14,8 @@ pub struct Diagnostic {
     pub level: Level,
     pub message: Vec<(String, Style)>,
     pub code: Option<DiagnosticId>,
+    /// A stable identifier given at the creation site, see `get_slug`.
+    pub slug: Option<String>,
     pub span: MultiSpan,
     pub children: Vec<SubDiagnostic>,
     pub suggestions: Vec<CodeSuggestion>,
@@ -84,6 +86,7 @@ impl Diagnostic {
             level,
             message: vec![(message.to_owned(), Style::NoStyle)],
             code,
+            slug: None,
             span: MultiSpan::new(),
             children: vec![],
             suggestions: vec![],
@@ -407,6 +410,30 @@ impl Diagnostic {
         self.code.clone()
     }
 
+    /// Sets the stable, machine-readable identifier of this kind of diagnostic, like
+    /// `expand::empty_derive`. This is for diagnostics without an error code or lint name, which
+    /// have no slug otherwise. Once published, a slug must not be changed or reused for another
+    /// kind of diagnostic.
+    pub fn slug(&mut self, slug: &str) -> &mut Self {
+        self.slug = Some(slug.to_owned());
+        self
+    }
+
+    /// Returns the slug given with `slug`, or else one derived from the lint name or error code,
+    /// like `lint::unused_variables` or `error::E0308`. This is what tools should use to track
+    /// diagnostics across compiler versions, and what `-Z treat-diagnostic` matches. Diagnostics
+    /// given none of these have no slug.
+    pub fn get_slug(&self) -> Option<String> {
+        if let Some(ref slug) = self.slug {
+            return Some(slug.clone());
+        }
+        match self.code {
+            Some(DiagnosticId::Lint(ref name)) => Some(format!("lint::{}", name)),
+            Some(DiagnosticId::Error(ref code)) => Some(format!("error::{}", code)),
+            None => None,
+        }
+    }
+
     pub fn message(&self) -> String {
         self.message.iter().map(|i| i.0.as_str()).collect::<String>()
     }
@@ -1,5 +1,6 @@
 use crate::Diagnostic;
 use crate::DiagnosticId;
+use crate::DiagnosticTreatment;
 use crate::DiagnosticStyledString;
 use crate::Applicability;
 
@@ -95,11 +96,41 @@ impl<'a> DiagnosticBuilder<'a> {
         if self.cancelled() {
             return;
         }
+        if !self.apply_treatment() {
+            self.cancel();
+            return;
+        }
 
         self.handler.emit_db(&self);
         self.cancel();
     }
 
+    /// Changes the level of the diagnostic as requested with `-Z treat-diagnostic`, returning
+    /// whether it should still be emitted.
+    fn apply_treatment(&mut self) -> bool {
+        let treatments = &self.handler.flags.diagnostic_treatments;
+        if treatments.is_empty() {
+            return true;
+        }
+        let treatment = match self.get_slug().and_then(|slug| treatments.get(&slug).cloned()) {
+            Some(treatment) => treatment,
+            None => return true,
+        };
+        match treatment {
+            // Errors cannot be downgraded, as the compiler cannot recover from them.
+            _ if self.is_error() => true,
+            DiagnosticTreatment::Allow => false,
+            DiagnosticTreatment::Warn => {
+                self.level = Level::Warning;
+                true
+            }
+            DiagnosticTreatment::Deny => {
+                self.level = Level::Error;
+                true
+            }
+        }
+    }
+
     /// Buffers the diagnostic for later emission, unless handler
     /// has disabled such buffering.
     pub fn buffer(mut self, buffered_diagnostics: &mut Vec<Diagnostic>) {
@@ -322,6 +353,7 @@ impl<'a> DiagnosticBuilder<'a> {
 
     forward!(pub fn set_span<S: Into<MultiSpan>>(&mut self, sp: S) -> &mut Self);
     forward!(pub fn code(&mut self, s: DiagnosticId) -> &mut Self);
+    forward!(pub fn slug(&mut self, slug: &str) -> &mut Self);
 
     pub fn allow_suggestions(&mut self, allow: bool) -> &mut Self {
         self.allow_suggestions = allow;
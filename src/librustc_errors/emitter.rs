@@ -8,6 +8,7 @@ use crate::{
 };
 use crate::snippet::{Annotation, AnnotationType, Line, MultilineAnnotation, StyledString, Style};
 use crate::styled_buffer::StyledBuffer;
+use crate::registry::Registry;
 
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::sync::Lrc;
@@ -29,6 +30,11 @@ pub trait Emitter {
     fn should_show_explain(&self) -> bool {
         true
     }
+
+    /// The source map the emitter renders spans with, if any.
+    fn source_map(&self) -> Option<&Lrc<SourceMapperDyn>> {
+        None
+    }
 }
 
 impl Emitter for EmitterWriter {
@@ -70,6 +76,19 @@ impl Emitter for EmitterWriter {
             }
         }
 
+        if let (Some(registry), Some(DiagnosticId::Error(code))) =
+            (&self.inline_explanations, &db.code)
+        {
+            if let Some(explanation) = registry.find_explanation(code) {
+                children.push(SubDiagnostic {
+                    level: Level::Note,
+                    message: vec![(explanation.summary, Style::NoStyle)],
+                    span: MultiSpan::new(),
+                    render_span: None,
+                });
+            }
+        }
+
         self.fix_multispans_in_std_macros(&mut primary_span,
                                           &mut children,
                                           db.handler.flags.external_macro_backtrace);
@@ -85,6 +104,10 @@ impl Emitter for EmitterWriter {
     fn should_show_explain(&self) -> bool {
         !self.short_message
     }
+
+    fn source_map(&self) -> Option<&Lrc<SourceMapperDyn>> {
+        self.sm.as_ref()
+    }
 }
 
 /// maximum number of lines we will print for each error; arbitrary.
@@ -126,6 +149,9 @@ pub struct EmitterWriter {
     short_message: bool,
     teach: bool,
     ui_testing: bool,
+    /// The registry to look up the explanations printed under errors, if
+    /// `--explain-inline` was passed.
+    inline_explanations: Option<Registry>,
 }
 
 struct FileWithAnnotatedLines {
@@ -147,6 +173,7 @@ impl EmitterWriter {
             short_message,
             teach,
             ui_testing: false,
+            inline_explanations: None,
         }
     }
 
@@ -161,6 +188,7 @@ impl EmitterWriter {
             short_message,
             teach,
             ui_testing: false,
+            inline_explanations: None,
         }
     }
 
@@ -169,6 +197,12 @@ impl EmitterWriter {
         self
     }
 
+    /// Prints the summary of the explanation of the error code from `registry` under each error.
+    pub fn explain_inline(mut self, registry: Option<Registry>) -> Self {
+        self.inline_explanations = registry;
+        self
+    }
+
     fn maybe_anonymized(&self, line_num: usize) -> String {
         if self.ui_testing {
             ANONYMIZED_LINE_NUM.to_string()
@@ -0,0 +1,123 @@
+//! Groups similar errors and warnings, so that a mistake that causes the same
+//! error at many places (such as every call of a misdeclared function) is only
+//! shown once, followed by a summary of where else it was reported.
+
+use crate::{Diagnostic, DiagnosticId, Level, SourceMapperDyn};
+
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::stable_hasher::StableHasher;
+use syntax_pos::Span;
+
+use std::hash::Hash;
+use std::mem;
+
+/// The maximum number of locations of hidden diagnostics listed in a summary.
+const MAX_LISTED_LOCATIONS: usize = 10;
+
+struct DiagnosticGroup {
+    level: Level,
+    code: Option<DiagnosticId>,
+    message: String,
+    /// The primary span of the diagnostic that was emitted.
+    span: Option<Span>,
+    /// The primary spans of the similar diagnostics that were hidden.
+    hidden: Vec<Option<Span>>,
+}
+
+#[derive(Default)]
+pub struct DiagnosticGroups {
+    index: FxHashMap<u128, usize>,
+    groups: Vec<DiagnosticGroup>,
+}
+
+impl DiagnosticGroups {
+    /// Records `diagnostic` and returns whether it should be emitted, which is
+    /// the case unless a similar diagnostic was emitted before.
+    pub fn add(&mut self, diagnostic: &Diagnostic) -> bool {
+        match diagnostic.level {
+            Level::Error | Level::Warning => {}
+            _ => return true,
+        }
+
+        // Diagnostics are similar if they have the same code, message, primary
+        // labels and notes, which describe the cause. The labels alone are not
+        // enough, e.g. a type mismatch is labelled `found reference` whatever
+        // the type behind the reference is, which is only given by its note.
+        let key = {
+            let mut hasher = StableHasher::new();
+            diagnostic.level.hash(&mut hasher);
+            diagnostic.code.hash(&mut hasher);
+            diagnostic.message().hash(&mut hasher);
+            for span_label in diagnostic.span.span_labels() {
+                if span_label.is_primary {
+                    span_label.label.hash(&mut hasher);
+                }
+            }
+            for child in &diagnostic.children {
+                child.level.hash(&mut hasher);
+                child.message().hash(&mut hasher);
+            }
+            hasher.finish()
+        };
+
+        let span = diagnostic.span.primary_span();
+        if let Some(&index) = self.index.get(&key) {
+            self.groups[index].hidden.push(span);
+            return false;
+        }
+        self.index.insert(key, self.groups.len());
+        self.groups.push(DiagnosticGroup {
+            level: diagnostic.level,
+            code: diagnostic.code.clone(),
+            message: diagnostic.message(),
+            span,
+            hidden: Vec::new(),
+        });
+        true
+    }
+
+    /// Returns a summary of every group with hidden diagnostics and starts over.
+    pub fn take_summaries(&mut self, sm: Option<&SourceMapperDyn>) -> Vec<String> {
+        self.index.clear();
+        mem::replace(&mut self.groups, Vec::new()).iter()
+            .filter(|group| !group.hidden.is_empty())
+            .map(|group| summary(group, sm))
+            .collect()
+    }
+}
+
+/// Describes a group like `error[E0308]: mismatched types (src/lib.rs:3:13) and
+/// 2 similar errors at: src/lib.rs:7:13, src/lib.rs:11:13`.
+fn summary(group: &DiagnosticGroup, sm: Option<&SourceMapperDyn>) -> String {
+    let location = |span: Option<Span>| -> Option<String> {
+        let loc = sm?.lookup_char_pos(span?.lo());
+        Some(format!("{}:{}:{}", loc.file.name, loc.line, loc.col.0 + 1))
+    };
+
+    let mut summary = group.level.to_str().to_string();
+    match group.code {
+        Some(DiagnosticId::Error(ref code)) | Some(DiagnosticId::Lint(ref code)) => {
+            summary.push_str(&format!("[{}]", code));
+        }
+        None => {}
+    }
+    summary.push_str(&format!(": {}", group.message));
+    if let Some(location) = location(group.span) {
+        summary.push_str(&format!(" ({})", location));
+    }
+
+    let count = group.hidden.len();
+    summary.push_str(&format!(" and {} similar {}{}",
+                              count,
+                              group.level.to_str(),
+                              if count == 1 { "" } else { "s" }));
+    let locations: Vec<_> = group.hidden.iter().filter_map(|&span| location(span)).collect();
+    if !locations.is_empty() {
+        let listed = locations.len().min(MAX_LISTED_LOCATIONS);
+        summary.push_str(&format!(" at: {}", locations[..listed].join(", ")));
+        if locations.len() > listed {
+            summary.push_str(&format!(", and {} more", locations.len() - listed));
+        }
+    }
+    summary
+}
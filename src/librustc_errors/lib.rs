@@ -18,7 +18,7 @@ use Level::*;
 use emitter::{Emitter, EmitterWriter};
 
 use rustc_data_structures::sync::{self, Lrc, Lock, AtomicUsize, AtomicBool, SeqCst};
-use rustc_data_structures::fx::FxHashSet;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_data_structures::stable_hasher::StableHasher;
 
 use std::borrow::Cow;
@@ -31,8 +31,10 @@ use termcolor::{ColorSpec, Color};
 mod diagnostic;
 mod diagnostic_builder;
 pub mod emitter;
+mod grouping;
 mod snippet;
 pub mod registry;
+pub mod sarif;
 mod styled_buffer;
 mod lock;
 
@@ -316,6 +318,12 @@ pub struct Handler {
     // this handler. These hashes is used to avoid emitting the same error
     // twice.
     emitted_diagnostics: Lock<FxHashSet<u128>>,
+
+    /// The suggestions of all emitted diagnostics, if `flags.collect_suggestions` is set.
+    collected_suggestions: Lock<Vec<CodeSuggestion>>,
+
+    /// The groups of similar diagnostics, if `flags.group_similar_diagnostics` is set.
+    diagnostic_groups: Lock<grouping::DiagnosticGroups>,
 }
 
 fn default_track_diagnostic(_: &Diagnostic) {}
@@ -340,6 +348,24 @@ pub struct HandlerFlags {
     /// show macro backtraces even for non-local macros.
     /// (rustc: see `-Z external-macro-backtrace`)
     pub external_macro_backtrace: bool,
+    /// If true, keep the suggestions of emitted diagnostics so they can be applied.
+    /// (rustc: see `-Z apply-suggestions`)
+    pub collect_suggestions: bool,
+    /// If true, only the first of several similar errors or warnings is emitted, and the others
+    /// are listed in a summary at the end.
+    /// (rustc: see `-Z group-similar-diagnostics`)
+    pub group_similar_diagnostics: bool,
+    /// The levels to change diagnostics to, by their slug.
+    /// (rustc: see `-Z treat-diagnostic`)
+    pub diagnostic_treatments: FxHashMap<String, DiagnosticTreatment>,
+}
+
+/// How `-Z treat-diagnostic` changes the level of a diagnostic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticTreatment {
+    Allow,
+    Warn,
+    Deny,
 }
 
 impl Drop for Handler {
@@ -405,6 +431,8 @@ impl Handler {
             taught_diagnostics: Default::default(),
             emitted_diagnostic_codes: Default::default(),
             emitted_diagnostics: Default::default(),
+            collected_suggestions: Default::default(),
+            diagnostic_groups: Default::default(),
         }
     }
 
@@ -651,7 +679,15 @@ impl Handler {
         self.err_count() > 0
     }
 
+    /// Takes the suggestions collected from the emitted diagnostics, in the order in which
+    /// they were emitted. This is always empty unless `flags.collect_suggestions` is set.
+    pub fn take_collected_suggestions(&self) -> Vec<CodeSuggestion> {
+        std::mem::replace(&mut *self.collected_suggestions.borrow_mut(), Vec::new())
+    }
+
     pub fn print_error_count(&self) {
+        self.emit_diagnostic_group_summaries();
+
         let s = match self.err_count() {
             0 => return,
             1 => "aborting due to previous error".to_string(),
@@ -693,6 +729,19 @@ impl Handler {
         }
     }
 
+    fn emit_diagnostic_group_summaries(&self) {
+        if !self.flags.group_similar_diagnostics {
+            return;
+        }
+        let summaries = {
+            let emitter = self.emitter.borrow();
+            self.diagnostic_groups.borrow_mut().take_summaries(emitter.source_map().map(|sm| &**sm))
+        };
+        for summary in summaries {
+            self.emit(&MultiSpan::new(), &summary, Note);
+        }
+    }
+
     pub fn abort_if_errors(&self) {
         if self.err_count() == 0 {
             return;
@@ -757,7 +806,16 @@ impl Handler {
         // Only emit the diagnostic if we haven't already emitted an equivalent
         // one:
         if self.emitted_diagnostics.borrow_mut().insert(diagnostic_hash) {
-            self.emitter.borrow_mut().emit(db);
+            // Diagnostics similar to an earlier one are only counted, and listed in a summary by
+            // `print_error_count`.
+            let hidden = self.flags.group_similar_diagnostics &&
+                !self.diagnostic_groups.borrow_mut().add(diagnostic);
+            if !hidden {
+                self.emitter.borrow_mut().emit(db);
+            }
+            if self.flags.collect_suggestions {
+                self.collected_suggestions.borrow_mut().extend(db.suggestions.iter().cloned());
+            }
             if db.is_error() {
                 self.bump_err_count();
             }
@@ -13,4 +13,118 @@ impl Registry {
     pub fn find_description(&self, code: &str) -> Option<&'static str> {
         self.descriptions.get(code).cloned()
     }
+
+    pub fn find_explanation(&self, code: &str) -> Option<Explanation> {
+        self.find_description(code).map(Explanation::parse)
+    }
+}
+
+/// The sections of the long explanation of an error code, for tools and for
+/// `--explain-inline`.
+#[derive(Clone, Debug, Default, PartialEq, RustcEncodable)]
+pub struct Explanation {
+    /// The first sentence of the explanation.
+    pub summary: String,
+    /// The first example of code causing the error.
+    pub erroneous_example: Option<String>,
+    /// The text following the erroneous example, describing how to fix it.
+    pub fix: Option<String>,
+    /// The example of fixed code following the erroneous example.
+    pub fixed_example: Option<String>,
+}
+
+enum Block<'a> {
+    /// A paragraph, with its lines joined by spaces.
+    Text(String),
+    /// A code block, without the lines rustdoc hides.
+    Code { info: &'a str, code: String },
+}
+
+impl Explanation {
+    /// Splits the Markdown of a long explanation into its sections. Erroneous
+    /// examples are recognized as the code blocks marked `compile_fail`.
+    pub fn parse(description: &str) -> Explanation {
+        let blocks = blocks(description);
+        let is_erroneous = |block: &Block<'_>| match *block {
+            Block::Code { info, .. } => info.contains("compile_fail"),
+            Block::Text(_) => false,
+        };
+
+        let mut explanation = Explanation::default();
+        for block in &blocks {
+            if let Block::Text(ref text) = *block {
+                explanation.summary = first_sentence(text).to_string();
+                break;
+            }
+        }
+
+        let erroneous = match blocks.iter().position(is_erroneous) {
+            Some(erroneous) => erroneous,
+            None => return explanation,
+        };
+        if let Block::Code { ref code, .. } = blocks[erroneous] {
+            explanation.erroneous_example = Some(code.clone());
+        }
+        let mut fix = Vec::new();
+        for block in &blocks[erroneous + 1..] {
+            match *block {
+                Block::Text(ref text) => fix.push(&text[..]),
+                Block::Code { ref code, .. } if !is_erroneous(block) => {
+                    explanation.fixed_example = Some(code.clone());
+                    break;
+                }
+                Block::Code { .. } => break,
+            }
+        }
+        if !fix.is_empty() {
+            explanation.fix = Some(fix.join("\n\n"));
+        }
+        explanation
+    }
+}
+
+fn blocks(description: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut text: Vec<&str> = Vec::new();
+    let mut code: Option<(&str, Vec<&str>)> = None;
+
+    for line in description.lines() {
+        let trimmed = line.trim();
+        if let Some((info, mut lines)) = code.take() {
+            if trimmed.starts_with("```") {
+                blocks.push(Block::Code { info, code: lines.join("\n") });
+            } else {
+                // Lines starting with `# ` are hidden by rustdoc.
+                if line != "#" && !line.starts_with("# ") {
+                    lines.push(line);
+                }
+                code = Some((info, lines));
+            }
+            continue;
+        }
+
+        // Headings are not part of any paragraph.
+        if trimmed.is_empty() || trimmed.starts_with("```") || trimmed.starts_with('#') {
+            if !text.is_empty() {
+                blocks.push(Block::Text(text.join(" ")));
+                text.clear();
+            }
+            if trimmed.starts_with("```") {
+                code = Some((&trimmed[3..], Vec::new()));
+            }
+        } else {
+            text.push(trimmed);
+        }
+    }
+    if !text.is_empty() {
+        blocks.push(Block::Text(text.join(" ")));
+    }
+    blocks
+}
+
+fn first_sentence(text: &str) -> &str {
+    match text.find(". ") {
+        Some(end) => &text[..=end],
+        None => text,
+    }
 }
@@ -0,0 +1,300 @@
+//! An emitter that collects the diagnostics of a compilation session into a
+//! single [SARIF 2.1.0] log, which is written out once the session is over and
+//! the emitter is dropped.
+//!
+//! Error codes and lint names become the rules of the run, the primary spans of
+//! a diagnostic its locations and its labelled secondary spans and the spans of
+//! its children its related locations. Every substitution of a suggestion
+//! becomes a fix, with the `Applicability` of the suggestion in the property
+//! bag of the fix.
+//!
+//! The log is written on a single line. Diagnostics that are reported before
+//! the session is created, such as warnings about the command line options,
+//! have their own emitter and so end up in logs of their own, on the lines
+//! before the log of the session.
+//!
+//! [SARIF 2.1.0]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use crate::{CodeSuggestion, DiagnosticBuilder, DiagnosticId, Level, SourceMapperDyn};
+use crate::emitter::Emitter;
+use crate::registry::Registry;
+
+use rustc_data_structures::sync::Lrc;
+use rustc_serialize::json::{as_json, Json};
+use syntax_pos::{FileName, Span};
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::mem;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+
+pub struct SarifEmitter {
+    dst: Box<dyn Write + Send>,
+    registry: Option<Registry>,
+    sm: Option<Lrc<SourceMapperDyn>>,
+    /// The rules referred to by the results, in the order they were first seen.
+    rules: Vec<DiagnosticId>,
+    results: Vec<Json>,
+}
+
+impl SarifEmitter {
+    pub fn stderr(registry: Option<Registry>,
+                  source_map: Option<Lrc<SourceMapperDyn>>) -> SarifEmitter {
+        SarifEmitter::new(Box::new(io::stderr()), registry, source_map)
+    }
+
+    pub fn new(dst: Box<dyn Write + Send>,
+               registry: Option<Registry>,
+               source_map: Option<Lrc<SourceMapperDyn>>) -> SarifEmitter {
+        SarifEmitter {
+            dst,
+            registry,
+            sm: source_map,
+            rules: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    fn rule_index(&mut self, code: &DiagnosticId) -> usize {
+        match self.rules.iter().position(|rule| rule == code) {
+            Some(index) => index,
+            None => {
+                self.rules.push(code.clone());
+                self.rules.len() - 1
+            }
+        }
+    }
+
+    fn result(&mut self, db: &DiagnosticBuilder<'_>) -> Json {
+        // SARIF has no notion of sub-diagnostics, so the messages of the
+        // children are appended to the message of the result.
+        let mut message = db.message();
+        for child in &db.children {
+            message.push_str(&format!("\n{}: {}", child.level.to_str(), child.message()));
+        }
+
+        let mut fields = vec![
+            ("level", string(sarif_level(db.level))),
+            ("message", text(&message)),
+        ];
+        if let Some(ref code) = db.code {
+            let index = self.rule_index(code);
+            fields.push(("ruleId", string(rule_id(code))));
+            fields.push(("ruleIndex", Json::U64(index as u64)));
+        }
+
+        let locations: Vec<_> = db.span.primary_spans().iter()
+            .filter_map(|&span| self.location(span, None))
+            .collect();
+        if !locations.is_empty() {
+            fields.push(("locations", Json::Array(locations)));
+        }
+
+        let secondary = db.span.span_labels().into_iter()
+            .filter(|span_label| !span_label.is_primary)
+            .filter_map(|span_label| {
+                let label = span_label.label?;
+                self.location(span_label.span, Some(&label[..]))
+            });
+        let children = db.children.iter().flat_map(|child| {
+            let message = child.message();
+            child.span.primary_spans().iter()
+                .filter_map(|&span| self.location(span, Some(&message[..])))
+                .collect::<Vec<_>>()
+        });
+        let related_locations: Vec<_> = secondary.chain(children).collect();
+        if !related_locations.is_empty() {
+            fields.push(("relatedLocations", Json::Array(related_locations)));
+        }
+
+        let fixes: Vec<_> = db.suggestions.iter()
+            .flat_map(|suggestion| self.fixes(suggestion))
+            .collect();
+        if !fixes.is_empty() {
+            fields.push(("fixes", Json::Array(fixes)));
+        }
+
+        object(fields)
+    }
+
+    fn location(&self, span: Span, message: Option<&str>) -> Option<Json> {
+        let sm = self.sm.as_ref()?;
+        if span.is_dummy() {
+            return None;
+        }
+        let mut fields = vec![
+            ("physicalLocation", object(vec![
+                ("artifactLocation", artifact_location(&sm.span_to_filename(span))),
+                ("region", region(&**sm, span)),
+            ])),
+        ];
+        if let Some(message) = message {
+            fields.push(("message", text(message)));
+        }
+        Some(object(fields))
+    }
+
+    /// Turns every substitution of `suggestion` into a fix, grouping the
+    /// replacements it makes by the file they are made in.
+    fn fixes(&self, suggestion: &CodeSuggestion) -> Vec<Json> {
+        let sm = match self.sm {
+            Some(ref sm) => sm,
+            None => return Vec::new(),
+        };
+        suggestion.substitutions.iter().map(|substitution| {
+            let mut changes: BTreeMap<FileName, Vec<Json>> = BTreeMap::new();
+            for part in &substitution.parts {
+                changes.entry(sm.span_to_filename(part.span)).or_default().push(object(vec![
+                    ("deletedRegion", region(&**sm, part.span)),
+                    ("insertedContent", text(&part.snippet)),
+                ]));
+            }
+            let artifact_changes = changes.into_iter().map(|(file, replacements)| {
+                object(vec![
+                    ("artifactLocation", artifact_location(&file)),
+                    ("replacements", Json::Array(replacements)),
+                ])
+            }).collect();
+
+            object(vec![
+                ("description", text(&suggestion.msg)),
+                ("artifactChanges", Json::Array(artifact_changes)),
+                ("properties", object(vec![
+                    ("applicability", string(&format!("{:?}", suggestion.applicability))),
+                ])),
+            ])
+        }).collect()
+    }
+
+    fn rule(&self, code: &DiagnosticId) -> Json {
+        let mut fields = vec![("id", string(rule_id(code)))];
+        if let DiagnosticId::Error(ref code) = *code {
+            let help_uri = format!("https://doc.rust-lang.org/error-index.html#{}", code);
+            fields.push(("helpUri", string(&help_uri)));
+            let description = self.registry.as_ref().and_then(|r| r.find_description(code));
+            if let Some(description) = description {
+                fields.push(("fullDescription", text(description)));
+            }
+        }
+        object(fields)
+    }
+
+    fn log(&mut self) -> Json {
+        let rules = self.rules.iter().map(|code| self.rule(code)).collect();
+        let driver = object(vec![
+            ("name", string("rustc")),
+            ("version", string(option_env!("CFG_VERSION").unwrap_or("unknown"))),
+            ("informationUri", string("https://www.rust-lang.org/")),
+            ("rules", Json::Array(rules)),
+        ]);
+        let run = object(vec![
+            ("tool", object(vec![("driver", driver)])),
+            ("columnKind", string("unicodeCodePoints")),
+            ("results", Json::Array(mem::replace(&mut self.results, Vec::new()))),
+        ]);
+        object(vec![
+            ("$schema", string(SARIF_SCHEMA)),
+            ("version", string(SARIF_VERSION)),
+            ("runs", Json::Array(vec![run])),
+        ])
+    }
+}
+
+impl Emitter for SarifEmitter {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+        match db.level {
+            // Failure notes only summarize the errors that were reported.
+            Level::FailureNote | Level::Cancelled => return,
+            _ => {}
+        }
+        let result = self.result(db);
+        self.results.push(result);
+    }
+
+    fn should_show_explain(&self) -> bool {
+        false
+    }
+
+    fn source_map(&self) -> Option<&Lrc<SourceMapperDyn>> {
+        self.sm.as_ref()
+    }
+}
+
+impl Drop for SarifEmitter {
+    fn drop(&mut self) {
+        let log = self.log();
+        // There is nowhere left to report a failure to write the log to, and
+        // panicking here could abort a compiler that is already unwinding.
+        let _ = writeln!(self.dst, "{}", as_json(&log)).and_then(|()| self.dst.flush());
+    }
+}
+
+fn sarif_level(level: Level) -> &'static str {
+    match level {
+        Level::Bug | Level::Fatal | Level::PhaseFatal | Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Note | Level::Help | Level::FailureNote | Level::Cancelled => "note",
+    }
+}
+
+fn rule_id(code: &DiagnosticId) -> &str {
+    match *code {
+        DiagnosticId::Error(ref code) | DiagnosticId::Lint(ref code) => code,
+    }
+}
+
+fn region(sm: &SourceMapperDyn, span: Span) -> Json {
+    let lo = sm.lookup_char_pos(span.lo());
+    let hi = sm.lookup_char_pos(span.hi());
+    // SARIF columns are 1-based and the end column is exclusive.
+    object(vec![
+        ("startLine", Json::U64(lo.line as u64)),
+        ("startColumn", Json::U64(lo.col.0 as u64 + 1)),
+        ("endLine", Json::U64(hi.line as u64)),
+        ("endColumn", Json::U64(hi.col.0 as u64 + 1)),
+    ])
+}
+
+/// Files are referred to by URI: absolute paths become `file` URIs and all
+/// other file names are used as relative references.
+fn artifact_location(file: &FileName) -> Json {
+    let uri = match *file {
+        FileName::Real(ref path) => {
+            let path = path.to_string_lossy().replace('\\', "/");
+            let mut uri = if path.starts_with('/') {
+                "file://".to_string()
+            } else if path.as_bytes().get(1) == Some(&b':') {
+                // A Windows path with a drive letter.
+                "file:///".to_string()
+            } else {
+                String::new()
+            };
+            for c in path.chars() {
+                match c {
+                    ' ' => uri.push_str("%20"),
+                    '#' => uri.push_str("%23"),
+                    '%' => uri.push_str("%25"),
+                    '?' => uri.push_str("%3F"),
+                    c => uri.push(c),
+                }
+            }
+            uri
+        }
+        ref other => other.to_string(),
+    };
+    object(vec![("uri", Json::String(uri))])
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn text(s: &str) -> Json {
+    object(vec![("text", string(s))])
+}
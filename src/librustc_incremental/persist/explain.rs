@@ -0,0 +1,137 @@
+//! Writes the report requested with `-Z incremental-explain[=<filter>]`.
+//!
+//! For every query from the previous compilation session that could not be
+//! marked green and had to be re-executed, the report lists the chain of
+//! dependencies that changed, ending at the input that started it, e.g.:
+//!
+//! ```text
+//! TypeckTables(foo::bar) was re-executed, and its result changed
+//!     because TypeOf(foo::Baz) changed
+//!     because Hir(foo::Baz) changed (the HIR of `foo::Baz` was modified)
+//! ```
+//!
+//! The optional filter has the same format as `RUST_DEP_GRAPH_FILTER` and
+//! selects the re-executed queries to explain. Changing the command-line
+//! arguments discards the whole cache, which the report mentions instead.
+
+use rustc::dep_graph::{DepKind, DepNode, DepNodeColor, RedReason};
+use rustc::dep_graph::debug::DepNodeFilter;
+use rustc::hir::def_id::LOCAL_CRATE;
+use rustc::ty::TyCtxt;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+pub fn write_explanation<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>) {
+    let filter = match tcx.sess.opts.debugging_opts.incremental_explain {
+        Some(ref filter) => DepNodeFilter::new(filter),
+        None => return,
+    };
+
+    let path = tcx.output_filenames(LOCAL_CRATE).with_extension("incremental-explain.txt");
+    let result = File::create(&path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        write_report(tcx, &filter, &mut out)?;
+        out.flush()
+    });
+    if let Err(e) = result {
+        tcx.sess.warn(&format!("could not write `{}`: {}", path.display(), e));
+    }
+}
+
+fn write_report<'a, 'tcx, W: Write>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                                     filter: &DepNodeFilter,
+                                     out: &mut W)
+                                     -> io::Result<()> {
+    if tcx.dep_graph.prev_graph_is_empty() {
+        return writeln!(out, "there was no usable previous compilation session to reuse \
+                              results from, e.g. because this is the first session or the \
+                              command-line arguments changed (see `-Z incremental-info`)");
+    }
+
+    let reasons = tcx.dep_graph.red_reasons();
+    let mut nodes: Vec<_> = reasons.keys()
+                                   .filter(|node| filter.accepts_all() || filter.test(node))
+                                   .map(|node| (dep_node_str(tcx, node), node))
+                                   .collect();
+    nodes.sort();
+
+    if nodes.is_empty() {
+        return writeln!(out, "no queries had to be re-executed");
+    }
+
+    for (node_str, node) in nodes {
+        let outcome = match tcx.dep_graph.node_color(node) {
+            Some(DepNodeColor::Green(_)) => "was re-executed, and its result did not change",
+            Some(DepNodeColor::Red) => "was re-executed, and its result changed",
+            None => "could not be reused, but was not executed again",
+        };
+        writeln!(out, "{} {}", node_str, outcome)?;
+        write_chain(tcx, &reasons, node, out)?;
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the dependencies that changed, starting at `node`, until reaching
+/// one whose change was not caused by another dependency.
+fn write_chain<'a, 'tcx, W: Write>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                                    reasons: &FxHashMap<DepNode, RedReason>,
+                                    node: &DepNode,
+                                    out: &mut W)
+                                    -> io::Result<()> {
+    let mut visited = FxHashSet::default();
+    let mut node = node;
+    while let Some(reason) = reasons.get(node) {
+        if !visited.insert(node) {
+            break;
+        }
+
+        let dep_node = reason.dependency();
+        let dep_node_str = dep_node_str(tcx, dep_node);
+        match *reason {
+            RedReason::DependencyRemoved(_) => {
+                return writeln!(out, "    because {} no longer exists", dep_node_str);
+            }
+            RedReason::DependencyNotForced(_) => {
+                return writeln!(out, "    because {} could not be checked for changes",
+                                dep_node_str);
+            }
+            RedReason::DependencyChanged(_) if reasons.contains_key(dep_node) => {
+                writeln!(out, "    because {} changed", dep_node_str)?;
+            }
+            RedReason::DependencyChanged(_) => {
+                return writeln!(out, "    because {} changed ({})",
+                                dep_node_str, describe_input(tcx, dep_node));
+            }
+        }
+        node = dep_node;
+    }
+
+    Ok(())
+}
+
+/// Describes the source-level input behind a node that changed although
+/// none of its dependencies did.
+fn describe_input<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>, dep_node: &DepNode) -> String {
+    let item_path = dep_node.extract_def_id(tcx).map(|def_id| tcx.item_path_str(def_id));
+    match (dep_node.kind, item_path) {
+        (DepKind::Hir, Some(path)) => format!("the HIR of `{}` was modified", path),
+        (DepKind::HirBody, Some(path)) => format!("the body of `{}` was modified", path),
+        (DepKind::CrateMetadata, Some(path)) => {
+            format!("the metadata of the crate `{}` changed", path)
+        }
+        (DepKind::Krate, _) => "the crate's source files changed".to_string(),
+        (kind, _) if kind.is_input() => "it is an input that changed".to_string(),
+        _ => "its result changed when it was re-executed".to_string(),
+    }
+}
+
+fn dep_node_str<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>, dep_node: &DepNode) -> String {
+    if let Some(def_id) = dep_node.extract_def_id(tcx) {
+        format!("{:?}({})", dep_node.kind, tcx.item_path_str(def_id))
+    } else {
+        format!("{:?}({:?})", dep_node.kind, dep_node.hash)
+    }
+}
@@ -50,6 +50,33 @@
 //! any more and will delete those. It will also delete any finalized session
 //! directories for a given crate except for the most recent one.
 //!
+//! ## Size and Age Limits
+//!
+//! The above keeps one finalized session directory per crate, but the
+//! incremental compilation directory still grows with every crate that was
+//! ever compiled into it, and with the work products (object files) of those
+//! sessions. With `-Z incremental-cache-limit=<size>` and
+//! `-Z incremental-cache-max-age=<days>`, the compiler additionally goes over
+//! the finalized session directories of *all* crates in the directory after
+//! finalizing its own session, and evicts them least recently used first: those
+//! older than the maximum age, and then as many as needed to bring the whole
+//! directory below the size limit. A session directory is evicted together
+//! with the work products it holds. Since every compilation session starts out
+//! by copying the most recent session directory of its crate into a new one,
+//! the timestamp in the name of a directory is also the time it was last used.
+//!
+//! ## Relocation
+//!
+//! Nothing in an incremental compilation directory refers to the directory
+//! itself, so it can be copied to another machine, e.g. to warm up a CI build
+//! in a fresh checkout. Whether the cached results can be reused there depends
+//! on the source files: spans are hashed by the name of their file, and the
+//! query result cache refers to files by their full path. Compiling with
+//! `--remap-path-prefix=<checkout>=<name>` makes both independent of the
+//! checkout directory. The remapping is not part of the command-line arguments
+//! that invalidate the cache, and the query result cache falls back to looking
+//! up local files by their remapped names if their original paths differ.
+//!
 //! ## Synchronization
 //!
 //! There is some synchronization needed in order for the compiler to be able to
@@ -88,6 +115,9 @@
 //! the finalized session directory of all crates it links to and while doing
 //! so, it will also place a read lock on that the respective session directory
 //! so that it won't be deleted while the metadata hashes are loaded.
+//! Evicting session directories because of the size and age limits follows
+//! the same scheme: a directory is only evicted if the exclusive lock on it
+//! can be acquired without waiting, otherwise it is skipped.
 //!
 //! ## Preconditions
 //!
@@ -373,6 +403,7 @@ pub fn finalize_session_directory(sess: &Session, svh: Svh) {
     }
 
     let _ = garbage_collect_session_directories(sess);
+    let _ = enforce_cache_limits(sess);
 }
 
 pub fn delete_all_session_dir_contents(sess: &Session) -> io::Result<()> {
@@ -852,6 +883,149 @@ pub fn garbage_collect_session_directories(sess: &Session) -> io::Result<()> {
     Ok(())
 }
 
+/// Evicts the finalized session directories of all crates in the incremental
+/// compilation directory that exceed `-Z incremental-cache-max-age` or
+/// `-Z incremental-cache-limit`, least recently used first.
+pub fn enforce_cache_limits(sess: &Session) -> io::Result<()> {
+    let limit = sess.opts.debugging_opts.incremental_cache_limit;
+    let max_age = sess.opts.debugging_opts.incremental_cache_max_age;
+    if limit.is_none() && max_age.is_none() {
+        return Ok(())
+    }
+
+    let session_directory = sess.incr_comp_session_dir();
+    let incr_comp_directory = session_directory.parent().unwrap().parent().unwrap();
+    debug!("enforce_cache_limits() - incr. comp. directory: {}",
+           incr_comp_directory.display());
+
+    // Measure everything in the directory, but only consider finalized
+    // session directories other than our own for eviction.
+    let mut total_size = 0;
+    let mut candidates = vec![];
+
+    for crate_dir_entry in incr_comp_directory.read_dir()? {
+        let crate_directory = match crate_dir_entry {
+            Ok(entry) => entry.path(),
+            Err(_) => continue,
+        };
+        let session_dir_entries = match crate_directory.read_dir() {
+            Ok(entries) => entries,
+            Err(_) => {
+                total_size += disk_usage(&crate_directory).unwrap_or(0);
+                continue
+            }
+        };
+
+        for dir_entry in session_dir_entries {
+            let dir_entry = match dir_entry {
+                Ok(dir_entry) => dir_entry,
+                Err(_) => continue,
+            };
+            let path = dir_entry.path();
+            let size = disk_usage(&path).unwrap_or(0);
+            total_size += size;
+
+            let entry_name = dir_entry.file_name();
+            let entry_name = entry_name.to_string_lossy();
+            if path == *session_directory ||
+               !is_session_directory(&entry_name) ||
+               !is_finalized(&entry_name) {
+                continue
+            }
+            if let Ok(timestamp) = extract_timestamp_from_session_dir(&entry_name) {
+                candidates.push((timestamp, path, size));
+            }
+        }
+    }
+
+    let cutoff = max_age.map(|days| {
+        let max_age = Duration::from_secs((days as u64).saturating_mul(24 * 60 * 60));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        UNIX_EPOCH + now.checked_sub(max_age).unwrap_or_default()
+    });
+
+    let mut evicted = 0;
+    let remaining_size = evict_least_recently_used(candidates, total_size, limit, cutoff, |path| {
+        debug!("enforce_cache_limits() - evicting `{}`", path.display());
+
+        // Like garbage collection, only evict directories nobody is reading from.
+        let lock = match flock::Lock::new(&lock_file_path(path),
+                                          false,  // don't wait
+                                          false,  // don't create the lock-file
+                                          true) { // get an exclusive lock
+            Ok(lock) => lock,
+            Err(_) => {
+                debug!("enforce_cache_limits() - not evicting, still in use");
+                return false
+            }
+        };
+
+        if let Err(err) = safe_remove_dir_all(path) {
+            sess.warn(&format!("Failed to evict incremental compilation session \
+                                directory `{}`: {}",
+                               path.display(),
+                               err));
+            return false
+        }
+        delete_session_dir_lock_file(sess, &lock_file_path(path));
+        mem::drop(lock);
+
+        evicted += 1;
+        true
+    });
+
+    if sess.opts.debugging_opts.incremental_info {
+        println!("[incremental] evicted {} session directories, the cache \
+                  now takes up {} bytes", evicted, remaining_size);
+    }
+
+    Ok(())
+}
+
+/// Calls `evict` for the candidates, least recently used first, as long as
+/// they are older than `cutoff` or the total size is above `limit`. `evict`
+/// returns whether the candidate could be evicted. Returns the total size
+/// after eviction.
+fn evict_least_recently_used<F>(mut candidates: Vec<(SystemTime, PathBuf, u64)>,
+                                mut total_size: u64,
+                                limit: Option<u64>,
+                                cutoff: Option<SystemTime>,
+                                mut evict: F)
+                                -> u64
+    where F: FnMut(&Path) -> bool
+{
+    candidates.sort_by_key(|&(timestamp, ..)| timestamp);
+
+    for (timestamp, path, size) in candidates {
+        let too_old = cutoff.map_or(false, |cutoff| timestamp < cutoff);
+        let too_large = limit.map_or(false, |limit| total_size > limit);
+        if !too_old && !too_large {
+            // All remaining candidates are more recent.
+            break
+        }
+
+        if evict(&path) {
+            total_size -= size;
+        }
+    }
+
+    total_size
+}
+
+/// Returns the total size of the files in `path`.
+fn disk_usage(path: &Path) -> io::Result<u64> {
+    let metadata = std_fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len())
+    }
+
+    let mut size = 0;
+    for entry in path.read_dir()? {
+        size += disk_usage(&entry?.path())?;
+    }
+    Ok(size)
+}
+
 fn all_except_most_recent(deletion_candidates: Vec<(SystemTime, PathBuf, Option<flock::Lock>)>)
                           -> FxHashMap<PathBuf, Option<flock::Lock>> {
     let most_recent = deletion_candidates.iter()
@@ -955,3 +1129,37 @@ fn test_find_source_directory_in_iter() {
              PathBuf::from("crate-dir/s-1234-0000-working")].into_iter(), &already_visited),
         None);
 }
+
+#[test]
+fn test_evict_least_recently_used() {
+    let candidates = || vec![
+        (UNIX_EPOCH + Duration::new(3, 0), PathBuf::from("3"), 30),
+        (UNIX_EPOCH + Duration::new(1, 0), PathBuf::from("1"), 10),
+        (UNIX_EPOCH + Duration::new(2, 0), PathBuf::from("2"), 20),
+    ];
+    let evict = |limit, cutoff, in_use: &str| {
+        let mut evicted = vec![];
+        let remaining = evict_least_recently_used(candidates(), 100, limit, cutoff, |path| {
+            if path == Path::new(in_use) {
+                return false
+            }
+            evicted.push(path.to_path_buf());
+            true
+        });
+        (evicted, remaining)
+    };
+
+    // Nothing to do
+    assert_eq!(evict(None, None, ""), (vec![], 100));
+    assert_eq!(evict(Some(100), None, ""), (vec![], 100));
+
+    // Evict the least recently used first, until below the limit
+    assert_eq!(evict(Some(75), None, ""), (vec![PathBuf::from("1"), PathBuf::from("2")], 70));
+
+    // Skip directories that are in use
+    assert_eq!(evict(Some(75), None, "1"), (vec![PathBuf::from("2"), PathBuf::from("3")], 50));
+
+    // Evict everything older than the cutoff
+    assert_eq!(evict(None, Some(UNIX_EPOCH + Duration::new(3, 0)), ""),
+               (vec![PathBuf::from("1"), PathBuf::from("2")], 70));
+}
@@ -4,6 +4,7 @@
 
 mod data;
 mod dirty_clean;
+mod explain;
 mod fs;
 mod load;
 mod save;
@@ -12,6 +12,7 @@ use std::path::PathBuf;
 use super::data::*;
 use super::fs::*;
 use super::dirty_clean;
+use super::explain;
 use super::file_format;
 use super::work_product;
 
@@ -47,6 +48,7 @@ pub fn save_dep_graph<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>) {
         });
 
         dirty_clean::check_dirty_clean_annotations(tcx);
+        explain::write_explanation(tcx);
     })
 }
 
@@ -17,6 +17,7 @@ use syntax;
 use syntax::source_map::{FileLoader, SourceMap};
 use util;
 use profile;
+use suggestions;
 
 pub use passes::BoxedResolver;
 
@@ -114,6 +115,15 @@ where
 
     let _sess_abort_error = OnDrop(|| compiler.sess.diagnostic().print_error_count());
 
+    // Errors often come with suggestions too, so these are also applied when compilation stops
+    // early because of an error. Guards are dropped in reverse order, so the diagnostics of
+    // applying them are still included in the error count.
+    let _apply_suggestions = OnDrop(|| {
+        if let Some(applicability) = compiler.sess.opts.debugging_opts.apply_suggestions {
+            suggestions::apply_suggestions(&compiler.sess, &compiler.source_map, applicability);
+        }
+    });
+
     if compiler.sess.profile_queries() {
         profile::begin(&compiler.sess);
     }
@@ -128,6 +138,16 @@ where
         compiler.sess.profiler(|p| p.dump_raw_events(&compiler.sess.opts));
     }
 
+    if compiler.sess.opts.debugging_opts.self_profile_folded {
+        compiler.sess.profiler(|p| p.dump_folded_stacks(&compiler.sess.opts));
+    }
+
+    compiler.sess.profiler(|p| {
+        if let Err(e) = p.write_event_stream(&compiler.sess.opts) {
+            compiler.sess.warn(&format!("failed to write the self-profile event stream: {}", e));
+        }
+    });
+
     r
 }
 
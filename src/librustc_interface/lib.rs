@@ -44,5 +44,6 @@ mod queries;
 pub mod util;
 mod proc_macro_decls;
 mod profile;
+mod suggestions;
 
 pub use interface::{run_compiler, Config};
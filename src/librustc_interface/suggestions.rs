@@ -0,0 +1,290 @@
+//! Applies the suggestions of the emitted diagnostics to the source files, for
+//! `-Z apply-suggestions`.
+//!
+//! A suggestion is only applied if it is at least as certain as the requested
+//! applicability and offers a single way of changing the code. All
+//! replacements of a suggestion are applied together or not at all, and a
+//! suggestion whose replacements overlap with those of an earlier suggestion is
+//! skipped. Every suggestion that is not applied is reported with a warning.
+//! Suggestions are applied at the end of the session, even if it ends early
+//! because of errors.
+
+use rustc::session::Session;
+use rustc_data_structures::sync::Lrc;
+use rustc_errors::{Applicability, CodeSuggestion};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+use syntax::source_map::SourceMap;
+use syntax_pos::{FileName, SourceFile};
+use syntax_pos::hygiene::SyntaxContext;
+
+/// The number of unchanged lines around each hunk of the diff.
+const CONTEXT: usize = 3;
+
+/// Replaces the bytes `lo..hi` of a source file with `snippet`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Replacement {
+    lo: usize,
+    hi: usize,
+    snippet: String,
+}
+
+impl Replacement {
+    fn overlaps(&self, other: &Replacement) -> bool {
+        // Two insertions at the same position conflict as well, as the order
+        // they would be applied in is ambiguous.
+        (self.lo < other.hi && other.lo < self.hi) || self.lo == other.lo
+    }
+}
+
+struct FileChanges {
+    file: Lrc<SourceFile>,
+    replacements: Vec<Replacement>,
+}
+
+pub fn apply_suggestions(sess: &Session, source_map: &SourceMap, required: Applicability) {
+    let mut changes = BTreeMap::new();
+    for suggestion in sess.diagnostic().take_collected_suggestions() {
+        if let Err(reason) = add_suggestion(source_map, &suggestion, required, &mut changes) {
+            let msg = format!("not applying suggestion `{}`: {}", suggestion.msg, reason);
+            let span = suggestion.substitutions.iter()
+                .flat_map(|substitution| substitution.parts.first())
+                .next()
+                .map(|part| part.span);
+            match span {
+                Some(span) => sess.span_warn(span, &msg),
+                None => sess.warn(&msg),
+            }
+        }
+    }
+
+    let mut diff = String::new();
+    for (path, mut changes) in changes {
+        changes.replacements.sort();
+        let old = match fs::read_to_string(&path) {
+            Ok(old) => old,
+            Err(e) => {
+                sess.err(&format!("failed to read `{}`: {}", path.display(), e));
+                continue;
+            }
+        };
+
+        // The source map does not contain the byte order mark.
+        let bom = if old.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        if changes.file.src.as_ref().map(|src| &src[..]) != Some(&old[bom..]) {
+            sess.warn(&format!("not applying suggestions to `{}`: the file was modified \
+                                during compilation", path.display()));
+            continue;
+        }
+        for replacement in &mut changes.replacements {
+            replacement.lo += bom;
+            replacement.hi += bom;
+        }
+
+        match sess.opts.debugging_opts.apply_suggestions_diff {
+            Some(_) => unified_diff(&mut diff, &path, &old, &changes.replacements),
+            None => {
+                let new = apply(&old, 0, &changes.replacements);
+                if let Err(e) = fs::write(&path, new) {
+                    sess.err(&format!("failed to write `{}`: {}", path.display(), e));
+                }
+            }
+        }
+    }
+
+    if let Some(ref diff_path) = sess.opts.debugging_opts.apply_suggestions_diff {
+        if let Err(e) = fs::write(diff_path, diff) {
+            sess.err(&format!("failed to write `{}`: {}", diff_path.display(), e));
+        }
+    }
+}
+
+/// Records the replacements of `suggestion`, or returns why it cannot be applied.
+fn add_suggestion(
+    source_map: &SourceMap,
+    suggestion: &CodeSuggestion,
+    required: Applicability,
+    changes: &mut BTreeMap<PathBuf, FileChanges>,
+) -> Result<(), String> {
+    if !is_certain_enough(suggestion.applicability, required) {
+        return Err(format!("its applicability is `{:?}`", suggestion.applicability));
+    }
+    let substitution = match &suggestion.substitutions[..] {
+        [substitution] => substitution,
+        _ => return Err("it offers several alternatives".to_string()),
+    };
+
+    let mut replacements: Vec<(PathBuf, Lrc<SourceFile>, Replacement)> = Vec::new();
+    for part in &substitution.parts {
+        if part.span.ctxt() != SyntaxContext::empty() {
+            return Err("it changes the expansion of a macro".to_string());
+        }
+        let lo = source_map.lookup_byte_offset(part.span.lo());
+        let hi = source_map.lookup_byte_offset(part.span.hi());
+        if !Lrc::ptr_eq(&lo.sf, &hi.sf) {
+            return Err("its span covers several files".to_string());
+        }
+        let path = match lo.sf.name {
+            FileName::Real(ref path) if !lo.sf.is_imported() && lo.sf.src.is_some() => {
+                path.clone()
+            }
+            ref name => return Err(format!("`{}` is not a local source file", name)),
+        };
+        let replacement = Replacement {
+            lo: lo.pos.0 as usize,
+            hi: hi.pos.0 as usize,
+            snippet: part.snippet.clone(),
+        };
+        replacements.push((path, lo.sf, replacement));
+    }
+
+    for (i, &(ref path, _, ref replacement)) in replacements.iter().enumerate() {
+        let earlier = changes.get(path).into_iter().flat_map(|changes| &changes.replacements);
+        let ours = replacements[..i].iter()
+            .filter(|&&(ref other_path, ..)| other_path == path)
+            .map(|&(_, _, ref other)| other);
+        // The same suggestion is often made by several diagnostics, so
+        // identical replacements do not conflict.
+        if earlier.chain(ours).any(|other| other != replacement && other.overlaps(replacement)) {
+            return Err("it overlaps with an earlier suggestion".to_string());
+        }
+    }
+
+    for (path, file, replacement) in replacements {
+        let changes = changes.entry(path).or_insert_with(|| FileChanges {
+            file,
+            replacements: Vec::new(),
+        });
+        if !changes.replacements.contains(&replacement) {
+            changes.replacements.push(replacement);
+        }
+    }
+    Ok(())
+}
+
+fn is_certain_enough(applicability: Applicability, required: Applicability) -> bool {
+    match applicability {
+        Applicability::MachineApplicable => true,
+        Applicability::MaybeIncorrect => required == Applicability::MaybeIncorrect,
+        Applicability::HasPlaceholders | Applicability::Unspecified => false,
+    }
+}
+
+/// Applies the sorted `replacements` to `text`, which starts at byte `offset`
+/// of its file.
+fn apply(text: &str, offset: usize, replacements: &[Replacement]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut pos = offset;
+    for replacement in replacements {
+        result.push_str(&text[pos - offset..replacement.lo - offset]);
+        result.push_str(&replacement.snippet);
+        pos = replacement.hi;
+    }
+    result.push_str(&text[pos - offset..]);
+    result
+}
+
+/// Appends the changes the sorted `replacements` make to `old` to `diff`, in
+/// the unified format.
+fn unified_diff(diff: &mut String, path: &Path, old: &str, replacements: &[Replacement]) {
+    if replacements.is_empty() {
+        return;
+    }
+    diff.push_str(&format!("--- {}\n+++ {}\n", path.display(), path.display()));
+
+    let old_lines = lines(old);
+    if old_lines.is_empty() {
+        let new = apply(old, 0, replacements);
+        let new_lines = lines(&new);
+        diff.push_str(&format!("@@ -0,0 +1,{} @@\n", new_lines.len()));
+        for new_line in new_lines {
+            push_line(diff, '+', new_line);
+        }
+        return;
+    }
+
+    // The byte offsets the lines of `old` start at, followed by its length.
+    let line_starts: Vec<usize> = iter::once(0)
+        .chain(old_lines.iter().scan(0, |end, line| {
+            *end += line.len();
+            Some(*end)
+        }))
+        .collect();
+    let line_of = |pos: usize| {
+        let line = match line_starts.binary_search(&pos) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        cmp::min(line, old_lines.len() - 1)
+    };
+
+    // Replacements whose hunks would share context lines go into the same hunk.
+    let mut hunks: Vec<(usize, usize, Vec<Replacement>)> = Vec::new();
+    for replacement in replacements {
+        let first = line_of(replacement.lo);
+        let last = line_of(cmp::max(replacement.lo, replacement.hi.saturating_sub(1)));
+        match hunks.last_mut() {
+            Some(hunk) if first <= hunk.1 + 2 * CONTEXT + 1 => {
+                hunk.1 = cmp::max(hunk.1, last);
+                hunk.2.push(replacement.clone());
+            }
+            _ => hunks.push((first, last, vec![replacement.clone()])),
+        }
+    }
+
+    // How many more lines the new file has than the old one before the current hunk.
+    let mut delta = 0isize;
+    for (first, last, replacements) in hunks {
+        let before = first.saturating_sub(CONTEXT);
+        let after = cmp::min(last + CONTEXT, old_lines.len() - 1);
+        let start = line_starts[first];
+        let new = apply(&old[start..line_starts[last + 1]], start, &replacements);
+        let new_lines = lines(&new);
+
+        let old_count = after + 1 - before;
+        let new_count = old_count - (last + 1 - first) + new_lines.len();
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n",
+                               before + 1,
+                               old_count,
+                               before as isize + 1 + delta,
+                               new_count));
+        for old_line in &old_lines[before..first] {
+            push_line(diff, ' ', old_line);
+        }
+        for old_line in &old_lines[first..=last] {
+            push_line(diff, '-', old_line);
+        }
+        for new_line in &new_lines {
+            push_line(diff, '+', new_line);
+        }
+        for old_line in &old_lines[last + 1..=after] {
+            push_line(diff, ' ', old_line);
+        }
+        delta += new_lines.len() as isize - (last + 1 - first) as isize;
+    }
+}
+
+/// Splits `text` into lines, keeping their line terminators.
+fn lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, _) in text.match_indices('\n') {
+        lines.push(&text[start..i + 1]);
+        start = i + 1;
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    lines
+}
+
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
@@ -198,6 +198,10 @@ pub struct RenderOptions {
     pub generate_search_filter: bool,
     /// Option (disabled by default) to generate files used by RLS and some other tools.
     pub generate_redirect_pages: bool,
+    /// Whether to skip re-rendering pages whose inputs did not change since the previous run.
+    /// Set by `-C incremental`, which also makes the analysis incremental. Cleaning the crate
+    /// is not incremental.
+    pub incremental: bool,
 }
 
 impl Options {
@@ -259,13 +263,20 @@ impl Options {
             Some("json") => ErrorOutputType::Json(false),
             Some("pretty-json") => ErrorOutputType::Json(true),
             Some("short") => ErrorOutputType::Short(color),
+            Some("sarif") => ErrorOutputType::Sarif,
             None => ErrorOutputType::HumanReadable(color),
             Some(arg) => {
                 early_error(ErrorOutputType::default(),
-                            &format!("argument for --error-format must be `human`, `json` or \
-                                      `short` (instead was `{}`)", arg));
+                            &format!("argument for --error-format must be `human`, `json`, \
+                                      `short` or `sarif` (instead was `{}`)", arg));
             }
         };
+        if error_format == ErrorOutputType::Sarif &&
+           !nightly_options::is_unstable_enabled(matches) {
+            early_error(ErrorOutputType::default(),
+                        "the `-Z unstable-options` flag must also be passed to enable \
+                         `--error-format=sarif`");
+        }
 
         let codegen_options = build_codegen_options(matches, error_format);
         let debugging_options = build_debugging_options(matches, error_format);
@@ -462,6 +473,7 @@ impl Options {
         let generate_search_filter = !matches.opt_present("disable-per-crate-search");
         let persist_doctests = matches.opt_str("persist-doctests").map(PathBuf::from);
         let generate_redirect_pages = matches.opt_present("generate-redirect-pages");
+        let incremental = codegen_options.incremental.is_some();
 
         let (lint_opts, describe_lints, lint_cap) = get_cmd_lint_options(matches, error_format);
 
@@ -508,6 +520,7 @@ impl Options {
                 markdown_playground_url,
                 generate_search_filter,
                 generate_redirect_pages,
+                incremental,
             }
         })
     }
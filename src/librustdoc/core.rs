@@ -25,10 +25,12 @@ use syntax::symbol::keywords;
 use syntax_pos::DUMMY_SP;
 use errors;
 use errors::emitter::{Emitter, EmitterWriter};
+use errors::sarif::SarifEmitter;
 use parking_lot::ReentrantMutex;
 
 use std::cell::RefCell;
 use std::mem;
+use std::path::PathBuf;
 use rustc_data_structures::sync::{self, Lrc};
 use std::sync::Arc;
 use std::rc::Rc;
@@ -280,8 +282,8 @@ impl DocAccessLevels for AccessLevels<DefId> {
 
 /// Creates a new diagnostic `Handler` that can be used to emit warnings and errors.
 ///
-/// If the given `error_format` is `ErrorOutputType::Json` and no `SourceMap` is given, a new one
-/// will be created for the handler.
+/// If the given `error_format` is `ErrorOutputType::Json` or `ErrorOutputType::Sarif` and no
+/// `SourceMap` is given, a new one will be created for the handler.
 pub fn new_handler(error_format: ErrorOutputType,
                    source_map: Option<Lrc<source_map::SourceMap>>,
                    treat_err_as_bug: Option<usize>,
@@ -317,6 +319,11 @@ pub fn new_handler(error_format: ErrorOutputType,
                 true,
                 false)
         ),
+        ErrorOutputType::Sarif => {
+            let source_map = source_map.unwrap_or_else(
+                || Lrc::new(source_map::SourceMap::new(sessopts.file_path_mapping())));
+            Box::new(SarifEmitter::stderr(None, Some(source_map)))
+        },
     };
 
     errors::Handler::with_emitter_and_flags(
@@ -401,6 +408,7 @@ pub fn run_core(options: RustdocOptions) -> (clean::Crate, RenderInfo, RenderOpt
         }
     }).collect();
 
+    let incremental = codegen_options.incremental.as_ref().map(PathBuf::from);
     let host_triple = TargetTriple::from_triple(config::host_triple());
     // plays with error output here!
     let sessopts = config::Options {
@@ -414,6 +422,7 @@ pub fn run_core(options: RustdocOptions) -> (clean::Crate, RenderInfo, RenderOpt
         },
         lint_cap: Some(lint_cap.unwrap_or_else(|| lint::Forbid)),
         cg: codegen_options,
+        incremental,
         externs,
         target_triple: target.unwrap_or(host_triple),
         // Ensure that rustdoc works even if rustc is feature-staged
@@ -574,6 +583,14 @@ pub fn run_core(options: RustdocOptions) -> (clean::Crate, RenderInfo, RenderOpt
 
             ctxt.sess().abort_if_errors();
 
+            // With `-C incremental`, save the dep-graph and the cached query
+            // results so that the next run only re-analyzes what changed. The
+            // `RustdocVisitor` and `clean` above still run in full every time.
+            if sess.opts.incremental.is_some() {
+                rustc_incremental::save_dep_graph(tcx);
+                rustc_incremental::finalize_session_directory(sess, tcx.crate_hash(LOCAL_CRATE));
+            }
+
             (krate, ctxt.renderinfo.into_inner(), render_options, passes)
         })
     })
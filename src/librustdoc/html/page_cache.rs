@@ -0,0 +1,212 @@
+//! Skips re-rendering the pages of items whose inputs did not change since the
+//! previous run of rustdoc. This is enabled by `-C incremental`, which also
+//! makes the analysis of the crate incremental. The items are still collected
+//! and cleaned in full on every run; only rendering is skipped.
+//!
+//! Every page is identified by a fingerprint of everything it is rendered
+//! from: the item itself (including its children and their documentation),
+//! the impls and implementors listed on the page, and the options that affect
+//! all pages. The fingerprints of the pages of the previous run are kept in a
+//! manifest in the output directory, and a page is only rendered again if its
+//! fingerprint changed or the file was removed.
+//!
+//! Other items are hashed by their paths rather than their `DefId`s, which
+//! change whenever items are added or removed, so that a page is only
+//! rendered again for the items it links to if those were renamed or moved.
+//! Likewise, the byte offsets of spans are not hashed, as they change with
+//! every edit earlier in the same file but do not appear on the page.
+
+use std::cell::RefCell;
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rustc::hir::def_id::DefId;
+use rustc::util::nodemap::FxHashMap;
+use rustc_data_structures::stable_hasher::StableHasher;
+use serialize::{Encodable, Encoder, SpecializedEncoder};
+use syntax_pos::Span;
+
+use crate::clean::{self, GetDefId};
+use crate::html::render::cache;
+
+const MANIFEST_HEADER: &str = "rustdoc page fingerprints, version 1";
+
+pub struct PageCache {
+    /// The file the fingerprints of the pages are stored in.
+    manifest: PathBuf,
+    /// The fingerprint of the inputs shared by all pages.
+    global: u128,
+    /// The fingerprints of the pages of the previous run.
+    previous: FxHashMap<PathBuf, u128>,
+    /// The fingerprints of the pages of this run.
+    current: RefCell<FxHashMap<PathBuf, u128>>,
+}
+
+impl PageCache {
+    /// Loads the fingerprints of the previous run. If they cannot be read, all
+    /// pages are rendered again.
+    pub fn load(manifest: PathBuf, global: u128) -> PageCache {
+        let previous = fs::read_to_string(&manifest)
+            .ok()
+            .and_then(|contents| parse_manifest(&contents))
+            .unwrap_or_default();
+        PageCache {
+            manifest,
+            global,
+            previous,
+            current: Default::default(),
+        }
+    }
+
+    /// Computes the fingerprint of the page of `item`. `redirect` is whether
+    /// the page only redirects to the real location of the item.
+    pub fn fingerprint(&self, item: &clean::Item, redirect: bool) -> u128 {
+        let cache = cache();
+        let mut hasher = PageHasher { hasher: StableHasher::new() };
+        self.global.hash(&mut hasher.hasher);
+        redirect.hash(&mut hasher.hasher);
+        hasher.hash(item);
+
+        for impl_ in cache.impls.get(&item.def_id).into_iter().flatten() {
+            hasher.hash(&impl_.impl_item);
+            // The page also lists the provided methods of implemented traits.
+            let trait_did = match impl_.impl_item.inner {
+                clean::ImplItem(ref i) => i.trait_.def_id(),
+                _ => None,
+            };
+            if let Some(trait_) = trait_did.and_then(|did| cache.traits.get(&did)) {
+                hasher.hash(trait_);
+            }
+        }
+        for impl_ in cache.implementors.get(&item.def_id).into_iter().flatten() {
+            hasher.hash(&impl_.impl_item);
+        }
+
+        hasher.hasher.finish()
+    }
+
+    /// Returns whether `dst` was written by the previous run from the same
+    /// inputs and can be kept as is. Either way, `dst` is recorded as being
+    /// rendered from `fingerprint` for the next run.
+    pub fn is_fresh(&self, dst: &Path, fingerprint: u128) -> bool {
+        self.current.borrow_mut().insert(dst.to_path_buf(), fingerprint);
+        self.previous.get(dst) == Some(&fingerprint) && dst.exists()
+    }
+
+    /// Writes the fingerprints of the pages of this run.
+    pub fn save(&self) -> io::Result<()> {
+        let current = self.current.borrow();
+        let mut pages: Vec<_> = current.iter().collect();
+        pages.sort();
+
+        let mut contents = format!("{}\n", MANIFEST_HEADER);
+        for (path, fingerprint) in pages {
+            if let Some(path) = path.to_str() {
+                contents.push_str(&format!("{:032x} {}\n", fingerprint, path));
+            }
+        }
+        fs::write(&self.manifest, contents)
+    }
+}
+
+fn parse_manifest(contents: &str) -> Option<FxHashMap<PathBuf, u128>> {
+    let mut lines = contents.lines();
+    if lines.next() != Some(MANIFEST_HEADER) {
+        return None;
+    }
+    lines.map(|line| {
+        let mut parts = line.splitn(2, ' ');
+        let fingerprint = u128::from_str_radix(parts.next()?, 16).ok()?;
+        let path = PathBuf::from(parts.next()?);
+        Some((path, fingerprint))
+    }).collect()
+}
+
+/// An `Encoder` that hashes everything it is given instead of writing it out.
+struct PageHasher {
+    hasher: StableHasher<u128>,
+}
+
+impl PageHasher {
+    fn hash<T: Encodable>(&mut self, value: &T) {
+        match value.encode(self) {
+            Ok(()) => {}
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl SpecializedEncoder<DefId> for PageHasher {
+    fn specialized_encode(&mut self, def_id: &DefId) -> Result<(), Self::Error> {
+        let cache = cache();
+        match cache.paths.get(def_id).or_else(|| cache.external_paths.get(def_id)) {
+            Some(&(ref path, item_type)) => {
+                path.encode(self)?;
+                self.emit_str(item_type.css_class())
+            }
+            None => {
+                self.emit_u32(def_id.krate.as_u32())?;
+                self.emit_u32(def_id.index.as_raw_u32())
+            }
+        }
+    }
+}
+
+impl SpecializedEncoder<Span> for PageHasher {
+    fn specialized_encode(&mut self, _: &Span) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+macro_rules! hash_primitives {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method(&mut self, v: $ty) -> Result<(), Self::Error> {
+                v.hash(&mut self.hasher);
+                Ok(())
+            }
+        )*
+    }
+}
+
+impl Encoder for PageHasher {
+    type Error = Infallible;
+
+    hash_primitives! {
+        emit_usize(usize);
+        emit_u128(u128);
+        emit_u64(u64);
+        emit_u32(u32);
+        emit_u16(u16);
+        emit_u8(u8);
+        emit_isize(isize);
+        emit_i128(i128);
+        emit_i64(i64);
+        emit_i32(i32);
+        emit_i16(i16);
+        emit_i8(i8);
+        emit_bool(bool);
+        emit_char(char);
+        emit_str(&str);
+    }
+
+    fn emit_unit(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn emit_f64(&mut self, v: f64) -> Result<(), Self::Error> {
+        v.to_bits().hash(&mut self.hasher);
+        Ok(())
+    }
+
+    fn emit_f32(&mut self, v: f32) -> Result<(), Self::Error> {
+        v.to_bits().hash(&mut self.hasher);
+        Ok(())
+    }
+}
+
+/// The error type of `PageHasher`, which cannot fail.
+#[derive(Debug)]
+enum Infallible {}
@@ -34,6 +34,7 @@ use std::error;
 use std::fmt::{self, Display, Formatter, Write as FmtWrite};
 use std::ffi::OsStr;
 use std::fs::{self, File, OpenOptions};
+use std::hash::Hash;
 use std::io::prelude::*;
 use std::io::{self, BufWriter, BufReader};
 use std::mem;
@@ -54,6 +55,7 @@ use rustc::middle::stability;
 use rustc::hir;
 use rustc::util::nodemap::{FxHashMap, FxHashSet};
 use rustc_data_structures::flock;
+use rustc_data_structures::stable_hasher::StableHasher;
 
 use crate::clean::{self, AttributesExt, Deprecation, GetDefId, SelfTy, Mutability};
 use crate::config::RenderOptions;
@@ -67,6 +69,7 @@ use crate::html::format::fmt_impl_for_trait_page;
 use crate::html::item_type::ItemType;
 use crate::html::markdown::{self, Markdown, MarkdownHtml, MarkdownSummaryLine, ErrorCodes, IdMap};
 use crate::html::{highlight, layout, static_files};
+use crate::html::page_cache::PageCache;
 
 use minifier;
 
@@ -108,6 +111,9 @@ struct Context {
     /// The map used to ensure all generated 'id=' attributes are unique.
     id_map: Rc<RefCell<IdMap>>,
     pub shared: Arc<SharedContext>,
+    /// The fingerprints of the pages rendered by the previous run, used to
+    /// skip the pages whose inputs did not change with `-C incremental`.
+    page_cache: Option<Rc<PageCache>>,
 }
 
 struct SharedContext {
@@ -182,6 +188,7 @@ impl SharedContext {
 }
 
 /// Indicates where an external crate can be found.
+#[derive(Hash)]
 pub enum ExternalLocation {
     /// Remote URL root of the external crate
     Remote(String),
@@ -519,6 +526,7 @@ pub fn run(mut krate: clean::Crate,
         static_root_path,
         generate_search_filter,
         generate_redirect_pages,
+        incremental,
         ..
     } = options;
 
@@ -589,13 +597,14 @@ pub fn run(mut krate: clean::Crate,
     let dst = output;
     try_err!(fs::create_dir_all(&dst), &dst);
     krate = render_sources(&dst, &mut scx, krate)?;
-    let cx = Context {
+    let mut cx = Context {
         current: Vec::new(),
         dst,
         render_redirect_pages: false,
         codes: ErrorCodes::from(UnstableFeatures::from_environment().is_nightly_build()),
         id_map: Rc::new(RefCell::new(id_map)),
         shared: Arc::new(scx),
+        page_cache: None,
     };
 
     // Crawl the crate to build various caches used for the output
@@ -692,10 +701,49 @@ pub fn run(mut krate: clean::Crate,
 
     write_shared(&cx, &krate, &*cache, index, &md_opts, diag)?;
 
+    if incremental {
+        let manifest = cx.dst.join(&krate.name).join(".rustdoc-pages");
+        let global = global_fingerprint(&cx, &cache);
+        cx.page_cache = Some(Rc::new(PageCache::load(manifest, global)));
+    }
+
     // And finally render the whole crate's documentation
     cx.krate(krate)
 }
 
+/// Hashes the inputs that affect every page, for `PageCache`.
+fn global_fingerprint(cx: &Context, cache: &Cache) -> u128 {
+    let mut hasher = StableHasher::new();
+    option_env!("CFG_VERSION").hash(&mut hasher);
+    cx.codes.hash(&mut hasher);
+
+    let shared = &cx.shared;
+    let layout = &shared.layout;
+    (&layout.logo, &layout.favicon, &layout.krate).hash(&mut hasher);
+    let external_html = &layout.external_html;
+    (&external_html.in_header, &external_html.before_content, &external_html.after_content)
+        .hash(&mut hasher);
+    (shared.include_sources, &shared.issue_tracker_base_url, &shared.css_file_extension)
+        .hash(&mut hasher);
+    (shared.sort_modules_alphabetically, &shared.themes, &shared.resource_suffix).hash(&mut hasher);
+    (&shared.static_root_path, shared.generate_search_filter, shared.generate_redirect_pages)
+        .hash(&mut hasher);
+    let mut passes: Vec<_> = shared.passes.iter().collect();
+    passes.sort();
+    passes.hash(&mut hasher);
+    let mut local_sources: Vec<_> = shared.local_sources.iter().collect();
+    local_sources.sort();
+    local_sources.hash(&mut hasher);
+
+    markdown::PLAYGROUND.with(|playground| playground.borrow().hash(&mut hasher));
+    cache.crate_version.hash(&mut hasher);
+    let mut extern_locations: Vec<_> = cache.extern_locations.values().collect();
+    extern_locations.sort_by(|a, b| a.0.cmp(&b.0));
+    extern_locations.hash(&mut hasher);
+
+    hasher.finish()
+}
+
 /// Builds the search index from the collected metadata
 fn build_index(krate: &clean::Crate, cache: &mut Cache) -> String {
     let mut nodeid_to_pathid = FxHashMap::default();
@@ -2058,6 +2106,11 @@ impl Context {
             }
         }
 
+        if let Some(ref page_cache) = self.page_cache {
+            let manifest = self.dst.join(&crate_name).join(".rustdoc-pages");
+            try_err!(page_cache.save(), &manifest);
+        }
+
         let mut w = BufWriter::new(try_err!(File::create(&final_file), &final_file));
         let mut root_path = self.dst.to_str().expect("invalid path").to_owned();
         if !root_path.ends_with('/') {
@@ -2210,13 +2263,15 @@ impl Context {
             self.recurse(name, |this| {
                 let item = item.take().unwrap();
 
-                let mut buf = Vec::new();
-                this.render_item(&mut buf, &item, false).unwrap();
-                // buf will be empty if the module is stripped and there is no redirect for it
-                if !buf.is_empty() {
-                    try_err!(this.shared.ensure_dir(&this.dst), &this.dst);
-                    let joint_dst = this.dst.join("index.html");
-                    try_err!(fs::write(&joint_dst, buf), &joint_dst);
+                let joint_dst = this.dst.join("index.html");
+                if !this.is_page_fresh(&joint_dst, &item) {
+                    let mut buf = Vec::new();
+                    this.render_item(&mut buf, &item, false).unwrap();
+                    // buf will be empty if the module is stripped and there is no redirect for it
+                    if !buf.is_empty() {
+                        try_err!(this.shared.ensure_dir(&this.dst), &this.dst);
+                        try_err!(fs::write(&joint_dst, buf), &joint_dst);
+                    }
                 }
 
                 let m = match item.inner {
@@ -2241,16 +2296,21 @@ impl Context {
                 Ok(())
             })?;
         } else if item.name.is_some() {
+            let name = item.name.as_ref().unwrap();
+            let item_type = item.type_();
+            let file_name = &item_path(item_type, name);
+            let joint_dst = self.dst.join(file_name);
             let mut buf = Vec::new();
-            self.render_item(&mut buf, &item, true).unwrap();
+            let fresh = self.is_page_fresh(&joint_dst, &item);
+            if !fresh {
+                self.render_item(&mut buf, &item, true).unwrap();
+            }
             // buf will be empty if the item is stripped and there is no redirect for it
-            if !buf.is_empty() {
-                let name = item.name.as_ref().unwrap();
-                let item_type = item.type_();
-                let file_name = &item_path(item_type, name);
-                try_err!(self.shared.ensure_dir(&self.dst), &self.dst);
-                let joint_dst = self.dst.join(file_name);
-                try_err!(fs::write(&joint_dst, buf), &joint_dst);
+            if fresh || !buf.is_empty() {
+                if !fresh {
+                    try_err!(self.shared.ensure_dir(&self.dst), &self.dst);
+                    try_err!(fs::write(&joint_dst, buf), &joint_dst);
+                }
 
                 if !self.render_redirect_pages {
                     all.append(full_path(self, &item), &item_type);
@@ -2281,6 +2341,18 @@ impl Context {
         Ok(())
     }
 
+    /// Returns whether the page of `item` at `dst` was rendered by the previous
+    /// run from the same inputs, and thus does not need to be rendered again.
+    fn is_page_fresh(&self, dst: &Path, item: &clean::Item) -> bool {
+        match self.page_cache {
+            Some(ref page_cache) => {
+                let fingerprint = page_cache.fingerprint(item, self.render_redirect_pages);
+                page_cache.is_fresh(dst, fingerprint)
+            }
+            None => false,
+        }
+    }
+
     fn build_sidebar_items(&self, m: &clean::Module) -> BTreeMap<String, Vec<NameDoc>> {
         // BTreeMap instead of HashMap to get a sorted output
         let mut map: BTreeMap<_, Vec<_>> = BTreeMap::new();
@@ -15,6 +15,7 @@
 #![feature(crate_visibility_modifier)]
 #![feature(const_fn)]
 #![feature(drain_filter)]
+#![feature(specialization)]
 #![feature(inner_deref)]
 
 #![recursion_limit="256"]
@@ -24,6 +25,7 @@ extern crate env_logger;
 extern crate rustc;
 extern crate rustc_data_structures;
 extern crate rustc_driver;
+extern crate rustc_incremental;
 extern crate rustc_resolve;
 extern crate rustc_lint;
 extern crate rustc_interface;
@@ -62,6 +64,7 @@ pub mod html {
     crate mod item_type;
     crate mod format;
     crate mod layout;
+    crate mod page_cache;
     pub mod markdown;
     crate mod render;
     crate mod static_files;
@@ -284,7 +287,7 @@ fn opts() -> Vec<RustcOptGroup> {
             o.optopt("",
                      "error-format",
                      "How errors and other messages are produced",
-                     "human|json|short")
+                     "human|json|short|sarif")
         }),
         unstable("disable-minification", |o| {
              o.optflag("",
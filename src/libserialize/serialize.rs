@@ -7,6 +7,7 @@ Core encoding and decoding interfaces.
 use std::borrow::Cow;
 use std::intrinsics;
 use std::marker::PhantomData;
+use std::ops;
 use std::path;
 use std::rc::Rc;
 use std::cell::{Cell, RefCell};
@@ -719,6 +720,25 @@ impl<T1:Decodable, T2:Decodable> Decodable for Result<T1, T2> {
     }
 }
 
+impl<T: Encodable> Encodable for ops::RangeInclusive<T> {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("RangeInclusive", 2, |s| {
+            s.emit_struct_field("start", 0, |s| self.start().encode(s))?;
+            s.emit_struct_field("end", 1, |s| self.end().encode(s))
+        })
+    }
+}
+
+impl<T: Decodable> Decodable for ops::RangeInclusive<T> {
+    fn decode<D: Decoder>(d: &mut D) -> Result<ops::RangeInclusive<T>, D::Error> {
+        d.read_struct("RangeInclusive", 2, |d| {
+            let start = d.read_struct_field("start", 0, |d| T::decode(d))?;
+            let end = d.read_struct_field("end", 1, |d| T::decode(d))?;
+            Ok(ops::RangeInclusive::new(start, end))
+        })
+    }
+}
+
 macro_rules! peel {
     ($name:ident, $($other:ident,)*) => (tuple! { $($other,)* })
 }
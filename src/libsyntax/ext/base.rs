@@ -1023,7 +1023,9 @@ pub fn check_zero_tts(cx: &ExtCtxt<'_>,
                       tts: &[tokenstream::TokenTree],
                       name: &str) {
     if !tts.is_empty() {
-        cx.span_err(sp, &format!("{} takes no arguments", name));
+        cx.struct_span_err(sp, &format!("{} takes no arguments", name))
+            .slug("expand::unexpected_macro_arguments")
+            .emit();
     }
 }
 
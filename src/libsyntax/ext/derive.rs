@@ -25,7 +25,9 @@ pub fn collect_derives(cx: &mut ExtCtxt<'_>, attrs: &mut Vec<ast::Attribute>) ->
         match attr.parse_list(cx.parse_sess,
                               |parser| parser.parse_path_allowing_meta(PathStyle::Mod)) {
             Ok(ref traits) if traits.is_empty() => {
-                cx.span_warn(attr.span, "empty trait list in `derive`");
+                cx.struct_span_warn(attr.span, "empty trait list in `derive`")
+                    .slug("expand::empty_derive")
+                    .emit();
                 false
             }
             Ok(traits) => {
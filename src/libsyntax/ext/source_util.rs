@@ -137,11 +137,15 @@ pub fn expand_include_str(cx: &mut ExtCtxt<'_>, sp: Span, tts: &[tokenstream::To
             base::MacEager::expr(cx.expr_str(sp, interned_src))
         },
         Err(ref e) if e.kind() == ErrorKind::InvalidData => {
-            cx.span_err(sp, &format!("{} wasn't a utf-8 file", file.display()));
+            cx.struct_span_err(sp, &format!("{} wasn't a utf-8 file", file.display()))
+                .slug("expand::include_file_not_utf8")
+                .emit();
             DummyResult::expr(sp)
         }
         Err(e) => {
-            cx.span_err(sp, &format!("couldn't read {}: {}", file.display(), e));
+            cx.struct_span_err(sp, &format!("couldn't read {}: {}", file.display(), e))
+                .slug("expand::include_file_unreadable")
+                .emit();
             DummyResult::expr(sp)
         }
     }
@@ -169,7 +173,9 @@ pub fn expand_include_bytes(cx: &mut ExtCtxt<'_>, sp: Span, tts: &[tokenstream::
             base::MacEager::expr(cx.expr_lit(sp, ast::LitKind::ByteStr(Lrc::new(bytes))))
         },
         Err(e) => {
-            cx.span_err(sp, &format!("couldn't read {}: {}", file.display(), e));
+            cx.struct_span_err(sp, &format!("couldn't read {}: {}", file.display(), e))
+                .slug("expand::include_file_unreadable")
+                .emit();
             DummyResult::expr(sp)
         }
     }
@@ -474,6 +474,12 @@ declare_features! (
 
     // Allows the use of C-variadics
     (active, c_variadic, "1.34.0", Some(44930), None),
+
+    // Allows a test to be killed after running for too long.
+    (active, test_timeout, "1.35.0", None, None),
+
+    // Allows `#[test_setup]` and `#[test_teardown]` functions.
+    (active, test_fixtures, "1.35.0", None, None),
 );
 
 declare_features! (
@@ -1226,6 +1232,11 @@ pub const BUILTIN_ATTRIBUTES: &[(&str, AttributeType, AttributeTemplate, Attribu
                                  "allow_fail attribute is currently unstable",
                                  cfg_fn!(allow_fail))),
 
+    ("test_timeout", Normal, template!(NameValueStr: "seconds"), Gated(Stability::Unstable,
+                                 "test_timeout",
+                                 "the test_timeout attribute is currently unstable",
+                                 cfg_fn!(test_timeout))),
+
     ("rustc_std_internal_symbol", Whitelisted, template!(Word), Gated(Stability::Unstable,
                                      "rustc_attrs",
                                      "this is an internal attribute that will \
@@ -1489,6 +1500,9 @@ pub const EXPLAIN_GLOBAL_ASM: &str =
 pub const EXPLAIN_CUSTOM_TEST_FRAMEWORKS: &str =
     "custom test frameworks are an unstable feature";
 
+pub const EXPLAIN_TEST_FIXTURES: &str =
+    "`#[test_setup]` and `#[test_teardown]` are an unstable feature";
+
 pub const EXPLAIN_LOG_SYNTAX: &str =
     "`log_syntax!` is not stable enough for use and is subject to change";
 
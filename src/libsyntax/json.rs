@@ -11,8 +11,8 @@
 
 use crate::source_map::{SourceMap, FilePathMapping};
 
-use errors::registry::Registry;
-use errors::{DiagnosticBuilder, SubDiagnostic, CodeSuggestion, SourceMapper};
+use errors::registry::{Explanation, Registry};
+use errors::{DiagnosticBuilder, SubDiagnostic, CodeSuggestion, SourceMapper, SourceMapperDyn};
 use errors::{DiagnosticId, Applicability};
 use errors::emitter::{Emitter, EmitterWriter};
 
@@ -81,6 +81,10 @@ impl Emitter for JsonEmitter {
             panic!("failed to print diagnostics: {:?}", e);
         }
     }
+
+    fn source_map(&self) -> Option<&Lrc<SourceMapperDyn>> {
+        Some(&self.sm)
+    }
 }
 
 // The following data types are provided just for serialisation.
@@ -90,6 +94,9 @@ struct Diagnostic {
     /// The primary error message.
     message: String,
     code: Option<DiagnosticCode>,
+    /// The stable identifier of the kind of diagnostic, like `error::E0308`, if it has one.
+    /// See `errors::Diagnostic::get_slug`.
+    slug: Option<String>,
     /// "error: internal compiler error", "error", "warning", "note", "help".
     level: &'static str,
     spans: Vec<DiagnosticSpan>,
@@ -157,6 +164,8 @@ struct DiagnosticCode {
     code: String,
     /// An explanation for the code.
     explanation: Option<&'static str>,
+    /// The sections of the explanation, for tools that show them separately.
+    sections: Option<Explanation>,
 }
 
 impl Diagnostic {
@@ -167,6 +176,7 @@ impl Diagnostic {
             Diagnostic {
                 message: sugg.msg.clone(),
                 code: None,
+                slug: None,
                 level: "help",
                 spans: DiagnosticSpan::from_suggestion(sugg, je),
                 children: vec![],
@@ -198,6 +208,7 @@ impl Diagnostic {
         Diagnostic {
             message: db.message(),
             code: DiagnosticCode::map_opt_string(db.code.clone(), je),
+            slug: db.get_slug(),
             level: db.level.to_str(),
             spans: DiagnosticSpan::from_multispan(&db.span, je),
             children: db.children.iter().map(|c| {
@@ -211,6 +222,7 @@ impl Diagnostic {
         Diagnostic {
             message: db.message(),
             code: None,
+            slug: None,
             level: db.level.to_str(),
             spans: db.render_span.as_ref()
                      .map(|sp| DiagnosticSpan::from_multispan(sp, je))
@@ -374,10 +386,12 @@ impl DiagnosticCode {
             let explanation = je.registry
                                 .as_ref()
                                 .and_then(|registry| registry.find_description(&s));
+            let sections = explanation.map(Explanation::parse);
 
             DiagnosticCode {
                 code: s,
                 explanation,
+                sections,
             }
         })
     }
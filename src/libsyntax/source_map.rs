@@ -116,6 +116,15 @@ impl StableSourceFileId {
 
         StableSourceFileId(hasher.finish())
     }
+
+    /// Like `new`, but ignores the path the file was loaded from before
+    /// `--remap-path-prefix` was applied. Files whose paths were remapped thus get
+    /// the same id no matter which directory they were compiled in.
+    pub fn new_relocatable(source_file: &SourceFile) -> StableSourceFileId {
+        StableSourceFileId::new_from_pieces(&source_file.name,
+                                         source_file.name_was_remapped,
+                                         None)
+    }
 }
 
 // _____________________________________________________________________________
@@ -178,6 +187,16 @@ impl SourceMap {
         self.files.borrow().stable_id_to_source_file.get(&stable_id).map(|sf| sf.clone())
     }
 
+    /// Looks up a file of the local crate by the id returned by
+    /// `StableSourceFileId::new_relocatable`. If several files have the same
+    /// (remapped) name, the one that was loaded first is returned.
+    pub fn local_source_file_by_relocatable_id(&self, relocatable_id: StableSourceFileId) ->
+    Option<Lrc<SourceFile>> {
+        self.files.borrow().source_files.iter().find(|sf| {
+            sf.crate_of_origin == 0 && StableSourceFileId::new_relocatable(sf) == relocatable_id
+        }).cloned()
+    }
+
     fn next_start_pos(&self) -> usize {
         match self.files.borrow().source_files.last() {
             None => 0,
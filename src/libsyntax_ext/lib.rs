@@ -95,6 +95,9 @@ pub fn register_builtins(resolver: &mut dyn syntax::ext::base::Resolver,
     register(Symbol::intern("test_case"), MultiModifier(Box::new(test_case::expand)));
     register(Symbol::intern("test"), MultiModifier(Box::new(test::expand_test)));
     register(Symbol::intern("bench"), MultiModifier(Box::new(test::expand_bench)));
+    register(Symbol::intern("test_setup"), MultiModifier(Box::new(test::expand_test_setup)));
+    register(Symbol::intern("test_teardown"),
+             MultiModifier(Box::new(test::expand_test_teardown)));
 
     // format_args uses `unstable` things internally.
     register(Symbol::intern("format_args"),
@@ -6,19 +6,30 @@ use syntax::ext::build::AstBuilder;
 use syntax::ext::hygiene::{self, Mark, SyntaxContext};
 use syntax::attr;
 use syntax::ast;
+use syntax::feature_gate;
 use syntax::print::pprust;
 use syntax::symbol::Symbol;
 use syntax_pos::{DUMMY_SP, Span};
 use syntax::source_map::{ExpnInfo, MacroAttribute};
 use std::iter;
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum TestKind {
+    Test,
+    Bench,
+    /// A `#[test_setup]` function, run before the tests of its module.
+    Setup,
+    /// A `#[test_teardown]` function, run after the tests of its module.
+    Teardown,
+}
+
 pub fn expand_test(
     cx: &mut ExtCtxt<'_>,
     attr_sp: Span,
     _meta_item: &ast::MetaItem,
     item: Annotatable,
 ) -> Vec<Annotatable> {
-    expand_test_or_bench(cx, attr_sp, item, false)
+    expand_test_or_bench(cx, attr_sp, item, TestKind::Test)
 }
 
 pub fn expand_bench(
@@ -27,15 +38,42 @@ pub fn expand_bench(
     _meta_item: &ast::MetaItem,
     item: Annotatable,
 ) -> Vec<Annotatable> {
-    expand_test_or_bench(cx, attr_sp, item, true)
+    expand_test_or_bench(cx, attr_sp, item, TestKind::Bench)
+}
+
+pub fn expand_test_setup(
+    cx: &mut ExtCtxt<'_>,
+    attr_sp: Span,
+    _meta_item: &ast::MetaItem,
+    item: Annotatable,
+) -> Vec<Annotatable> {
+    expand_test_or_bench(cx, attr_sp, item, TestKind::Setup)
+}
+
+pub fn expand_test_teardown(
+    cx: &mut ExtCtxt<'_>,
+    attr_sp: Span,
+    _meta_item: &ast::MetaItem,
+    item: Annotatable,
+) -> Vec<Annotatable> {
+    expand_test_or_bench(cx, attr_sp, item, TestKind::Teardown)
 }
 
 pub fn expand_test_or_bench(
     cx: &mut ExtCtxt<'_>,
     attr_sp: Span,
     item: Annotatable,
-    is_bench: bool
+    kind: TestKind
 ) -> Vec<Annotatable> {
+    let is_fixture = kind == TestKind::Setup || kind == TestKind::Teardown;
+    if is_fixture && !cx.ecfg.enable_test_fixtures() {
+        feature_gate::emit_feature_err(&cx.parse_sess,
+                                       "test_fixtures",
+                                       attr_sp,
+                                       feature_gate::GateIssue::Language,
+                                       feature_gate::EXPLAIN_TEST_FIXTURES);
+    }
+
     // If we're not in test configuration, remove the annotated item
     if !cx.ecfg.should_test { return vec![]; }
 
@@ -55,8 +93,12 @@ pub fn expand_test_or_bench(
     // has_*_signature will report any errors in the type so compilation
     // will fail. We shouldn't try to expand in this case because the errors
     // would be spurious.
-    if (!is_bench && !has_test_signature(cx, &item)) ||
-        (is_bench && !has_bench_signature(cx, &item)) {
+    let has_signature = match kind {
+        TestKind::Test => has_test_signature(cx, &item),
+        TestKind::Bench => has_bench_signature(cx, &item),
+        TestKind::Setup | TestKind::Teardown => has_fixture_signature(cx, &item),
+    };
+    if !has_signature {
         return vec![Annotatable::Item(item)];
     }
 
@@ -94,7 +136,7 @@ pub fn expand_test_or_bench(
     // creates $name: $expr
     let field = |name, expr| cx.field_imm(sp, cx.ident_of(name), expr);
 
-    let test_fn = if is_bench {
+    let test_fn = if kind == TestKind::Bench {
         // A simple ident for a lambda
         let b = cx.ident_of("b");
 
@@ -112,7 +154,12 @@ pub fn expand_test_or_bench(
             // )
         ])
     } else {
-        cx.expr_call(sp, cx.expr_path(test_path("StaticTestFn")), vec![
+        let variant = match kind {
+            TestKind::Setup => "StaticSetupFn",
+            TestKind::Teardown => "StaticTeardownFn",
+            _ => "StaticTestFn",
+        };
+        cx.expr_call(sp, cx.expr_path(test_path(variant)), vec![
             // || {
             cx.lambda0(sp,
                 // test::assert_test_result(
@@ -155,6 +202,13 @@ pub fn expand_test_or_bench(
                     field("ignore", cx.expr_bool(sp, should_ignore(&item))),
                     // allow_fail: true | false
                     field("allow_fail", cx.expr_bool(sp, should_fail(&item))),
+                    // timeout: Some(...) | None
+                    field("timeout", match test_timeout(cx, &item) {
+                        Some(secs) => cx.expr_some(sp, cx.expr_lit(sp,
+                            ast::LitKind::Int(secs as u128,
+                                              ast::LitIntType::Unsigned(ast::UintTy::U64)))),
+                        None => cx.expr_none(sp),
+                    }),
                     // should_panic: ...
                     field("should_panic", match should_panic(cx, &item) {
                         // test::ShouldPanic::No
@@ -168,7 +222,7 @@ pub fn expand_test_or_bench(
                     }),
                 // },
                 ])),
-                // testfn: test::StaticTestFn(...) | test::StaticBenchFn(...)
+                // testfn: test::StaticTestFn(...) | test::StaticBenchFn(...) | ...
                 field("testfn", test_fn)
             // }
             ])
@@ -213,6 +267,21 @@ fn should_fail(i: &ast::Item) -> bool {
     attr::contains_name(&i.attrs, "allow_fail")
 }
 
+fn test_timeout(cx: &ExtCtxt<'_>, i: &ast::Item) -> Option<u64> {
+    let attr = attr::find_by_name(&i.attrs, "test_timeout")?;
+    match attr.value_str().and_then(|s| s.as_str().parse::<u64>().ok()) {
+        Some(secs) if secs > 0 => Some(secs),
+        _ => {
+            cx.parse_sess.span_diagnostic.span_err(
+                attr.span,
+                "argument must be of the form: `test_timeout = \"seconds\"` \
+                 with a number of seconds greater than zero"
+            );
+            None
+        }
+    }
+}
+
 fn should_panic(cx: &ExtCtxt<'_>, i: &ast::Item) -> ShouldPanic {
     match attr::find_by_name(&i.attrs, "should_panic") {
         Some(attr) => {
@@ -299,6 +368,25 @@ fn has_test_signature(cx: &ExtCtxt<'_>, i: &ast::Item) -> bool {
     }
 }
 
+fn has_fixture_signature(cx: &ExtCtxt<'_>, i: &ast::Item) -> bool {
+    let ref sd = cx.parse_sess.span_diagnostic;
+    if let ast::ItemKind::Fn(ref decl, ref header, ref generics, _) = i.node {
+        if header.unsafety == ast::Unsafety::Unsafe || header.asyncness.node.is_async() {
+            sd.span_err(i.span, "unsafe and async functions cannot be used as test fixtures");
+            return false;
+        }
+        if !decl.inputs.is_empty() || !generics.params.is_empty() {
+            sd.span_err(i.span, "functions used as test fixtures must have signature \
+                `fn() -> impl Termination`");
+            return false;
+        }
+        true
+    } else {
+        sd.span_err(i.span, "only functions may be used as test fixtures");
+        false
+    }
+}
+
 fn has_bench_signature(cx: &ExtCtxt<'_>, i: &ast::Item) -> bool {
     let has_sig = if let ast::ItemKind::Fn(ref decl, _, _, _) = i.node {
         // N.B., inadequate check, but we're running
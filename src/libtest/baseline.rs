@@ -0,0 +1,304 @@
+//! Saving benchmark results to disk and comparing later runs against them.
+//!
+//! A baseline is a text file with one line per benchmark holding the fields of
+//! its `stats::Summary` followed by its name, e.g.:
+//!
+//! ```text
+//! # libtest benchmark baseline v1
+//! 51234 980 1102 1024.68 1021 3401.2 58.32 5.69 50.4 4.93 990 1021 1055 65 bench::parse
+//! ```
+//!
+//! Baselines are stored per test binary, so benchmarks from different crates
+//! saved under the same baseline name do not clash.
+
+use crate::stats::{self, Summary};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "# libtest benchmark baseline v1";
+
+/// Magnitude of Welch's t statistic above which a difference between two
+/// benchmark runs is considered significant (roughly 95% confidence).
+const SIGNIFICANCE_T: f64 = 2.0;
+
+/// The saved benchmark summaries of one baseline, by benchmark name.
+#[derive(Clone, Default)]
+pub struct Baseline {
+    summaries: BTreeMap<String, Summary>,
+}
+
+impl Baseline {
+    pub fn new() -> Baseline {
+        Baseline::default()
+    }
+
+    pub fn insert(&mut self, name: &str, summary: Summary) {
+        self.summaries.insert(name.to_owned(), summary);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Summary> {
+        self.summaries.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summaries.is_empty()
+    }
+
+    /// Loads the baseline called `name` from `dir`.
+    pub fn load(dir: &Path, name: &str) -> io::Result<Baseline> {
+        let path = baseline_path(dir, name);
+        let file = fs::File::open(&path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("failed to open baseline `{}`: {}", path.display(), e),
+            )
+        })?;
+
+        let mut baseline = Baseline::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let (name, summary) = parse_line(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed line {} in baseline `{}`", i + 1, path.display()),
+                )
+            })?;
+            baseline.summaries.insert(name, summary);
+        }
+        Ok(baseline)
+    }
+
+    /// Saves this baseline as `name` in `dir`, replacing any previous baseline
+    /// of that name.
+    pub fn save(&self, dir: &Path, name: &str) -> io::Result<()> {
+        let path = baseline_path(dir, name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = io::BufWriter::new(fs::File::create(&path)?);
+        writeln!(file, "{}", HEADER)?;
+        for (name, s) in &self.summaries {
+            writeln!(
+                file,
+                "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                s.sum,
+                s.min,
+                s.max,
+                s.mean,
+                s.median,
+                s.var,
+                s.std_dev,
+                s.std_dev_pct,
+                s.median_abs_dev,
+                s.median_abs_dev_pct,
+                s.quartiles.0,
+                s.quartiles.1,
+                s.quartiles.2,
+                s.iqr,
+                name
+            )?;
+        }
+        file.flush()
+    }
+
+    /// Saves this baseline as `name` in `dir`, keeping the saved summaries of
+    /// the benchmarks it has no summary for, e.g. because they were filtered
+    /// out of the run.
+    pub fn merge_into(&self, dir: &Path, name: &str) -> io::Result<()> {
+        let mut merged = match Baseline::load(dir, name) {
+            Ok(baseline) => baseline,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Baseline::new(),
+            Err(e) => return Err(e),
+        };
+        merged
+            .summaries
+            .extend(self.summaries.iter().map(|(name, s)| (name.clone(), *s)));
+        merged.save(dir, name)
+    }
+}
+
+/// The directory baselines are stored in when `--baseline-dir` is not given:
+/// a `baselines` directory next to the test binary.
+pub fn default_dir() -> PathBuf {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("baselines")))
+        .unwrap_or_else(|| PathBuf::from("baselines"))
+}
+
+fn baseline_path(dir: &Path, name: &str) -> PathBuf {
+    let binary = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "test".to_owned());
+    dir.join(name).join(binary)
+}
+
+fn parse_line(line: &str) -> Option<(String, Summary)> {
+    let mut fields = line.splitn(15, ' ');
+    let mut values = [0.0; 14];
+    for value in values.iter_mut() {
+        *value = fields.next()?.parse().ok()?;
+    }
+    let name = fields.next()?.to_owned();
+
+    let summary = Summary {
+        sum: values[0],
+        min: values[1],
+        max: values[2],
+        mean: values[3],
+        median: values[4],
+        var: values[5],
+        std_dev: values[6],
+        std_dev_pct: values[7],
+        median_abs_dev: values[8],
+        median_abs_dev_pct: values[9],
+        quartiles: (values[10], values[11], values[12]),
+        iqr: values[13],
+    };
+    Some((name, summary))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Change {
+    NoChange,
+    Improved,
+    Regressed,
+}
+
+/// How a benchmark's result compares to its result in a baseline.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BenchComparison {
+    /// Change of the median, in percent of the baseline's median.
+    pub change_pct: f64,
+    pub change: Change,
+}
+
+impl BenchComparison {
+    /// Compares two runs of `samples` samples each. A change is only reported
+    /// if it is statistically significant and the median moved by more than
+    /// `threshold_pct` percent.
+    pub fn new(
+        baseline: &Summary,
+        current: &Summary,
+        samples: usize,
+        threshold_pct: f64,
+    ) -> BenchComparison {
+        let change_pct = if baseline.median == 0.0 {
+            0.0
+        } else {
+            (current.median - baseline.median) / baseline.median * 100.0
+        };
+        let t = stats::welch_t(baseline, samples, current, samples);
+        let significant = t.abs() > SIGNIFICANCE_T;
+
+        let change = if !significant || change_pct.abs() <= threshold_pct {
+            Change::NoChange
+        } else if change_pct > 0.0 {
+            Change::Regressed
+        } else {
+            Change::Improved
+        };
+
+        BenchComparison { change_pct, change }
+    }
+}
+
+impl fmt::Display for BenchComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = match self.change {
+            Change::NoChange => "no change",
+            Change::Improved => "improved",
+            Change::Regressed => "regressed",
+        };
+        write!(f, "{:+.2}%, {}", self.change_pct, change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Baseline, BenchComparison, Change};
+    use crate::stats::Summary;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn summary(median: f64, spread: f64) -> Summary {
+        let samples = (0..50)
+            .map(|i| median + spread * ((i % 5) as f64 - 2.0))
+            .collect::<Vec<_>>();
+        Summary::new(&samples)
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = env::temp_dir().join(format!("libtest-baseline-{}", process::id()));
+
+        let mut baseline = Baseline::new();
+        baseline.insert("bench one", summary(1000.0, 10.0));
+        baseline.insert("two", summary(0.1, 0.001));
+        baseline.save(&dir, "main").unwrap();
+
+        let loaded = Baseline::load(&dir, "main").unwrap();
+        assert!(loaded.get("bench one") == baseline.get("bench one"));
+        assert!(loaded.get("two") == baseline.get("two"));
+        assert!(loaded.get("three").is_none());
+
+        assert!(Baseline::load(&dir, "missing").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn merge_keeps_other_benchmarks() {
+        let dir = env::temp_dir().join(format!("libtest-baseline-merge-{}", process::id()));
+
+        let mut first = Baseline::new();
+        first.insert("one", summary(1000.0, 10.0));
+        first.insert("two", summary(2000.0, 10.0));
+        first.merge_into(&dir, "main").unwrap();
+
+        let mut second = Baseline::new();
+        second.insert("two", summary(1500.0, 10.0));
+        second.merge_into(&dir, "main").unwrap();
+
+        let loaded = Baseline::load(&dir, "main").unwrap();
+        assert!(loaded.get("one") == first.get("one"));
+        assert!(loaded.get("two") == second.get("two"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compare_summaries() {
+        let base = summary(1000.0, 10.0);
+
+        let same = BenchComparison::new(&base, &summary(1001.0, 10.0), 50, 5.0);
+        assert_eq!(same.change, Change::NoChange);
+
+        let slower = BenchComparison::new(&base, &summary(1200.0, 10.0), 50, 5.0);
+        assert_eq!(slower.change, Change::Regressed);
+        assert!((slower.change_pct - 20.0).abs() < 1e-9);
+        assert_eq!(slower.to_string(), "+20.00%, regressed");
+
+        let faster = BenchComparison::new(&base, &summary(800.0, 10.0), 50, 5.0);
+        assert_eq!(faster.change, Change::Improved);
+
+        // Significant, but below the threshold.
+        let small = BenchComparison::new(&base, &summary(1030.0, 10.0), 50, 5.0);
+        assert_eq!(small.change, Change::NoChange);
+
+        // Above the threshold, but lost in the noise.
+        let noisy =
+            BenchComparison::new(&summary(1000.0, 2000.0), &summary(1100.0, 2000.0), 50, 5.0);
+        assert_eq!(noisy.change, Change::NoChange);
+    }
+}
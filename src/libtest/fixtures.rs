@@ -0,0 +1,179 @@
+//! Setup and teardown functions shared by several tests.
+//!
+//! A fixture registered with `#[test_setup]` or `#[test_teardown]` applies to
+//! the tests of the module it is defined in, including those of nested
+//! modules, or to every test of the binary if it is defined at the crate root.
+//! Setups run before the first of these tests starts and teardowns after the
+//! last of them finishes, so each fixture runs at most once. Fixtures whose
+//! tests are all filtered out or ignored are not run at all.
+//!
+//! A test that runs in a subprocess (with `--isolate` or a timeout) would not
+//! see the state its fixtures set up in the main process, so such tests cannot
+//! have fixtures: `run_tests` refuses to run them.
+
+use super::{StaticSetupFn, StaticTeardownFn, TestDesc, TestDescAndFn, TestName};
+use std::io;
+
+pub struct Fixture {
+    pub desc: TestDesc,
+    is_setup: bool,
+    f: fn(),
+}
+
+impl Fixture {
+    /// Returns the fixture as a test that can be passed to `run_test`.
+    pub fn to_test(&self) -> TestDescAndFn {
+        TestDescAndFn {
+            desc: self.desc.clone(),
+            testfn: if self.is_setup {
+                StaticSetupFn(self.f)
+            } else {
+                StaticTeardownFn(self.f)
+            },
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum State {
+    /// None of the tests have started yet.
+    Idle,
+    /// The setups ran successfully.
+    Ready,
+    /// The setup with this name failed.
+    Failed(TestName),
+}
+
+/// The fixtures of one module.
+struct Scope {
+    /// The path of the module, empty for the whole binary.
+    path: String,
+    setups: Vec<Fixture>,
+    teardowns: Vec<Fixture>,
+    /// The number of tests in the module which have not finished yet.
+    pending: usize,
+    state: State,
+}
+
+impl Scope {
+    fn contains(&self, desc: &TestDesc) -> bool {
+        let name = desc.name.as_slice();
+        self.path.is_empty()
+            || (name.starts_with(&self.path[..]) && name[self.path.len()..].starts_with("::"))
+    }
+}
+
+pub struct Fixtures {
+    /// Sorted by path, so that a module comes before the modules nested in it.
+    scopes: Vec<Scope>,
+}
+
+impl Fixtures {
+    /// Separates the fixtures from the tests.
+    pub fn extract(tests: Vec<TestDescAndFn>) -> (Fixtures, Vec<TestDescAndFn>) {
+        let mut scopes: Vec<Scope> = Vec::new();
+        let mut rest = Vec::with_capacity(tests.len());
+
+        for test in tests {
+            let (is_setup, f) = match test.testfn {
+                StaticSetupFn(f) => (true, f),
+                StaticTeardownFn(f) => (false, f),
+                _ => {
+                    rest.push(test);
+                    continue;
+                }
+            };
+
+            let name = test.desc.name.as_slice();
+            let path = name.rfind("::").map_or("", |pos| &name[..pos]).to_owned();
+            let index = match scopes.iter().position(|scope| scope.path == path) {
+                Some(index) => index,
+                None => {
+                    scopes.push(Scope {
+                        path,
+                        setups: Vec::new(),
+                        teardowns: Vec::new(),
+                        pending: 0,
+                        state: State::Idle,
+                    });
+                    scopes.len() - 1
+                }
+            };
+
+            let fixture = Fixture {
+                desc: test.desc,
+                is_setup,
+                f,
+            };
+            if is_setup {
+                scopes[index].setups.push(fixture);
+            } else {
+                scopes[index].teardowns.push(fixture);
+            }
+        }
+
+        scopes.sort_by(|a, b| a.path.cmp(&b.path));
+        (Fixtures { scopes }, rest)
+    }
+
+    /// Records that `desc` is going to be run.
+    pub fn add_test(&mut self, desc: &TestDesc) {
+        for scope in self.scopes.iter_mut().filter(|scope| scope.contains(desc)) {
+            scope.pending += 1;
+        }
+    }
+
+    /// Returns whether any fixture applies to `desc`.
+    pub fn applies_to(&self, desc: &TestDesc) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(desc))
+    }
+
+    /// Runs the setups that have to run before `desc`, outermost module
+    /// first. `run` runs a fixture and returns whether it succeeded. Returns
+    /// the name of the failed setup if `desc` must not be run.
+    ///
+    /// If a setup fails after others of the same module succeeded, the
+    /// teardowns of the module run right away, since none of its tests will.
+    pub fn set_up<F>(&mut self, desc: &TestDesc, mut run: F) -> io::Result<Option<TestName>>
+    where
+        F: FnMut(&Fixture) -> io::Result<bool>,
+    {
+        for scope in self.scopes.iter_mut().filter(|scope| scope.contains(desc)) {
+            if scope.state == State::Idle {
+                scope.state = State::Ready;
+                for (i, setup) in scope.setups.iter().enumerate() {
+                    if !run(setup)? {
+                        scope.state = State::Failed(setup.desc.name.clone());
+                        if i > 0 {
+                            for teardown in &scope.teardowns {
+                                run(teardown)?;
+                            }
+                        }
+                        break;
+                    }
+                }
+            }
+            if let State::Failed(ref name) = scope.state {
+                return Ok(Some(name.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records that `desc` finished, and runs the teardowns of the modules
+    /// that have no tests left, innermost module first.
+    pub fn tear_down<F>(&mut self, desc: &TestDesc, mut run: F) -> io::Result<()>
+    where
+        F: FnMut(&Fixture) -> io::Result<bool>,
+    {
+        for scope in self.scopes.iter_mut().rev().filter(|scope| scope.contains(desc)) {
+            scope.pending -= 1;
+            if scope.pending == 0 && scope.state == State::Ready {
+                for teardown in &scope.teardowns {
+                    run(teardown)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -56,36 +56,57 @@ impl<T: Write> OutputFormatter for JsonFormatter<T> {
         &mut self,
         desc: &TestDesc,
         result: &TestResult,
+        exec_time: Duration,
         stdout: &[u8],
     ) -> io::Result<()> {
+        let exec_time = format!(r#""exec_time": {}"#, fmt_secs(exec_time));
+
         match *result {
-            TrOk => self.write_event("test", desc.name.as_slice(), "ok", None),
+            TrOk => self.write_event("test", desc.name.as_slice(), "ok", Some(exec_time)),
 
             TrFailed => {
                 let extra_data = if stdout.len() > 0 {
-                    Some(format!(
-                        r#""stdout": "{}""#,
+                    format!(
+                        r#"{}, "stdout": "{}""#,
+                        exec_time,
                         EscapedString(String::from_utf8_lossy(stdout))
-                    ))
+                    )
                 } else {
-                    None
+                    exec_time
                 };
 
-                self.write_event("test", desc.name.as_slice(), "failed", extra_data)
+                self.write_event("test", desc.name.as_slice(), "failed", Some(extra_data))
             }
 
             TrFailedMsg(ref m) => self.write_event(
                 "test",
                 desc.name.as_slice(),
                 "failed",
-                Some(format!(r#""message": "{}""#, EscapedString(m))),
+                Some(format!(r#"{}, "message": "{}""#, exec_time, EscapedString(m))),
+            ),
+
+            TrTimedFail => self.write_event(
+                "test",
+                desc.name.as_slice(),
+                "failed",
+                Some(format!(r#"{}, "reason": "time limit exceeded""#, exec_time)),
+            ),
+
+            TrFlaky(retries) => self.write_event(
+                "test",
+                desc.name.as_slice(),
+                "flaky",
+                Some(format!(r#"{}, "retries": {}"#, exec_time, retries)),
             ),
 
             TrIgnored => self.write_event("test", desc.name.as_slice(), "ignored", None),
 
-            TrAllowedFail => {
-                self.write_event("test", desc.name.as_slice(), "allowed_failure", None)
-            }
+            TrAllowedFail => self.write_event(
+                "test",
+                desc.name.as_slice(),
+                "allowed_failure",
+                Some(exec_time),
+            ),
 
             TrBench(ref bs) => {
                 let median = bs.ns_iter_summ.median as usize;
@@ -97,12 +118,25 @@ impl<T: Write> OutputFormatter for JsonFormatter<T> {
                     format!(r#", "mib_per_second": {}"#, bs.mb_s)
                 };
 
+                let comparison = match bs.comparison {
+                    Some(ref c) => format!(
+                        r#", "change_pct": {:.2}, "change": "{}""#,
+                        c.change_pct,
+                        match c.change {
+                            Change::NoChange => "none",
+                            Change::Improved => "improved",
+                            Change::Regressed => "regressed",
+                        }
+                    ),
+                    None => String::new(),
+                };
+
                 let line = format!(
                     "{{ \"type\": \"bench\", \
                      \"name\": \"{}\", \
                      \"median\": {}, \
-                     \"deviation\": {}{} }}",
-                    desc.name, median, deviation, mbps
+                     \"deviation\": {}{}{} }}",
+                    desc.name, median, deviation, mbps, comparison
                 );
 
                 self.write_message(&*line)
@@ -118,6 +152,17 @@ impl<T: Write> OutputFormatter for JsonFormatter<T> {
     }
 
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
+        let mut extra = String::new();
+        if state.baseline.is_some() {
+            extra.push_str(&format!(r#", "regressed": {}"#, state.regressed));
+        }
+        if !state.flaky.is_empty() {
+            extra.push_str(&format!(r#", "flaky": {}"#, state.flaky.len()));
+        }
+        if let Some(seed) = state.shuffle_seed {
+            extra.push_str(&format!(r#", "shuffle_seed": {}"#, seed));
+        }
+
         self.write_message(&*format!(
             "{{ \"type\": \"suite\", \
              \"event\": \"{}\", \
@@ -126,17 +171,18 @@ impl<T: Write> OutputFormatter for JsonFormatter<T> {
              \"allowed_fail\": {}, \
              \"ignored\": {}, \
              \"measured\": {}, \
-             \"filtered_out\": {} }}",
-            if state.failed == 0 { "ok" } else { "failed" },
+             \"filtered_out\": {}{} }}",
+            if state.is_success() { "ok" } else { "failed" },
             state.passed,
             state.failed + state.allowed_fail,
             state.allowed_fail,
             state.ignored,
             state.measured,
-            state.filtered_out
+            state.filtered_out,
+            extra
         ))?;
 
-        Ok(state.failed == 0)
+        Ok(state.is_success())
     }
 }
 
@@ -0,0 +1,196 @@
+use super::*;
+
+/// Writes a JUnit XML report. Since the `<testsuite>` element carries the
+/// totals as attributes, results are buffered and the whole document is
+/// written once the run finishes.
+pub(crate) struct JunitFormatter<T> {
+    out: OutputLocation<T>,
+    run_start: Option<Instant>,
+    results: Vec<(TestDesc, TestResult, Duration, Vec<u8>)>,
+}
+
+impl<T: Write> JunitFormatter<T> {
+    pub fn new(out: OutputLocation<T>) -> Self {
+        Self {
+            out,
+            run_start: None,
+            results: Vec::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+
+    fn write_failure(&mut self, message: &str) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "<failure type=\"assert\" message=\"{}\"/>",
+            EscapedXml(message)
+        )
+    }
+
+    fn write_testcase(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        duration: Duration,
+        stdout: &[u8],
+    ) -> io::Result<()> {
+        let name = desc.name.as_slice();
+        let (class_name, test_name) = match name.rfind("::") {
+            Some(pos) => (&name[..pos], &name[pos + 2..]),
+            None => ("", name),
+        };
+
+        write!(
+            self.out,
+            "<testcase classname=\"{}\" name=\"{}\" time=\"{}\"",
+            EscapedXml(class_name),
+            EscapedXml(test_name),
+            fmt_secs(duration)
+        )?;
+
+        match *result {
+            TrOk | TrFlaky(_) | TrBench(_) if stdout.is_empty() => {
+                return writeln!(self.out, "/>");
+            }
+            _ => writeln!(self.out, ">")?,
+        }
+
+        match *result {
+            TrOk | TrFlaky(_) | TrBench(_) => {}
+            TrIgnored => writeln!(self.out, "<skipped/>")?,
+            TrAllowedFail => writeln!(self.out, "<skipped message=\"failure allowed\"/>")?,
+            TrFailed => self.write_failure("test failed")?,
+            TrFailedMsg(ref msg) => self.write_failure(msg)?,
+            TrTimedFail => self.write_failure("time limit exceeded")?,
+        }
+        if !stdout.is_empty() {
+            writeln!(
+                self.out,
+                "<system-out>{}</system-out>",
+                EscapedXml(&String::from_utf8_lossy(stdout))
+            )?;
+        }
+        writeln!(self.out, "</testcase>")
+    }
+}
+
+impl<T: Write> OutputFormatter for JunitFormatter<T> {
+    fn write_run_start(&mut self, _test_count: usize) -> io::Result<()> {
+        self.run_start = Some(Instant::now());
+        Ok(())
+    }
+
+    fn write_test_start(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_timeout(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        // The report only contains final results.
+        Ok(())
+    }
+
+    fn write_result(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        exec_time: Duration,
+        stdout: &[u8],
+    ) -> io::Result<()> {
+        self.results
+            .push((desc.clone(), result.clone(), exec_time, stdout.to_vec()));
+        Ok(())
+    }
+
+    fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
+        let total_time = self
+            .run_start
+            .map(|start| start.elapsed())
+            .unwrap_or_default();
+        let suite_name = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "test".to_owned());
+
+        writeln!(self.out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(
+            self.out,
+            "<testsuites name=\"{name}\" tests=\"{tests}\" failures=\"{failures}\" \
+             errors=\"0\" skipped=\"{skipped}\" time=\"{time}\">",
+            name = EscapedXml(&suite_name),
+            tests = self.results.len(),
+            failures = state.failed,
+            skipped = state.ignored + state.allowed_fail,
+            time = fmt_secs(total_time)
+        )?;
+        writeln!(
+            self.out,
+            "<testsuite name=\"{name}\" id=\"0\" tests=\"{tests}\" failures=\"{failures}\" \
+             errors=\"0\" skipped=\"{skipped}\" time=\"{time}\">",
+            name = EscapedXml(&suite_name),
+            tests = self.results.len(),
+            failures = state.failed,
+            skipped = state.ignored + state.allowed_fail,
+            time = fmt_secs(total_time)
+        )?;
+        if let Some(seed) = state.shuffle_seed {
+            writeln!(
+                self.out,
+                "<properties><property name=\"shuffle_seed\" value=\"{}\"/></properties>",
+                seed
+            )?;
+        }
+
+        let results = ::std::mem::replace(&mut self.results, Vec::new());
+        for (desc, result, duration, stdout) in &results {
+            self.write_testcase(desc, result, *duration, stdout)?;
+        }
+
+        writeln!(self.out, "</testsuite>")?;
+        writeln!(self.out, "</testsuites>")?;
+        self.out.flush()?;
+
+        Ok(state.is_success())
+    }
+}
+
+/// A formatting utility used to print strings as XML attribute values or
+/// character data. Characters that are not allowed in XML 1.0 documents,
+/// even when escaped, are replaced with U+FFFD.
+struct EscapedXml<'a>(&'a str);
+
+impl<'a> ::std::fmt::Display for EscapedXml<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        let mut start = 0;
+
+        for (i, c) in self.0.char_indices() {
+            let escaped = match c {
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '&' => "&amp;",
+                '"' => "&quot;",
+                '\'' => "&apos;",
+                '\t' | '\n' | '\r' => continue,
+                c if (c as u32) < 0x20 || c == '\u{fffe}' || c == '\u{ffff}' => "\u{fffd}",
+                _ => continue,
+            };
+
+            if start < i {
+                f.write_str(&self.0[start..i])?;
+            }
+
+            f.write_str(escaped)?;
+
+            start = i + c.len_utf8();
+        }
+
+        if start != self.0.len() {
+            f.write_str(&self.0[start..])?;
+        }
+
+        Ok(())
+    }
+}
@@ -8,6 +8,9 @@ pub(crate) struct PrettyFormatter<T> {
     max_name_len: usize,
 
     is_multithreaded: bool,
+
+    /// Set when execution times should be shown
+    time_options: Option<TestTimeOptions>,
 }
 
 impl<T: Write> PrettyFormatter<T> {
@@ -16,12 +19,14 @@ impl<T: Write> PrettyFormatter<T> {
         use_color: bool,
         max_name_len: usize,
         is_multithreaded: bool,
+        time_options: Option<TestTimeOptions>,
     ) -> Self {
         PrettyFormatter {
             out,
             use_color,
             max_name_len,
             is_multithreaded,
+            time_options,
         }
     }
 
@@ -46,6 +51,14 @@ impl<T: Write> PrettyFormatter<T> {
         self.write_short_result("FAILED (allowed)", term::color::YELLOW)
     }
 
+    pub fn write_flaky(&mut self) -> io::Result<()> {
+        self.write_short_result("ok (flaky)", term::color::YELLOW)
+    }
+
+    pub fn write_time_failed(&mut self) -> io::Result<()> {
+        self.write_short_result("FAILED (time limit exceeded)", term::color::RED)
+    }
+
     pub fn write_bench(&mut self) -> io::Result<()> {
         self.write_pretty("bench", term::color::CYAN)
     }
@@ -55,8 +68,22 @@ impl<T: Write> PrettyFormatter<T> {
         result: &str,
         color: term::color::Color,
     ) -> io::Result<()> {
-        self.write_pretty(result, color)?;
-        self.write_plain("\n")
+        self.write_pretty(result, color)
+    }
+
+    fn write_time(&mut self, exec_time: Duration) -> io::Result<()> {
+        if let Some(time_opts) = self.time_options {
+            let time = format!(" <{}s>", fmt_secs(exec_time));
+            if time_opts.is_critical(exec_time) {
+                self.write_pretty(&time, term::color::RED)?;
+            } else if time_opts.is_warn(exec_time) {
+                self.write_pretty(&time, term::color::YELLOW)?;
+            } else {
+                self.write_plain(&time)?;
+            }
+        }
+
+        Ok(())
     }
 
     pub fn write_pretty(&mut self, word: &str, color: term::color::Color) -> io::Result<()> {
@@ -136,6 +163,17 @@ impl<T: Write> PrettyFormatter<T> {
         Ok(())
     }
 
+    pub fn write_flaky_tests(&mut self, state: &ConsoleTestState) -> io::Result<()> {
+        self.write_plain("\nflaky tests:\n")?;
+        let mut flaky = state.flaky.iter().collect::<Vec<_>>();
+        flaky.sort_by(|a, b| a.0.name.as_slice().cmp(b.0.name.as_slice()));
+        for &(ref f, retries) in flaky {
+            let noun = if retries != 1 { "retries" } else { "retry" };
+            self.write_plain(&format!("    {} (passed after {} {})\n", f.name, retries, noun))?;
+        }
+        Ok(())
+    }
+
     fn write_test_name(&mut self, desc: &TestDesc) -> io::Result<()> {
         let name = desc.padded_name(self.max_name_len, desc.name.padding());
         self.write_plain(&format!("test {} ... ", name))?;
@@ -162,21 +200,35 @@ impl<T: Write> OutputFormatter for PrettyFormatter<T> {
         Ok(())
     }
 
-    fn write_result(&mut self, desc: &TestDesc, result: &TestResult, _: &[u8]) -> io::Result<()> {
+    fn write_result(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        exec_time: Duration,
+        _: &[u8],
+    ) -> io::Result<()> {
         if self.is_multithreaded {
             self.write_test_name(desc)?;
         }
 
         match *result {
-            TrOk => self.write_ok(),
-            TrFailed | TrFailedMsg(_) => self.write_failed(),
-            TrIgnored => self.write_ignored(),
-            TrAllowedFail => self.write_allowed_fail(),
+            TrOk => self.write_ok()?,
+            TrFailed | TrFailedMsg(_) => self.write_failed()?,
+            TrIgnored => {
+                self.write_ignored()?;
+                return self.write_plain("\n");
+            }
+            TrAllowedFail => self.write_allowed_fail()?,
+            TrTimedFail => self.write_time_failed()?,
+            TrFlaky(_) => self.write_flaky()?,
             TrBench(ref bs) => {
                 self.write_bench()?;
-                self.write_plain(&format!(": {}\n", fmt_bench_samples(bs)))
+                return self.write_plain(&format!(": {}\n", fmt_bench_samples(bs)));
             }
         }
+
+        self.write_time(exec_time)?;
+        self.write_plain("\n")
     }
 
     fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
@@ -194,10 +246,17 @@ impl<T: Write> OutputFormatter for PrettyFormatter<T> {
         if state.options.display_output {
             self.write_successes(state)?;
         }
-        let success = state.failed == 0;
+        let success = state.is_success();
         if !success {
             self.write_failures(state)?;
         }
+        if !state.flaky.is_empty() {
+            self.write_flaky_tests(state)?;
+        }
+
+        if let Some(seed) = state.shuffle_seed {
+            self.write_plain(&format!("\nshuffle seed: {}\n", seed))?;
+        }
 
         self.write_plain("\ntest result: ")?;
 
@@ -208,20 +267,33 @@ impl<T: Write> OutputFormatter for PrettyFormatter<T> {
             self.write_pretty("FAILED", term::color::RED)?;
         }
 
+        let measured = if state.regressed > 0 {
+            format!("{} measured ({} regressed)", state.measured, state.regressed)
+        } else {
+            format!("{} measured", state.measured)
+        };
+
+        let flaky = if state.flaky.is_empty() {
+            String::new()
+        } else {
+            format!(" {} flaky;", state.flaky.len())
+        };
+
         let s = if state.allowed_fail > 0 {
             format!(
-                ". {} passed; {} failed ({} allowed); {} ignored; {} measured; {} filtered out\n\n",
+                ". {} passed;{} {} failed ({} allowed); {} ignored; {}; {} filtered out\n\n",
                 state.passed,
+                flaky,
                 state.failed + state.allowed_fail,
                 state.allowed_fail,
                 state.ignored,
-                state.measured,
+                measured,
                 state.filtered_out
             )
         } else {
             format!(
-                ". {} passed; {} failed; {} ignored; {} measured; {} filtered out\n\n",
-                state.passed, state.failed, state.ignored, state.measured, state.filtered_out
+                ". {} passed;{} {} failed; {} ignored; {}; {} filtered out\n\n",
+                state.passed, flaky, state.failed, state.ignored, measured, state.filtered_out
             )
         };
 
@@ -44,6 +44,10 @@ impl<T: Write> TerseFormatter<T> {
         self.write_short_result("a", term::color::YELLOW)
     }
 
+    pub fn write_flaky(&mut self) -> io::Result<()> {
+        self.write_short_result("f", term::color::YELLOW)
+    }
+
     pub fn write_bench(&mut self) -> io::Result<()> {
         self.write_pretty("bench", term::color::CYAN)
     }
@@ -143,6 +147,17 @@ impl<T: Write> TerseFormatter<T> {
         Ok(())
     }
 
+    pub fn write_flaky_tests(&mut self, state: &ConsoleTestState) -> io::Result<()> {
+        self.write_plain("\nflaky tests:\n")?;
+        let mut flaky = state.flaky.iter().collect::<Vec<_>>();
+        flaky.sort_by(|a, b| a.0.name.as_slice().cmp(b.0.name.as_slice()));
+        for &(ref f, retries) in flaky {
+            let noun = if retries != 1 { "retries" } else { "retry" };
+            self.write_plain(&format!("    {} (passed after {} {})\n", f.name, retries, noun))?;
+        }
+        Ok(())
+    }
+
     fn write_test_name(&mut self, desc: &TestDesc) -> io::Result<()> {
         let name = desc.padded_name(self.max_name_len, desc.name.padding());
         self.write_plain(&format!("test {} ... ", name))?;
@@ -170,12 +185,19 @@ impl<T: Write> OutputFormatter for TerseFormatter<T> {
         Ok(())
     }
 
-    fn write_result(&mut self, desc: &TestDesc, result: &TestResult, _: &[u8]) -> io::Result<()> {
+    fn write_result(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        _: Duration,
+        _: &[u8],
+    ) -> io::Result<()> {
         match *result {
             TrOk => self.write_ok(),
-            TrFailed | TrFailedMsg(_) => self.write_failed(),
+            TrFailed | TrFailedMsg(_) | TrTimedFail => self.write_failed(),
             TrIgnored => self.write_ignored(),
             TrAllowedFail => self.write_allowed_fail(),
+            TrFlaky(_) => self.write_flaky(),
             TrBench(ref bs) => {
                 if self.is_multithreaded {
                     self.write_test_name(desc)?;
@@ -197,10 +219,17 @@ impl<T: Write> OutputFormatter for TerseFormatter<T> {
         if state.options.display_output {
             self.write_outputs(state)?;
         }
-        let success = state.failed == 0;
+        let success = state.is_success();
         if !success {
             self.write_failures(state)?;
         }
+        if !state.flaky.is_empty() {
+            self.write_flaky_tests(state)?;
+        }
+
+        if let Some(seed) = state.shuffle_seed {
+            self.write_plain(&format!("\nshuffle seed: {}\n", seed))?;
+        }
 
         self.write_plain("\ntest result: ")?;
 
@@ -211,20 +240,33 @@ impl<T: Write> OutputFormatter for TerseFormatter<T> {
             self.write_pretty("FAILED", term::color::RED)?;
         }
 
+        let measured = if state.regressed > 0 {
+            format!("{} measured ({} regressed)", state.measured, state.regressed)
+        } else {
+            format!("{} measured", state.measured)
+        };
+
+        let flaky = if state.flaky.is_empty() {
+            String::new()
+        } else {
+            format!(" {} flaky;", state.flaky.len())
+        };
+
         let s = if state.allowed_fail > 0 {
             format!(
-                ". {} passed; {} failed ({} allowed); {} ignored; {} measured; {} filtered out\n\n",
+                ". {} passed;{} {} failed ({} allowed); {} ignored; {}; {} filtered out\n\n",
                 state.passed,
+                flaky,
                 state.failed + state.allowed_fail,
                 state.allowed_fail,
                 state.ignored,
-                state.measured,
+                measured,
                 state.filtered_out
             )
         } else {
             format!(
-                ". {} passed; {} failed; {} ignored; {} measured; {} filtered out\n\n",
-                state.passed, state.failed, state.ignored, state.measured, state.filtered_out
+                ". {} passed;{} {} failed; {} ignored; {}; {} filtered out\n\n",
+                state.passed, flaky, state.failed, state.ignored, measured, state.filtered_out
             )
         };
 
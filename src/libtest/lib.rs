@@ -57,10 +57,12 @@ use std::any::Any;
 use std::borrow::Cow;
 use std::boxed::FnBox;
 use std::cmp;
-use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fmt;
 use std::fs::File;
+use std::hash::BuildHasherDefault;
 use std::io;
 use std::io::prelude::*;
 use std::panic::{catch_unwind, AssertUnwindSafe};
@@ -74,21 +76,37 @@ use std::time::{Duration, Instant};
 
 const TEST_WARN_TIMEOUT_S: u64 = 60;
 const QUIET_MODE_MAX_COLUMN: usize = 100; // insert a '\n' after 100 tests in quiet mode
+const BENCH_SAMPLE_COUNT: usize = 50;
+const DEFAULT_REGRESSION_THRESHOLD_PCT: f64 = 5.0;
+
+// When set, the test binary runs only the named test and reports its result
+// on stdout instead of running the whole suite. Used to run tests in a
+// subprocess that can be killed.
+const SUBPROCESS_TEST_VAR: &str = "__RUST_TEST_SUBPROCESS";
+// Separates the captured output of a subprocess test from its result.
+const SUBPROCESS_RESULT_MARKER: &[u8] = b"\n__RUST_TEST_SUBPROCESS_RESULT__ ";
 
 // to be used by rustc to compile tests in libtest
 pub mod test {
     pub use crate::{
         assert_test_result, filter_tests, parse_opts, run_test, test_main, test_main_static,
         Bencher, DynTestFn, DynTestName, Metric, MetricMap, Options, RunIgnored, ShouldPanic,
-        StaticBenchFn, StaticTestFn, StaticTestName, TestDesc, TestDescAndFn, TestName, TestOpts,
-        TestResult, TrFailed, TrFailedMsg, TrIgnored, TrOk,
+        StaticBenchFn, StaticSetupFn, StaticTeardownFn, StaticTestFn, StaticTestName, TestDesc,
+        TestDescAndFn, TestName, TestOpts,
+        TestResult, TrFailed, TrFailedMsg, TrFlaky, TrIgnored, TrOk, TrTimedFail,
     };
 }
 
+mod baseline;
+mod fixtures;
 mod formatters;
 pub mod stats;
 
-use crate::formatters::{JsonFormatter, OutputFormatter, PrettyFormatter, TerseFormatter};
+use crate::baseline::{Baseline, BenchComparison, Change};
+use crate::fixtures::{Fixture, Fixtures};
+use crate::formatters::{
+    JsonFormatter, JunitFormatter, OutputFormatter, PrettyFormatter, TerseFormatter,
+};
 
 /// Whether to execute tests concurrently or not
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -175,6 +193,11 @@ pub enum TestFn {
     StaticBenchFn(fn(&mut Bencher)),
     DynTestFn(Box<dyn FnBox() + Send>),
     DynBenchFn(Box<dyn TDynBenchFn + 'static>),
+    /// Runs once before the tests of the module it is defined in, see
+    /// the `fixtures` module.
+    StaticSetupFn(fn()),
+    /// Runs once after the tests of the module it is defined in.
+    StaticTeardownFn(fn()),
 }
 
 impl TestFn {
@@ -184,6 +207,8 @@ impl TestFn {
             StaticBenchFn(..) => PadOnRight,
             DynTestFn(..) => PadNone,
             DynBenchFn(..) => PadOnRight,
+            StaticSetupFn(..) => PadNone,
+            StaticTeardownFn(..) => PadNone,
         }
     }
 }
@@ -195,6 +220,8 @@ impl fmt::Debug for TestFn {
             StaticBenchFn(..) => "StaticBenchFn(..)",
             DynTestFn(..) => "DynTestFn(..)",
             DynBenchFn(..) => "DynBenchFn(..)",
+            StaticSetupFn(..) => "StaticSetupFn(..)",
+            StaticTeardownFn(..) => "StaticTeardownFn(..)",
         })
     }
 }
@@ -232,6 +259,8 @@ pub struct TestDesc {
     pub ignore: bool,
     pub should_panic: ShouldPanic,
     pub allow_fail: bool,
+    /// Number of seconds after which the test is killed and reported as failed.
+    pub timeout: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -284,6 +313,9 @@ pub fn test_main(args: &[String], tests: Vec<TestDescAndFn>, options: Options) {
     };
 
     opts.options = options;
+    if let Ok(name) = env::var(SUBPROCESS_TEST_VAR) {
+        run_test_in_spawned_subprocess(opts, &name, tests);
+    }
     if opts.list {
         if let Err(e) = list_tests_console(&opts, tests) {
             eprintln!("error: io error when listing tests: {:?}", e);
@@ -293,6 +325,10 @@ pub fn test_main(args: &[String], tests: Vec<TestDescAndFn>, options: Options) {
         match run_tests_console(&opts, tests) {
             Ok(true) => {}
             Ok(false) => process::exit(101),
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
+                eprintln!("error: {}", e);
+                process::exit(101);
+            }
             Err(e) => {
                 eprintln!("error: io error when listing tests: {:?}", e);
                 process::exit(101);
@@ -321,6 +357,14 @@ pub fn test_main_static(tests: &[&TestDescAndFn]) {
                 testfn: StaticBenchFn(f),
                 desc: t.desc.clone(),
             },
+            StaticSetupFn(f) => TestDescAndFn {
+                testfn: StaticSetupFn(f),
+                desc: t.desc.clone(),
+            },
+            StaticTeardownFn(f) => TestDescAndFn {
+                testfn: StaticTeardownFn(f),
+                desc: t.desc.clone(),
+            },
             _ => panic!("non-static tests passed to test::test_main_static"),
         })
         .collect();
@@ -352,6 +396,7 @@ pub enum OutputFormat {
     Pretty,
     Terse,
     Json,
+    Junit,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -361,6 +406,75 @@ pub enum RunIgnored {
     Only,
 }
 
+/// Execution times above `warn` are highlighted when reporting times, and
+/// times above `critical` can be treated as failures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeThreshold {
+    pub warn: Duration,
+    pub critical: Duration,
+}
+
+impl TimeThreshold {
+    pub fn new(warn: Duration, critical: Duration) -> TimeThreshold {
+        TimeThreshold { warn, critical }
+    }
+}
+
+impl Default for TimeThreshold {
+    fn default() -> TimeThreshold {
+        TimeThreshold::new(Duration::from_millis(50), Duration::from_millis(100))
+    }
+}
+
+/// Controls how test execution times are reported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TestTimeOptions {
+    /// Fail tests that run for longer than the critical threshold.
+    pub error_on_excess: bool,
+    pub threshold: TimeThreshold,
+}
+
+impl TestTimeOptions {
+    fn is_warn(&self, exec_time: Duration) -> bool {
+        exec_time >= self.threshold.warn
+    }
+
+    fn is_critical(&self, exec_time: Duration) -> bool {
+        exec_time >= self.threshold.critical
+    }
+}
+
+/// Selects the subset of tests with `index` out of `count` roughly equal
+/// subsets, so that a test suite can be split across several machines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TestShard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl TestShard {
+    /// Returns whether the test called `name` belongs to this shard. Tests are
+    /// assigned by a hash of their name which does not depend on the platform
+    /// or the compiler version, so that a test stays in the same shard when
+    /// other tests are added or removed.
+    pub fn contains(&self, name: &TestName) -> bool {
+        let hash = fnv1a(FNV_OFFSET_BASIS, name.as_slice().as_bytes());
+        hash % self.count as u64 == self.index as u64
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continues the 64-bit FNV-1a hash `hash` with `bytes`. Unlike `DefaultHasher`,
+/// whose algorithm may change between releases, this gives the same result
+/// everywhere, so test shards and shuffled orders can be reproduced with any
+/// toolchain.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
 #[derive(Debug)]
 pub struct TestOpts {
     pub list: bool,
@@ -376,10 +490,26 @@ pub struct TestOpts {
     pub format: OutputFormat,
     pub test_threads: Option<usize>,
     pub skip: Vec<String>,
+    pub test_timeout: Option<Duration>,
+    pub isolate: bool,
+    pub shuffle_seed: Option<u64>,
+    pub time_options: Option<TestTimeOptions>,
+    pub save_baseline: Option<String>,
+    pub baseline: Option<String>,
+    pub baseline_dir: Option<PathBuf>,
+    pub regression_threshold: f64,
+    pub shard: Option<TestShard>,
+    pub retries: usize,
     pub options: Options,
 }
 
 impl TestOpts {
+    fn baseline_dir(&self) -> PathBuf {
+        self.baseline_dir
+            .clone()
+            .unwrap_or_else(baseline::default_dir)
+    }
+
     #[cfg(test)]
     fn new() -> TestOpts {
         TestOpts {
@@ -396,6 +526,16 @@ impl TestOpts {
             format: OutputFormat::Pretty,
             test_threads: None,
             skip: vec![],
+            test_timeout: None,
+            isolate: false,
+            shuffle_seed: None,
+            time_options: None,
+            save_baseline: None,
+            baseline: None,
+            baseline_dir: None,
+            regression_threshold: DEFAULT_REGRESSION_THRESHOLD_PCT,
+            shard: None,
+            retries: 0,
             options: Options::new(),
         }
     }
@@ -433,6 +573,101 @@ fn optgroups() -> getopts::Options {
              in parallel",
             "n_threads",
         )
+        .optopt(
+            "",
+            "test-timeout",
+            "Kill tests that run for longer than SECS seconds and \
+             report them as failed (runs each such test in a subprocess)",
+            "SECS",
+        )
+        .optflag(
+            "",
+            "isolate",
+            "Run each test in its own subprocess, so that a test which \
+             crashes or exits does not take down the others",
+        )
+        .optflag(
+            "",
+            "shuffle",
+            "Run tests in a random order; the seed used is printed \
+             with the results",
+        )
+        .optopt(
+            "",
+            "shuffle-seed",
+            "Run tests in the random order given by SEED, as printed \
+             by a previous run with --shuffle",
+            "SEED",
+        )
+        .optflag(
+            "",
+            "report-time",
+            "Show the execution time of each test",
+        )
+        .optopt(
+            "",
+            "time-threshold",
+            "Execution times, in milliseconds, above which a test is \
+             highlighted as slow (WARN) or very slow (CRITICAL) by \
+             --report-time (default: 50,100)",
+            "WARN,CRITICAL",
+        )
+        .optflag(
+            "",
+            "ensure-time",
+            "Fail tests that run for longer than the critical time \
+             threshold (implies --report-time)",
+        )
+        .optopt(
+            "",
+            "save-baseline",
+            "Save the benchmark results as a baseline called NAME, \
+             keeping the saved results of benchmarks that did not run",
+            "NAME",
+        )
+        .optopt(
+            "",
+            "baseline",
+            "Compare the benchmark results to the baseline called NAME, \
+             failing if any benchmark regressed",
+            "NAME",
+        )
+        .optopt(
+            "",
+            "baseline-dir",
+            "Directory to save and load baselines in (default: a \
+             `baselines` directory next to the test binary)",
+            "PATH",
+        )
+        .optopt(
+            "",
+            "regression-threshold",
+            "Minimum slowdown, in percent, for a significant change \
+             compared to a baseline to count as a regression (default: 5)",
+            "PCT",
+        )
+        .optopt(
+            "",
+            "shard-index",
+            "Only run the tests of shard INDEX, counting from 0 \
+             (requires --shard-count)",
+            "INDEX",
+        )
+        .optopt(
+            "",
+            "shard-count",
+            "Split the tests into COUNT shards, of which only the one \
+             given by --shard-index is run",
+            "COUNT",
+        )
+        .optopt(
+            "",
+            "retries",
+            "Run failed tests again up to N times; tests that pass on a \
+             retry are reported as flaky and do not fail the run (tests \
+             that can only be run once, such as doctests, are not retried)",
+            "N",
+        )
         .optmulti(
             "",
             "skip",
@@ -466,8 +701,9 @@ fn optgroups() -> getopts::Options {
             "Configure formatting of output:
             pretty = Print verbose output;
             terse  = Display one character per test;
-            json   = Output a json document",
-            "pretty|terse|json",
+            json   = Output a json document;
+            junit  = Output a JUnit XML document",
+            "pretty|terse|json|junit",
         )
         .optopt(
             "Z",
@@ -495,6 +731,16 @@ All tests have their standard output and standard error captured by default.
 This can be overridden with the --nocapture flag or setting RUST_TEST_NOCAPTURE
 environment variable to a value other than "0". Logging is not captured by default.
 
+With --isolate, every test is run in a fresh copy of the test binary. Tests
+that abort, exit, overflow their stack or are killed by a signal are then
+reported as failures instead of ending the whole run. Tests with fixtures
+cannot be run this way, nor with a time limit.
+
+With --shard-index and --shard-count, the tests are split into COUNT shards
+and only those of shard INDEX are run (or listed, with --list). Each test is
+assigned to a shard by a hash of its name, so running every shard, for example
+on separate machines, runs each test exactly once.
+
 Test Attributes:
 
     #[test]        - Indicates a function is a test to be run. This function
@@ -508,7 +754,14 @@ Test Attributes:
     #[ignore]      - When applied to a function which is already attributed as a
                      test, then the test runner will ignore these tests during
                      normal test runs. Running with --ignored or --include-ignored will run
-                     these tests."#,
+                     these tests.
+    #[test_timeout = "N"] - The test will be killed and reported as failed if it runs
+                     for longer than N seconds. This overrides --test-timeout.
+    #[test_setup]  - This function is run once before the first test of the
+                     module it is defined in (or of the whole crate, if defined
+                     at the crate root). If it fails, these tests are not run.
+    #[test_teardown] - This function is run once after the last test of the
+                     module it is defined in (or of the whole crate)."#,
         usage = options.usage(&message)
     );
 }
@@ -618,6 +871,119 @@ pub fn parse_opts(args: &[String]) -> Option<OptRes> {
         None => None,
     };
 
+    let test_timeout = match matches.opt_str("test-timeout") {
+        Some(_) if !allow_unstable => {
+            return Some(Err(
+                "The \"test-timeout\" flag is only accepted on the nightly compiler".into(),
+            ));
+        }
+        Some(secs) => match secs.parse::<u64>() {
+            Ok(0) => return Some(Err("argument for --test-timeout must not be 0".to_string())),
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(e) => {
+                return Some(Err(format!(
+                    "argument for --test-timeout must be a number > 0 \
+                     (error: {})",
+                    e
+                )));
+            }
+        },
+        None => None,
+    };
+
+    let isolate = matches.opt_present("isolate");
+    if !allow_unstable && isolate {
+        return Some(Err(
+            "The \"isolate\" flag is only accepted on the nightly compiler".into(),
+        ));
+    }
+
+    let shuffle_seed = match matches.opt_str("shuffle-seed") {
+        Some(_) if !allow_unstable => {
+            return Some(Err(
+                "The \"shuffle-seed\" flag is only accepted on the nightly compiler".into(),
+            ));
+        }
+        Some(seed) => match seed.parse::<u64>() {
+            Ok(seed) => Some(seed),
+            Err(e) => {
+                return Some(Err(format!(
+                    "argument for --shuffle-seed must be a number \
+                     (error: {})",
+                    e
+                )));
+            }
+        },
+        None if matches.opt_present("shuffle") => {
+            if !allow_unstable {
+                return Some(Err(
+                    "The \"shuffle\" flag is only accepted on the nightly compiler".into(),
+                ));
+            }
+            Some(random_shuffle_seed())
+        }
+        None => None,
+    };
+
+    let time_options = match parse_time_options(&matches, allow_unstable) {
+        Ok(time_options) => time_options,
+        Err(e) => return Some(Err(e)),
+    };
+
+    let save_baseline = matches.opt_str("save-baseline");
+    let baseline = matches.opt_str("baseline");
+    let baseline_dir = matches.opt_str("baseline-dir").map(PathBuf::from);
+    let regression_threshold = matches.opt_str("regression-threshold");
+    if !allow_unstable
+        && (save_baseline.is_some()
+            || baseline.is_some()
+            || baseline_dir.is_some()
+            || regression_threshold.is_some())
+    {
+        return Some(Err(
+            "The \"save-baseline\", \"baseline\", \"baseline-dir\" and \
+             \"regression-threshold\" flags are only accepted on the nightly compiler"
+                .into(),
+        ));
+    }
+    let regression_threshold = match regression_threshold {
+        Some(pct) => match pct.parse::<f64>() {
+            Ok(pct) if pct >= 0.0 => pct,
+            _ => {
+                return Some(Err(format!(
+                    "argument for --regression-threshold must be a non-negative \
+                     percentage (was {})",
+                    pct
+                )));
+            }
+        },
+        None => DEFAULT_REGRESSION_THRESHOLD_PCT,
+    };
+
+    let shard = match parse_shard(&matches, allow_unstable) {
+        Ok(shard) => shard,
+        Err(e) => return Some(Err(e)),
+    };
+
+    let retries = match matches.opt_str("retries") {
+        Some(_) if !allow_unstable => {
+            return Some(Err(
+                "The \"retries\" flag is only accepted on the nightly compiler".into(),
+            ));
+        }
+        Some(n) => match n.parse::<usize>() {
+            Ok(n) => n,
+            Err(e) => {
+                return Some(Err(format!(
+                    "argument for --retries must be a number \
+                     (error: {})",
+                    e
+                )));
+            }
+        },
+        None => 0,
+    };
+
     let color = match matches.opt_str("color").as_ref().map(|s| &**s) {
         Some("auto") | None => AutoColor,
         Some("always") => AlwaysColor,
@@ -644,10 +1010,18 @@ pub fn parse_opts(args: &[String]) -> Option<OptRes> {
             }
             OutputFormat::Json
         }
+        Some("junit") => {
+            if !allow_unstable {
+                return Some(Err(
+                    "The \"junit\" format is only accepted on the nightly compiler".into(),
+                ));
+            }
+            OutputFormat::Junit
+        }
 
         Some(v) => {
             return Some(Err(format!(
-                "argument for --format must be pretty, terse, or json (was \
+                "argument for --format must be pretty, terse, json or junit (was \
                  {})",
                 v
             )));
@@ -668,16 +1042,120 @@ pub fn parse_opts(args: &[String]) -> Option<OptRes> {
         format,
         test_threads,
         skip: matches.opt_strs("skip"),
+        test_timeout,
+        isolate,
+        shuffle_seed,
+        time_options,
+        save_baseline,
+        baseline,
+        baseline_dir,
+        regression_threshold,
+        shard,
+        retries,
         options: Options::new(),
     };
 
     Some(Ok(test_opts))
 }
 
+fn parse_time_options(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> Result<Option<TestTimeOptions>, String> {
+    let report_time = matches.opt_present("report-time");
+    let error_on_excess = matches.opt_present("ensure-time");
+    let threshold = matches.opt_str("time-threshold");
+
+    if !report_time && !error_on_excess && threshold.is_none() {
+        return Ok(None);
+    }
+    if !allow_unstable {
+        return Err("The \"report-time\", \"time-threshold\" and \"ensure-time\" flags \
+                    are only accepted on the nightly compiler"
+            .into());
+    }
+
+    let threshold = match threshold {
+        Some(threshold) => {
+            let durations = threshold
+                .split(',')
+                .map(|ms| ms.trim().parse::<u64>().map(Duration::from_millis))
+                .collect::<Result<Vec<_>, _>>();
+            match durations.as_ref().map(|d| &d[..]) {
+                Ok(&[warn, critical]) if warn <= critical => TimeThreshold::new(warn, critical),
+                _ => {
+                    return Err(format!(
+                        "argument for --time-threshold must be two numbers of \
+                         milliseconds WARN,CRITICAL with WARN <= CRITICAL (was {})",
+                        threshold
+                    ));
+                }
+            }
+        }
+        None => TimeThreshold::default(),
+    };
+
+    Ok(Some(TestTimeOptions {
+        error_on_excess,
+        threshold,
+    }))
+}
+
+fn parse_shard(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> Result<Option<TestShard>, String> {
+    let (index, count) = match (matches.opt_str("shard-index"), matches.opt_str("shard-count")) {
+        (None, None) => return Ok(None),
+        (Some(index), Some(count)) => (index, count),
+        _ => {
+            return Err("the \"shard-index\" and \"shard-count\" flags must be \
+                        given together"
+                .into());
+        }
+    };
+    if !allow_unstable {
+        return Err("The \"shard-index\" and \"shard-count\" flags are only accepted \
+                    on the nightly compiler"
+            .into());
+    }
+
+    let count = match count.parse::<usize>() {
+        Ok(count) if count > 0 => count,
+        _ => {
+            return Err(format!(
+                "argument for --shard-count must be a positive number (was {})",
+                count
+            ));
+        }
+    };
+    let index = match index.parse::<usize>() {
+        Ok(index) if index < count => index,
+        _ => {
+            return Err(format!(
+                "argument for --shard-index must be a number less than \
+                 --shard-count (was {})",
+                index
+            ));
+        }
+    };
+
+    Ok(Some(TestShard { index, count }))
+}
+
+fn random_shuffle_seed() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    // `RandomState` is seeded from the OS's random number generator.
+    RandomState::new().build_hasher().finish()
+}
+
 #[derive(Clone, PartialEq)]
 pub struct BenchSamples {
     ns_iter_summ: stats::Summary,
     mb_s: usize,
+    comparison: Option<BenchComparison>,
 }
 
 #[derive(Clone, PartialEq)]
@@ -687,6 +1165,9 @@ pub enum TestResult {
     TrFailedMsg(String),
     TrIgnored,
     TrAllowedFail,
+    TrTimedFail,
+    /// The test passed after failing this many times.
+    TrFlaky(usize),
     TrBench(BenchSamples),
 }
 
@@ -725,6 +1206,11 @@ struct ConsoleTestState {
     metrics: MetricMap,
     failures: Vec<(TestDesc, Vec<u8>)>,
     not_failures: Vec<(TestDesc, Vec<u8>)>,
+    flaky: Vec<(TestDesc, usize)>,
+    shuffle_seed: Option<u64>,
+    baseline: Option<Baseline>,
+    new_baseline: Baseline,
+    regressed: usize,
     options: Options,
 }
 
@@ -735,6 +1221,11 @@ impl ConsoleTestState {
             None => None,
         };
 
+        let baseline = match opts.baseline {
+            Some(ref name) => Some(Baseline::load(&opts.baseline_dir(), name)?),
+            None => None,
+        };
+
         Ok(ConsoleTestState {
             log_out,
             total: 0,
@@ -747,6 +1238,11 @@ impl ConsoleTestState {
             metrics: MetricMap::new(),
             failures: Vec::new(),
             not_failures: Vec::new(),
+            flaky: Vec::new(),
+            shuffle_seed: opts.shuffle_seed,
+            baseline,
+            new_baseline: Baseline::new(),
+            regressed: 0,
             options: opts.options,
         })
     }
@@ -768,6 +1264,8 @@ impl ConsoleTestState {
                 TrFailedMsg(ref msg) => format!("failed: {}", msg),
                 TrIgnored => "ignored".to_owned(),
                 TrAllowedFail => "failed (allowed)".to_owned(),
+                TrTimedFail => "failed (time limit exceeded)".to_owned(),
+                TrFlaky(retries) => format!("ok (flaky, {} retries)", retries),
                 TrBench(ref bs) => fmt_bench_samples(bs),
             },
             test.name
@@ -775,10 +1273,28 @@ impl ConsoleTestState {
     }
 
     fn current_test_count(&self) -> usize {
-        self.passed + self.failed + self.ignored + self.measured + self.allowed_fail
+        self.passed
+            + self.failed
+            + self.ignored
+            + self.measured
+            + self.allowed_fail
+            + self.flaky.len()
+    }
+
+    fn is_success(&self) -> bool {
+        self.failed == 0 && self.regressed == 0
     }
 }
 
+// Format a duration as seconds with millisecond precision
+fn fmt_secs(duration: Duration) -> String {
+    format!(
+        "{}.{:03}",
+        duration.as_secs(),
+        duration.subsec_nanos() / 1_000_000
+    )
+}
+
 // Format a number with thousands separators
 fn fmt_thousands_sep(mut n: usize, sep: char) -> String {
     use std::fmt::Write;
@@ -822,6 +1338,11 @@ pub fn fmt_bench_samples(bs: &BenchSamples) -> String {
             .write_fmt(format_args!(" = {} MB/s", bs.mb_s))
             .unwrap();
     }
+    if let Some(ref comparison) = bs.comparison {
+        output
+            .write_fmt(format_args!(" [{}]", comparison))
+            .unwrap();
+    }
     output
 }
 
@@ -838,6 +1359,7 @@ pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Res
     let mut ntest = 0;
     let mut nbench = 0;
 
+    let (_, tests) = Fixtures::extract(tests);
     for test in filter_tests(&opts, tests) {
         use crate::TestFn::*;
 
@@ -855,6 +1377,7 @@ pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Res
                 nbench += 1;
                 "benchmark"
             }
+            StaticSetupFn(..) | StaticTeardownFn(..) => unreachable!(),
         };
 
         writeln!(output, "{}: {}", name, fntype)?;
@@ -888,6 +1411,7 @@ pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Res
 pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Result<bool> {
     fn callback(
         event: &TestEvent,
+        opts: &TestOpts,
         st: &mut ConsoleTestState,
         out: &mut dyn OutputFormatter,
     ) -> io::Result<()> {
@@ -899,14 +1423,28 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
             TeFilteredOut(filtered_out) => Ok(st.filtered_out = filtered_out),
             TeWait(ref test) => out.write_test_start(test),
             TeTimeout(ref test) => out.write_timeout(test),
-            TeResult(test, result, stdout) => {
+            TeFixtureFailed(fixture, result, stdout) => {
+                // Failed fixtures are reported like an additional failed test.
+                st.total += 1;
+                out.write_test_start(&fixture)?;
+                let event = TeResult(fixture, result, Duration::new(0, 0), stdout);
+                callback(&event, opts, st, out)
+            }
+            TeResult(test, mut result, exec_time, stdout) => {
+                if let TrBench(ref mut bs) = result {
+                    compare_to_baseline(opts, st, &test, bs);
+                }
                 st.write_log_result(&test, &result)?;
-                out.write_result(&test, &result, &*stdout)?;
+                out.write_result(&test, &result, exec_time, &*stdout)?;
                 match result {
                     TrOk => {
                         st.passed += 1;
                         st.not_failures.push((test, stdout));
                     }
+                    TrFlaky(retries) => {
+                        st.flaky.push((test.clone(), retries));
+                        st.not_failures.push((test, stdout));
+                    }
                     TrIgnored => st.ignored += 1,
                     TrAllowedFail => st.allowed_fail += 1,
                     TrBench(bs) => {
@@ -927,6 +1465,14 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
                         stdout.extend_from_slice(format!("note: {}", msg).as_bytes());
                         st.failures.push((test, stdout));
                     }
+                    TrTimedFail => {
+                        st.failed += 1;
+                        let mut stdout = stdout;
+                        stdout.extend_from_slice(
+                            b"note: test exceeded its time limit and was killed",
+                        );
+                        st.failures.push((test, stdout));
+                    }
                 }
                 Ok(())
             }
@@ -952,6 +1498,7 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
             use_color(opts),
             max_name_len,
             is_multithreaded,
+            opts.time_options,
         )),
         OutputFormat::Terse => Box::new(TerseFormatter::new(
             output,
@@ -960,6 +1507,7 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
             is_multithreaded,
         )),
         OutputFormat::Json => Box::new(JsonFormatter::new(output)),
+        OutputFormat::Junit => Box::new(JunitFormatter::new(output)),
     };
     let mut st = ConsoleTestState::new(opts)?;
     fn len_if_padded(t: &TestDescAndFn) -> usize {
@@ -969,13 +1517,45 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
         }
     }
 
-    run_tests(opts, tests, |x| callback(&x, &mut st, &mut *out))?;
+    run_tests(opts, tests, |x| callback(&x, opts, &mut st, &mut *out))?;
 
     assert!(st.current_test_count() == st.total);
 
+    // A run without benchmarks would otherwise wipe out the saved baseline.
+    if let Some(ref name) = opts.save_baseline {
+        if !st.new_baseline.is_empty() {
+            st.new_baseline.merge_into(&opts.baseline_dir(), name)?;
+        }
+    }
+
     return out.write_run_finish(&st);
 }
 
+fn compare_to_baseline(
+    opts: &TestOpts,
+    st: &mut ConsoleTestState,
+    test: &TestDesc,
+    bs: &mut BenchSamples,
+) {
+    let name = test.name.as_slice();
+    st.new_baseline.insert(name, bs.ns_iter_summ);
+
+    let baseline = match st.baseline.as_ref().and_then(|b| b.get(name)) {
+        Some(baseline) => baseline,
+        None => return,
+    };
+    let comparison = BenchComparison::new(
+        baseline,
+        &bs.ns_iter_summ,
+        BENCH_SAMPLE_COUNT,
+        opts.regression_threshold,
+    );
+    if comparison.change == Change::Regressed {
+        st.regressed += 1;
+    }
+    bs.comparison = Some(comparison);
+}
+
 #[test]
 fn should_sort_failures_before_printing_them() {
     let test_a = TestDesc {
@@ -983,6 +1563,7 @@ fn should_sort_failures_before_printing_them() {
         ignore: false,
         should_panic: ShouldPanic::No,
         allow_fail: false,
+        timeout: None,
     };
 
     let test_b = TestDesc {
@@ -990,9 +1571,10 @@ fn should_sort_failures_before_printing_them() {
         ignore: false,
         should_panic: ShouldPanic::No,
         allow_fail: false,
+        timeout: None,
     };
 
-    let mut out = PrettyFormatter::new(Raw(Vec::new()), false, 10, false);
+    let mut out = PrettyFormatter::new(Raw(Vec::new()), false, 10, false, None);
 
     let st = ConsoleTestState {
         log_out: None,
@@ -1007,6 +1589,11 @@ fn should_sort_failures_before_printing_them() {
         failures: vec![(test_b, Vec::new()), (test_a, Vec::new())],
         options: Options::new(),
         not_failures: Vec::new(),
+        flaky: Vec::new(),
+        shuffle_seed: None,
+        baseline: None,
+        new_baseline: Baseline::new(),
+        regressed: 0,
     };
 
     out.write_failures(&st).unwrap();
@@ -1020,6 +1607,81 @@ fn should_sort_failures_before_printing_them() {
     assert!(apos < bpos);
 }
 
+#[test]
+fn should_report_time_when_requested() {
+    let desc = TestDesc {
+        name: StaticTestName("a"),
+        ignore: false,
+        should_panic: ShouldPanic::No,
+        allow_fail: false,
+        timeout: None,
+    };
+    let time_options = TestTimeOptions {
+        error_on_excess: false,
+        threshold: TimeThreshold::default(),
+    };
+
+    let mut out = PrettyFormatter::new(Raw(Vec::new()), false, 10, true, Some(time_options));
+    out.write_result(&desc, &TrOk, Duration::from_millis(1234), b"").unwrap();
+    let s = match out.output_location() {
+        &Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &Pretty(_) => unreachable!(),
+    };
+    assert_eq!(s, "test a ... ok <1.234s>\n");
+}
+
+#[test]
+fn junit_report_lists_every_result() {
+    let desc = |name| TestDesc {
+        name: StaticTestName(name),
+        ignore: false,
+        should_panic: ShouldPanic::No,
+        allow_fail: false,
+        timeout: None,
+    };
+
+    let mut out = JunitFormatter::new(Raw(Vec::new()));
+    out.write_run_start(3).unwrap();
+    let time = Duration::from_millis(1500);
+    out.write_result(&desc("m::passes"), &TrOk, time, b"").unwrap();
+    out.write_result(&desc("m::fails"), &TrFailedMsg("a < b".to_owned()), time, b"out & err")
+        .unwrap();
+    out.write_result(&desc("skipped"), &TrIgnored, Duration::new(0, 0), b"").unwrap();
+
+    let st = ConsoleTestState {
+        log_out: None,
+        total: 3,
+        passed: 1,
+        failed: 1,
+        ignored: 1,
+        allowed_fail: 0,
+        filtered_out: 0,
+        measured: 0,
+        metrics: MetricMap::new(),
+        failures: Vec::new(),
+        options: Options::new(),
+        not_failures: Vec::new(),
+        flaky: Vec::new(),
+        shuffle_seed: None,
+        baseline: None,
+        new_baseline: Baseline::new(),
+        regressed: 0,
+    };
+    assert!(!out.write_run_finish(&st).unwrap());
+
+    let s = match out.output_location() {
+        &Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &Pretty(_) => unreachable!(),
+    };
+    assert!(s.starts_with("<?xml"));
+    assert!(s.contains("tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\""));
+    assert!(s.contains("<testcase classname=\"m\" name=\"passes\" time=\"1.500\"/>"));
+    assert!(s.contains("<failure type=\"assert\" message=\"a &lt; b\"/>"));
+    assert!(s.contains("<system-out>out &amp; err</system-out>"));
+    assert!(s.contains("<testcase classname=\"\" name=\"skipped\" time=\"0.000\">\n<skipped/>"));
+    assert!(s.trim_end().ends_with("</testsuites>"));
+}
+
 fn use_color(opts: &TestOpts) -> bool {
     match opts.color {
         AutoColor => !opts.nocapture && stdout_isatty(),
@@ -1064,12 +1726,14 @@ fn stdout_isatty() -> bool {
 pub enum TestEvent {
     TeFiltered(Vec<TestDesc>),
     TeWait(TestDesc),
-    TeResult(TestDesc, TestResult, Vec<u8>),
+    TeResult(TestDesc, TestResult, Duration, Vec<u8>),
     TeTimeout(TestDesc),
     TeFilteredOut(usize),
+    /// A setup or teardown function failed.
+    TeFixtureFailed(TestDesc, TestResult, Vec<u8>),
 }
 
-pub type MonitorMsg = (TestDesc, TestResult, Vec<u8>);
+pub type MonitorMsg = (TestDesc, TestResult, Duration, Vec<u8>);
 
 struct Sink(Arc<Mutex<Vec<u8>>>);
 impl Write for Sink {
@@ -1085,13 +1749,11 @@ pub fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F)
 where
     F: FnMut(TestEvent) -> io::Result<()>,
 {
-    use std::collections::{self, HashMap};
-    use std::hash::BuildHasherDefault;
     use std::sync::mpsc::RecvTimeoutError;
     // Use a deterministic hasher
-    type TestMap =
-        HashMap<TestDesc, Instant, BuildHasherDefault<collections::hash_map::DefaultHasher>>;
+    type TestMap = HashMap<TestDesc, Instant, BuildHasherDefault<DefaultHasher>>;
 
+    let (mut fixtures, tests) = Fixtures::extract(tests);
     let tests_len = tests.len();
 
     let mut filtered_tests = filter_tests(opts, tests);
@@ -1108,6 +1770,11 @@ where
         filtered_tests
     };
 
+    let filtered_tests = match opts.shuffle_seed {
+        Some(seed) => shuffle_tests(seed, filtered_tests),
+        None => filtered_tests,
+    };
+
     let filtered_out = tests_len - filtered_tests.len();
     callback(TeFilteredOut(filtered_out))?;
 
@@ -1121,6 +1788,42 @@ where
             _ => false,
         });
 
+    // Only the tests which are actually run need their fixtures.
+    let force_ignore = !opts.run_tests;
+    for test in &filtered_tests {
+        if !is_ignored(force_ignore, &test.desc) {
+            fixtures.add_test(&test.desc);
+        }
+    }
+    if opts.bench_benchmarks {
+        for b in &filtered_benchs {
+            if !is_ignored(false, &b.desc) {
+                fixtures.add_test(&b.desc);
+            }
+        }
+    }
+
+    // Fixtures only run in this process, so a test run in a subprocess would
+    // not see the state they set up.
+    if supports_subprocesses() {
+        let in_subprocess = filtered_tests.iter().find(|test| {
+            let timeout = test.desc.timeout.map(Duration::from_secs).or(opts.test_timeout);
+            !is_ignored(force_ignore, &test.desc)
+                && (opts.isolate || timeout.is_some())
+                && fixtures.applies_to(&test.desc)
+        });
+        if let Some(test) = in_subprocess {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "test `{}` has fixtures, which cannot be combined with --isolate \
+                     or a time limit",
+                    test.desc.name.as_slice()
+                ),
+            ));
+        }
+    }
+
     let concurrency = opts.test_threads.unwrap_or_else(get_concurrency);
 
     let mut remaining = filtered_tests;
@@ -1130,6 +1833,17 @@ where
     let (tx, rx) = channel::<MonitorMsg>();
 
     let mut running_tests: TestMap = HashMap::default();
+    let mut retries = Retries::new(opts.retries);
+    if opts.retries > 0 {
+        let once = remaining.iter().filter(|test| Retries::runs_once(test)).count();
+        if once > 0 {
+            eprintln!(
+                "warning: --retries does not apply to {} test{} that can only be run once",
+                once,
+                if once == 1 { "" } else { "s" }
+            );
+        }
+    }
 
     fn get_timed_out_tests(running_tests: &mut TestMap) -> Vec<TestDesc> {
         let now = Instant::now();
@@ -1163,19 +1877,48 @@ where
     if concurrency == 1 {
         while !remaining.is_empty() {
             let test = remaining.pop().unwrap();
-            callback(TeWait(test.desc.clone()))?;
-            run_test(opts, !opts.run_tests, test, tx.clone(), Concurrent::No);
-            let (test, result, stdout) = rx.recv().unwrap();
-            callback(TeResult(test, result, stdout))?;
+            let setup_failed =
+                set_up_fixtures(opts, &mut fixtures, force_ignore, &test.desc, &mut callback)?;
+            if !retries.is_retry(&test.desc) {
+                callback(TeWait(test.desc.clone()))?;
+            }
+            if let Some(result) = setup_failed {
+                callback(TeResult(test.desc.clone(), result, Duration::new(0, 0), Vec::new()))?;
+                tear_down_fixtures(opts, &mut fixtures, force_ignore, &test.desc, &mut callback)?;
+                continue;
+            }
+            retries.track(&test);
+            run_test(opts, force_ignore, test, tx.clone(), Concurrent::No);
+            let (test, result, exec_time, stdout) = rx.recv().unwrap();
+            let result = check_exec_time(opts, &test, result, exec_time);
+            if let Some(retry) = retries.retry(&test, &result) {
+                remaining.push(retry);
+                continue;
+            }
+            let result = retries.finish(&test, result);
+            callback(TeResult(test.clone(), result, exec_time, stdout))?;
+            tear_down_fixtures(opts, &mut fixtures, force_ignore, &test, &mut callback)?;
         }
     } else {
         while pending > 0 || !remaining.is_empty() {
             while pending < concurrency && !remaining.is_empty() {
                 let test = remaining.pop().unwrap();
+                let setup_failed =
+                    set_up_fixtures(opts, &mut fixtures, force_ignore, &test.desc, &mut callback)?;
+                if let Some(result) = setup_failed {
+                    let desc = test.desc;
+                    callback(TeWait(desc.clone()))?;
+                    callback(TeResult(desc.clone(), result, Duration::new(0, 0), Vec::new()))?;
+                    tear_down_fixtures(opts, &mut fixtures, force_ignore, &desc, &mut callback)?;
+                    continue;
+                }
                 let timeout = Instant::now() + Duration::from_secs(TEST_WARN_TIMEOUT_S);
                 running_tests.insert(test.desc.clone(), timeout);
-                callback(TeWait(test.desc.clone()))?; //here no pad
-                run_test(opts, !opts.run_tests, test, tx.clone(), Concurrent::Yes);
+                if !retries.is_retry(&test.desc) {
+                    callback(TeWait(test.desc.clone()))?; //here no pad
+                }
+                retries.track(&test);
+                run_test(opts, force_ignore, test, tx.clone(), Concurrent::Yes);
                 pending += 1;
             }
 
@@ -1195,26 +1938,187 @@ where
                 }
             }
 
-            let (desc, result, stdout) = res.unwrap();
+            let (desc, result, exec_time, stdout) = res.unwrap();
             running_tests.remove(&desc);
 
-            callback(TeResult(desc, result, stdout))?;
             pending -= 1;
+
+            let result = check_exec_time(opts, &desc, result, exec_time);
+            if let Some(retry) = retries.retry(&desc, &result) {
+                remaining.push(retry);
+                continue;
+            }
+            let result = retries.finish(&desc, result);
+            callback(TeResult(desc.clone(), result, exec_time, stdout))?;
+            tear_down_fixtures(opts, &mut fixtures, force_ignore, &desc, &mut callback)?;
         }
     }
 
     if opts.bench_benchmarks {
         // All benchmarks run at the end, in serial.
         for b in filtered_benchs {
+            let setup_failed = set_up_fixtures(opts, &mut fixtures, false, &b.desc, &mut callback)?;
             callback(TeWait(b.desc.clone()))?;
+            if let Some(result) = setup_failed {
+                callback(TeResult(b.desc.clone(), result, Duration::new(0, 0), Vec::new()))?;
+                tear_down_fixtures(opts, &mut fixtures, false, &b.desc, &mut callback)?;
+                continue;
+            }
             run_test(opts, false, b, tx.clone(), Concurrent::No);
-            let (test, result, stdout) = rx.recv().unwrap();
-            callback(TeResult(test, result, stdout))?;
+            let (test, result, exec_time, stdout) = rx.recv().unwrap();
+            let result = check_exec_time(opts, &test, result, exec_time);
+            callback(TeResult(test.clone(), result, exec_time, stdout))?;
+            tear_down_fixtures(opts, &mut fixtures, false, &test, &mut callback)?;
         }
     }
     Ok(())
 }
 
+// Runs the setups `desc` needs which have not run yet. If one of them fails,
+// returns the result to report for `desc` instead of running it.
+fn set_up_fixtures<F>(
+    opts: &TestOpts,
+    fixtures: &mut Fixtures,
+    force_ignore: bool,
+    desc: &TestDesc,
+    callback: &mut F,
+) -> io::Result<Option<TestResult>>
+where
+    F: FnMut(TestEvent) -> io::Result<()>,
+{
+    if is_ignored(force_ignore, desc) {
+        return Ok(None);
+    }
+    let failed = fixtures.set_up(desc, |setup| run_fixture(opts, setup, callback))?;
+    Ok(failed.map(|name| TrFailedMsg(format!("not run because setup `{}` failed", name))))
+}
+
+// Runs the teardowns which are due now that `desc` has finished.
+fn tear_down_fixtures<F>(
+    opts: &TestOpts,
+    fixtures: &mut Fixtures,
+    force_ignore: bool,
+    desc: &TestDesc,
+    callback: &mut F,
+) -> io::Result<()>
+where
+    F: FnMut(TestEvent) -> io::Result<()>,
+{
+    if is_ignored(force_ignore, desc) {
+        return Ok(());
+    }
+    fixtures.tear_down(desc, |teardown| run_fixture(opts, teardown, callback))
+}
+
+fn run_fixture<F>(opts: &TestOpts, fixture: &Fixture, callback: &mut F) -> io::Result<bool>
+where
+    F: FnMut(TestEvent) -> io::Result<()>,
+{
+    let (tx, rx) = channel();
+    run_test(opts, false, fixture.to_test(), tx, Concurrent::No);
+    let (desc, result, _, stdout) = rx.recv().unwrap();
+    match result {
+        TrFailed | TrFailedMsg(_) => {
+            callback(TeFixtureFailed(desc, result, stdout))?;
+            Ok(false)
+        }
+        _ => Ok(true),
+    }
+}
+
+/// Keeps track of the tests that may be run again with `--retries`.
+struct Retries {
+    max: usize,
+    /// The number of failed runs so far and the function of each test.
+    tests: HashMap<TestDesc, (usize, fn()), BuildHasherDefault<DefaultHasher>>,
+}
+
+impl Retries {
+    fn new(max: usize) -> Retries {
+        Retries {
+            max,
+            tests: HashMap::default(),
+        }
+    }
+
+    /// Returns whether `test` cannot be retried because its function can only
+    /// be called once. Dynamic tests, such as doctests and the tests of custom
+    /// harnesses, are boxed closures that are consumed by running them.
+    fn runs_once(test: &TestDescAndFn) -> bool {
+        match test.testfn {
+            DynTestFn(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Remembers how to run `test` again, if it can be retried.
+    fn track(&mut self, test: &TestDescAndFn) {
+        if self.max == 0 {
+            return;
+        }
+        if let StaticTestFn(f) = test.testfn {
+            self.tests.entry(test.desc.clone()).or_insert((0, f));
+        }
+    }
+
+    fn is_retry(&self, desc: &TestDesc) -> bool {
+        self.tests.get(desc).map_or(false, |&(failures, _)| failures > 0)
+    }
+
+    /// Returns the test to run again if it failed and has retries left.
+    fn retry(&mut self, desc: &TestDesc, result: &TestResult) -> Option<TestDescAndFn> {
+        match *result {
+            TrFailed | TrFailedMsg(_) | TrTimedFail => {}
+            _ => return None,
+        }
+        let (failures, f) = self.tests.get_mut(desc)?;
+        if *failures == self.max {
+            return None;
+        }
+        *failures += 1;
+        Some(TestDescAndFn {
+            desc: desc.clone(),
+            testfn: StaticTestFn(*f),
+        })
+    }
+
+    /// Returns the final result of a test, which is flaky if it only passed
+    /// after being retried.
+    fn finish(&mut self, desc: &TestDesc, result: TestResult) -> TestResult {
+        match (self.tests.remove(desc), result) {
+            (Some((failures, _)), TrOk) if failures > 0 => TrFlaky(failures),
+            (_, result) => result,
+        }
+    }
+}
+
+// Turns a successful test that ran for longer than the critical threshold
+// into a failure, if requested with `--ensure-time`.
+fn check_exec_time(
+    opts: &TestOpts,
+    desc: &TestDesc,
+    result: TestResult,
+    exec_time: Duration,
+) -> TestResult {
+    match (result, opts.time_options) {
+        (TrOk, Some(time_opts))
+            if time_opts.error_on_excess && time_opts.is_critical(exec_time) =>
+        {
+            let msg = format!(
+                "test ran for {}s, exceeding the critical time threshold of {}s",
+                fmt_secs(exec_time),
+                fmt_secs(time_opts.threshold.critical)
+            );
+            if desc.allow_fail {
+                TrAllowedFail
+            } else {
+                TrFailedMsg(msg)
+            }
+        }
+        (result, _) => result,
+    }
+}
+
 #[allow(deprecated)]
 fn get_concurrency() -> usize {
     return match env::var("RUST_TEST_THREADS") {
@@ -1397,12 +2301,44 @@ pub fn filter_tests(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> Vec<TestDescA
         RunIgnored::No => {}
     }
 
+    // Only keep the tests of the requested shard
+    if let Some(shard) = opts.shard {
+        filtered.retain(|test| shard.contains(&test.desc.name));
+    }
+
     // Sort the tests alphabetically
     filtered.sort_by(|t1, t2| t1.desc.name.as_slice().cmp(t2.desc.name.as_slice()));
 
     filtered
 }
 
+/// Reorders `tests` pseudo-randomly based on `seed`. The position of each test
+/// only depends on the seed and its name, so the relative order of any two
+/// tests is the same no matter which other tests are run alongside them.
+pub fn shuffle_tests(seed: u64, tests: Vec<TestDescAndFn>) -> Vec<TestDescAndFn> {
+    let seeded = fnv1a(FNV_OFFSET_BASIS, &seed.to_le_bytes());
+    let mut keyed = tests
+        .into_iter()
+        .map(|test| {
+            let hash = fnv1a(seeded, test.desc.name.as_slice().as_bytes());
+            // FNV-1a mixes the last bytes poorly, so names that only differ at
+            // the end would stay close together without a final mixing step
+            // (the 64-bit finalizer of MurmurHash3).
+            let mut key = hash ^ (hash >> 33);
+            key = key.wrapping_mul(0xff51_afd7_ed55_8ccd);
+            key ^= key >> 33;
+            key = key.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+            key ^= key >> 33;
+            (key, test)
+        })
+        .collect::<Vec<_>>();
+    keyed.sort_by(|(k1, t1), (k2, t2)| {
+        k1.cmp(k2)
+            .then_with(|| t1.desc.name.as_slice().cmp(t2.desc.name.as_slice()))
+    });
+    keyed.into_iter().map(|(_, test)| test).collect()
+}
+
 pub fn convert_benchmarks_to_tests(tests: Vec<TestDescAndFn>) -> Vec<TestDescAndFn> {
     // convert benchmarks to tests, if we're not benchmarking them
     tests
@@ -1434,12 +2370,10 @@ pub fn run_test(
 ) {
     let TestDescAndFn { desc, testfn } = test;
 
-    let ignore_because_panic_abort = cfg!(target_arch = "wasm32")
-        && !cfg!(target_os = "emscripten")
-        && desc.should_panic != ShouldPanic::No;
-
-    if force_ignore || desc.ignore || ignore_because_panic_abort {
-        monitor_ch.send((desc, TrIgnored, Vec::new())).unwrap();
+    if is_ignored(force_ignore, &desc) {
+        monitor_ch
+            .send((desc, TrIgnored, Duration::new(0, 0), Vec::new()))
+            .unwrap();
         return;
     }
 
@@ -1465,7 +2399,9 @@ pub fn run_test(
                 None
             };
 
+            let start = Instant::now();
             let result = catch_unwind(AssertUnwindSafe(testfn));
+            let exec_time = start.elapsed();
 
             if let Some((printio, panicio)) = oldio {
                 io::set_print(printio);
@@ -1475,7 +2411,7 @@ pub fn run_test(
             let test_result = calc_result(&desc, result);
             let stdout = data.lock().unwrap().to_vec();
             monitor_ch
-                .send((desc.clone(), test_result, stdout))
+                .send((desc.clone(), test_result, exec_time, stdout))
                 .unwrap();
         };
 
@@ -1491,6 +2427,8 @@ pub fn run_test(
         }
     }
 
+    let timeout = desc.timeout.map(Duration::from_secs).or(opts.test_timeout);
+
     match testfn {
         DynBenchFn(bencher) => {
             crate::bench::benchmark(desc, monitor_ch, opts.nocapture, |harness| {
@@ -1502,11 +2440,16 @@ pub fn run_test(
                 (benchfn.clone())(harness)
             });
         }
+        DynTestFn(_) | StaticTestFn(_)
+            if (opts.isolate || timeout.is_some()) && supports_subprocesses() =>
+        {
+            run_test_in_subprocess(desc, monitor_ch, opts.nocapture, timeout, concurrency)
+        }
         DynTestFn(f) => {
             let cb = move || __rust_begin_short_backtrace(f);
             run_test_inner(desc, monitor_ch, opts.nocapture, Box::new(cb), concurrency)
         }
-        StaticTestFn(f) => run_test_inner(
+        StaticTestFn(f) | StaticSetupFn(f) | StaticTeardownFn(f) => run_test_inner(
             desc,
             monitor_ch,
             opts.nocapture,
@@ -1516,6 +2459,241 @@ pub fn run_test(
     }
 }
 
+fn is_ignored(force_ignore: bool, desc: &TestDesc) -> bool {
+    let ignore_because_panic_abort = cfg!(target_arch = "wasm32")
+        && !cfg!(target_os = "emscripten")
+        && desc.should_panic != ShouldPanic::No;
+
+    force_ignore || desc.ignore || ignore_because_panic_abort
+}
+
+fn supports_subprocesses() -> bool {
+    !cfg!(target_os = "emscripten")
+        && !cfg!(target_arch = "wasm32")
+        && !cfg!(all(target_vendor = "fortanix", target_env = "sgx"))
+}
+
+// Runs a test by re-executing the current binary with the same arguments,
+// asking it to run only `desc` (see `run_test_in_spawned_subprocess`). Unlike
+// a test thread, the subprocess can be killed once `timeout` has elapsed, and
+// it cannot bring down the rest of the run by crashing.
+fn run_test_in_subprocess(
+    desc: TestDesc,
+    monitor_ch: Sender<MonitorMsg>,
+    nocapture: bool,
+    timeout: Option<Duration>,
+    concurrency: Concurrent,
+) {
+    let name = desc.name.clone();
+    let runtest = move || {
+        let start = Instant::now();
+        let (result, stdout) = match spawn_test_subprocess(&desc, nocapture, timeout) {
+            Ok(res) => res,
+            Err(e) => (
+                TrFailedMsg(format!("failed to spawn test subprocess: {}", e)),
+                Vec::new(),
+            ),
+        };
+        monitor_ch.send((desc, result, start.elapsed(), stdout)).unwrap();
+    };
+
+    if concurrency == Concurrent::Yes {
+        let cfg = thread::Builder::new().name(name.as_slice().to_owned());
+        cfg.spawn(runtest).unwrap();
+    } else {
+        runtest();
+    }
+}
+
+fn spawn_test_subprocess(
+    desc: &TestDesc,
+    nocapture: bool,
+    timeout: Option<Duration>,
+) -> io::Result<(TestResult, Vec<u8>)> {
+    use std::process::{Command, Stdio};
+
+    fn read_in_background<R: Read + Send + 'static>(
+        pipe: Option<R>,
+    ) -> thread::JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    }
+
+    let mut child = Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .env(SUBPROCESS_TEST_VAR, desc.name.as_slice())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(if nocapture {
+            Stdio::inherit()
+        } else {
+            Stdio::piped()
+        })
+        .spawn()?;
+
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let mut timed_out = false;
+    let status = match timeout {
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    // The child may have exited in the meantime, which is fine.
+                    let _ = child.kill();
+                    timed_out = true;
+                    break child.wait()?;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        None => child.wait()?,
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    let (result, mut output) = match parse_subprocess_output(&stdout) {
+        _ if timed_out => (TrTimedFail, stdout),
+        Some((result, output)) if status.success() => (result, output),
+        _ => {
+            let result = if desc.allow_fail {
+                TrAllowedFail
+            } else {
+                TrFailedMsg(describe_abnormal_exit(status))
+            };
+            (result, stdout)
+        }
+    };
+
+    if nocapture {
+        // The subprocess wrote its output to our pipe, pass it through.
+        io::stdout().write_all(&output)?;
+        output.clear();
+    }
+    output.extend_from_slice(&stderr);
+
+    Ok((result, output))
+}
+
+fn describe_abnormal_exit(status: process::ExitStatus) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            return format!("test subprocess was terminated by {}", signal_name(signal));
+        }
+    }
+
+    match status.code() {
+        Some(code) => format!(
+            "test subprocess exited with status {} before reporting a result",
+            code
+        ),
+        None => format!("test subprocess terminated abnormally ({})", status),
+    }
+}
+
+#[cfg(unix)]
+fn signal_name(signal: libc::c_int) -> String {
+    let name = match signal {
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGILL => "SIGILL",
+        libc::SIGINT => "SIGINT",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGTRAP => "SIGTRAP",
+        _ => return format!("signal {}", signal),
+    };
+    format!("signal {} ({})", signal, name)
+}
+
+// Entry point of a test binary re-executed by `spawn_test_subprocess`: runs
+// the single test `name` in this process, writes its captured output
+// followed by its result to stdout, and exits.
+fn run_test_in_spawned_subprocess(mut opts: TestOpts, name: &str, tests: Vec<TestDescAndFn>) -> ! {
+    let (_, tests) = Fixtures::extract(tests);
+    let mut test = match tests.into_iter().find(|t| t.desc.name.as_slice() == name) {
+        Some(test) => test,
+        None => {
+            eprintln!("error: no test named `{}` in this binary", name);
+            process::exit(101);
+        }
+    };
+
+    // The parent process enforces the time limit and already isolates us.
+    opts.test_timeout = None;
+    opts.isolate = false;
+    test.desc.timeout = None;
+    test.desc.ignore = false;
+
+    // Run the test on a thread named after it, as it would be in the parent.
+    let (tx, rx) = channel();
+    run_test(&opts, false, test, tx, Concurrent::Yes);
+    let (_, result, _, stdout) = rx.recv().unwrap();
+
+    let mut out = io::stdout();
+    let written = out
+        .write_all(&stdout)
+        .and_then(|_| out.write_all(SUBPROCESS_RESULT_MARKER))
+        .and_then(|_| out.write_all(encode_subprocess_result(&result).as_bytes()))
+        .and_then(|_| out.flush());
+    if let Err(e) = written {
+        eprintln!("error: failed to report test result: {}", e);
+        process::exit(101);
+    }
+    process::exit(0)
+}
+
+fn encode_subprocess_result(result: &TestResult) -> String {
+    match *result {
+        TrOk => "ok".to_owned(),
+        TrFailed => "failed".to_owned(),
+        TrFailedMsg(ref msg) => format!("failed-msg {}", msg),
+        TrIgnored => "ignored".to_owned(),
+        TrAllowedFail => "allowed-fail".to_owned(),
+        TrTimedFail => "timed-fail".to_owned(),
+        TrFlaky(_) => unreachable!("retries are only done by the parent process"),
+        TrBench(_) => unreachable!("benchmarks are never run in a subprocess"),
+    }
+}
+
+// Splits the stdout of a subprocess test into the captured output and the
+// result written by `run_test_in_spawned_subprocess`. Returns `None` if the
+// subprocess did not get to report a result.
+fn parse_subprocess_output(stdout: &[u8]) -> Option<(TestResult, Vec<u8>)> {
+    let marker_len = SUBPROCESS_RESULT_MARKER.len();
+    let pos = (0..(stdout.len() + 1).saturating_sub(marker_len))
+        .rev()
+        .find(|&i| stdout[i..].starts_with(SUBPROCESS_RESULT_MARKER))?;
+    let result = match String::from_utf8_lossy(&stdout[pos + marker_len..]).as_ref() {
+        "ok" => TrOk,
+        "failed" => TrFailed,
+        "ignored" => TrIgnored,
+        "allowed-fail" => TrAllowedFail,
+        "timed-fail" => TrTimedFail,
+        s if s.starts_with("failed-msg ") => TrFailedMsg(s["failed-msg ".len()..].to_owned()),
+        _ => return None,
+    };
+    Some((result, stdout[..pos].to_vec()))
+}
+
 /// Fixed frame used to clean the backtrace with `RUST_BACKTRACE=1`.
 #[inline(never)]
 fn __rust_begin_short_backtrace<F: FnOnce()>(f: F) {
@@ -1661,7 +2839,7 @@ where
     n = cmp::max(1, n);
 
     let mut total_run = Duration::new(0, 0);
-    let samples: &mut [f64] = &mut [0.0_f64; 50];
+    let samples: &mut [f64] = &mut [0.0_f64; BENCH_SAMPLE_COUNT];
     loop {
         let loop_start = Instant::now();
 
@@ -1717,6 +2895,7 @@ pub mod bench {
     use std::io;
     use std::panic::{catch_unwind, AssertUnwindSafe};
     use std::sync::{Arc, Mutex};
+    use std::time::Instant;
 
     pub fn benchmark<F>(desc: TestDesc, monitor_ch: Sender<MonitorMsg>, nocapture: bool, f: F)
     where
@@ -1740,7 +2919,9 @@ pub mod bench {
             None
         };
 
+        let start = Instant::now();
         let result = catch_unwind(AssertUnwindSafe(|| bs.bench(f)));
+        let exec_time = start.elapsed();
 
         if let Some((printio, panicio)) = oldio {
             io::set_print(printio);
@@ -1756,6 +2937,7 @@ pub mod bench {
                 let bs = BenchSamples {
                     ns_iter_summ,
                     mb_s: mb_s as usize,
+                    comparison: None,
                 };
                 TestResult::TrBench(bs)
             }
@@ -1766,6 +2948,7 @@ pub mod bench {
                 let bs = BenchSamples {
                     ns_iter_summ: stats::Summary::new(samples),
                     mb_s: 0,
+                    comparison: None,
                 };
                 TestResult::TrBench(bs)
             }
@@ -1773,7 +2956,9 @@ pub mod bench {
         };
 
         let stdout = data.lock().unwrap().to_vec();
-        monitor_ch.send((desc, test_result, stdout)).unwrap();
+        monitor_ch
+            .send((desc, test_result, exec_time, stdout))
+            .unwrap();
     }
 
     pub fn run_once<F>(f: F)
@@ -1794,12 +2979,17 @@ mod tests {
     use crate::bench;
     use crate::test::{
         filter_tests, parse_opts, run_test, DynTestFn, DynTestName, MetricMap, RunIgnored,
-        ShouldPanic, StaticTestName, TestDesc, TestDescAndFn, TestOpts, TrFailed, TrFailedMsg,
-        TrIgnored, TrOk,
+        ShouldPanic, StaticSetupFn, StaticTeardownFn, StaticTestFn, StaticTestName, TestDesc,
+        TestDescAndFn, TestOpts, TrFailed, TrFailedMsg, TrFlaky, TrIgnored, TrOk, TrTimedFail,
     };
     use crate::Bencher;
     use crate::Concurrent;
+    use crate::TestEvent::{TeFixtureFailed, TeResult, TeWait};
+    use crate::{run_tests, shuffle_tests, TestShard};
+    use crate::{check_exec_time, TestTimeOptions, TimeThreshold};
+    use crate::{encode_subprocess_result, parse_subprocess_output, SUBPROCESS_RESULT_MARKER};
     use std::sync::mpsc::channel;
+    use std::time::Duration;
 
     fn one_ignored_one_unignored_test() -> Vec<TestDescAndFn> {
         vec![
@@ -1809,6 +2999,7 @@ mod tests {
                     ignore: true,
                     should_panic: ShouldPanic::No,
                     allow_fail: false,
+                    timeout: None,
                 },
                 testfn: DynTestFn(Box::new(move || {})),
             },
@@ -1818,6 +3009,7 @@ mod tests {
                     ignore: false,
                     should_panic: ShouldPanic::No,
                     allow_fail: false,
+                    timeout: None,
                 },
                 testfn: DynTestFn(Box::new(move || {})),
             },
@@ -1835,12 +3027,13 @@ mod tests {
                 ignore: true,
                 should_panic: ShouldPanic::No,
                 allow_fail: false,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(f)),
         };
         let (tx, rx) = channel();
         run_test(&TestOpts::new(), false, desc, tx, Concurrent::No);
-        let (_, res, _) = rx.recv().unwrap();
+        let (_, res, _, _) = rx.recv().unwrap();
         assert!(res != TrOk);
     }
 
@@ -1853,12 +3046,13 @@ mod tests {
                 ignore: true,
                 should_panic: ShouldPanic::No,
                 allow_fail: false,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(f)),
         };
         let (tx, rx) = channel();
         run_test(&TestOpts::new(), false, desc, tx, Concurrent::No);
-        let (_, res, _) = rx.recv().unwrap();
+        let (_, res, _, _) = rx.recv().unwrap();
         assert!(res == TrIgnored);
     }
 
@@ -1873,12 +3067,13 @@ mod tests {
                 ignore: false,
                 should_panic: ShouldPanic::Yes,
                 allow_fail: false,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(f)),
         };
         let (tx, rx) = channel();
         run_test(&TestOpts::new(), false, desc, tx, Concurrent::No);
-        let (_, res, _) = rx.recv().unwrap();
+        let (_, res, _, _) = rx.recv().unwrap();
         assert!(res == TrOk);
     }
 
@@ -1893,12 +3088,13 @@ mod tests {
                 ignore: false,
                 should_panic: ShouldPanic::YesWithMessage("error message"),
                 allow_fail: false,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(f)),
         };
         let (tx, rx) = channel();
         run_test(&TestOpts::new(), false, desc, tx, Concurrent::No);
-        let (_, res, _) = rx.recv().unwrap();
+        let (_, res, _, _) = rx.recv().unwrap();
         assert!(res == TrOk);
     }
 
@@ -1915,12 +3111,13 @@ mod tests {
                 ignore: false,
                 should_panic: ShouldPanic::YesWithMessage(expected),
                 allow_fail: false,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(f)),
         };
         let (tx, rx) = channel();
         run_test(&TestOpts::new(), false, desc, tx, Concurrent::No);
-        let (_, res, _) = rx.recv().unwrap();
+        let (_, res, _, _) = rx.recv().unwrap();
         assert!(res == TrFailedMsg(format!("{} '{}'", failed_msg, expected)));
     }
 
@@ -1933,12 +3130,13 @@ mod tests {
                 ignore: false,
                 should_panic: ShouldPanic::Yes,
                 allow_fail: false,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(f)),
         };
         let (tx, rx) = channel();
         run_test(&TestOpts::new(), false, desc, tx, Concurrent::No);
-        let (_, res, _) = rx.recv().unwrap();
+        let (_, res, _, _) = rx.recv().unwrap();
         assert!(res == TrFailed);
     }
 
@@ -1965,6 +3163,66 @@ mod tests {
         assert_eq!(opts.run_ignored, RunIgnored::Yes);
     }
 
+    #[test]
+    fn parse_test_timeout_flag() {
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--test-timeout=30".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert_eq!(opts.test_timeout, Some(Duration::from_secs(30)));
+
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--test-timeout=0".to_string(),
+        ];
+        let err = parse_opts(&args).unwrap().unwrap_err();
+        assert_eq!(err, "argument for --test-timeout must not be 0");
+    }
+
+    #[test]
+    fn parse_isolate_flag() {
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--isolate".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert!(opts.isolate);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn describe_subprocess_killed_by_signal() {
+        use crate::describe_abnormal_exit;
+        use std::os::unix::process::ExitStatusExt;
+        use std::process;
+
+        let status = process::ExitStatus::from_raw(libc::SIGABRT);
+        assert_eq!(
+            describe_abnormal_exit(status),
+            format!("test subprocess was terminated by signal {} (SIGABRT)", libc::SIGABRT)
+        );
+    }
+
+    #[test]
+    fn subprocess_output_round_trip() {
+        for result in vec![TrOk, TrFailed, TrFailedMsg("a\nmessage".to_string()), TrTimedFail] {
+            let mut stdout = b"captured\noutput".to_vec();
+            stdout.extend_from_slice(SUBPROCESS_RESULT_MARKER);
+            stdout.extend_from_slice(encode_subprocess_result(&result).as_bytes());
+            let (parsed, output) = parse_subprocess_output(&stdout).unwrap();
+            assert!(parsed == result);
+            assert_eq!(output, b"captured\noutput");
+        }
+
+        // A subprocess that died before reporting has no result.
+        assert!(parse_subprocess_output(b"thread 'main' has overflowed its stack").is_none());
+        assert!(parse_subprocess_output(b"").is_none());
+    }
+
     #[test]
     pub fn filter_for_ignored_option() {
         // When we run ignored tests the test filter should filter out all the
@@ -2012,6 +3270,7 @@ mod tests {
                 ignore: false,
                 should_panic: ShouldPanic::Yes,
                 allow_fail: false,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(move || {})),
         });
@@ -2033,6 +3292,7 @@ mod tests {
                         ignore: false,
                         should_panic: ShouldPanic::No,
                         allow_fail: false,
+                        timeout: None,
                     },
                     testfn: DynTestFn(Box::new(move || {})),
                 })
@@ -2144,6 +3404,7 @@ mod tests {
                         ignore: false,
                         should_panic: ShouldPanic::No,
                         allow_fail: false,
+                        timeout: None,
                     },
                     testfn: DynTestFn(Box::new(testfn)),
                 };
@@ -2172,6 +3433,432 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_shuffle_flags() {
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--shuffle-seed=42".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert_eq!(opts.shuffle_seed, Some(42));
+
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--shuffle".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert!(opts.shuffle_seed.is_some());
+
+        let args = vec!["progname".to_string(), "--shuffle".to_string()];
+        assert!(parse_opts(&args).unwrap().is_err());
+    }
+
+    #[test]
+    pub fn shuffle_is_deterministic() {
+        fn tests() -> Vec<TestDescAndFn> {
+            (0..20)
+                .map(|i| TestDescAndFn {
+                    desc: TestDesc {
+                        name: DynTestName(format!("test{:02}", i)),
+                        ignore: false,
+                        should_panic: ShouldPanic::No,
+                        allow_fail: false,
+                        timeout: None,
+                    },
+                    testfn: DynTestFn(Box::new(move || {})),
+                })
+                .collect()
+        }
+        fn names(tests: Vec<TestDescAndFn>) -> Vec<String> {
+            tests.into_iter().map(|t| t.desc.name.to_string()).collect()
+        }
+
+        let sorted = names(tests());
+        let first = names(shuffle_tests(42, tests()));
+        let second = names(shuffle_tests(42, tests()));
+        let other = names(shuffle_tests(43, tests()));
+
+        assert_eq!(first, second);
+        assert_ne!(first, sorted);
+        assert_ne!(first, other);
+
+        let mut all = first.clone();
+        all.sort();
+        assert_eq!(all, sorted);
+
+        // Removing tests doesn't change the order of the others.
+        let subset = names(shuffle_tests(42, tests().into_iter().skip(10).collect()));
+        let expected = first
+            .iter()
+            .filter(|name| subset.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(subset, expected);
+    }
+
+    #[test]
+    pub fn shuffle_order_is_stable() {
+        // The order for a seed must not change between releases, so that a
+        // seed from an old log still reproduces the same run.
+        let tests = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|name| TestDescAndFn {
+                desc: TestDesc {
+                    name: StaticTestName(name),
+                    ignore: false,
+                    should_panic: ShouldPanic::No,
+                    allow_fail: false,
+                    timeout: None,
+                },
+                testfn: DynTestFn(Box::new(move || {})),
+            })
+            .collect();
+        let names = shuffle_tests(42, tests)
+            .into_iter()
+            .map(|t| t.desc.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["e", "c", "d", "a", "b"]);
+    }
+
+    #[test]
+    fn parse_shard_flags() {
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--shard-index=1".to_string(),
+            "--shard-count=3".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert_eq!(opts.shard, Some(TestShard { index: 1, count: 3 }));
+
+        let invalid = [
+            vec!["--shard-index=1"],
+            vec!["--shard-index=3", "--shard-count=3"],
+            vec!["--shard-index=0", "--shard-count=0"],
+        ];
+        for flags in &invalid {
+            let mut args = vec!["progname".to_string(), "-Zunstable-options".to_string()];
+            args.extend(flags.iter().map(|s| s.to_string()));
+            assert!(parse_opts(&args).unwrap().is_err());
+        }
+
+        let args = vec![
+            "progname".to_string(),
+            "--shard-index=0".to_string(),
+            "--shard-count=2".to_string(),
+        ];
+        assert!(parse_opts(&args).unwrap().is_err());
+    }
+
+    #[test]
+    pub fn filter_tests_by_shard() {
+        fn tests() -> Vec<TestDescAndFn> {
+            (0..50)
+                .map(|i| TestDescAndFn {
+                    desc: TestDesc {
+                        name: DynTestName(format!("test{:02}", i)),
+                        ignore: false,
+                        should_panic: ShouldPanic::No,
+                        allow_fail: false,
+                        timeout: None,
+                    },
+                    testfn: DynTestFn(Box::new(move || {})),
+                })
+                .collect()
+        }
+
+        let mut all = Vec::new();
+        for index in 0..4 {
+            let mut opts = TestOpts::new();
+            opts.shard = Some(TestShard { index, count: 4 });
+            let shard = filter_tests(&opts, tests());
+            assert!(!shard.is_empty());
+
+            // Filters apply before sharding, so a test stays in its shard.
+            opts.filter = Some("test1".to_string());
+            for test in filter_tests(&opts, tests()) {
+                assert!(shard.iter().any(|t| t.desc.name == test.desc.name));
+            }
+
+            all.extend(shard.into_iter().map(|t| t.desc.name.to_string()));
+        }
+
+        // Every test runs in exactly one shard.
+        all.sort();
+        let expected = tests()
+            .into_iter()
+            .map(|t| t.desc.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn parse_time_options() {
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--report-time".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert_eq!(
+            opts.time_options,
+            Some(TestTimeOptions {
+                error_on_excess: false,
+                threshold: TimeThreshold::default(),
+            })
+        );
+
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--ensure-time".to_string(),
+            "--time-threshold=100,200".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert_eq!(
+            opts.time_options,
+            Some(TestTimeOptions {
+                error_on_excess: true,
+                threshold: TimeThreshold::new(
+                    Duration::from_millis(100),
+                    Duration::from_millis(200)
+                ),
+            })
+        );
+
+        for threshold in &["100", "200,100", "a,b", "1,2,3"] {
+            let args = vec![
+                "progname".to_string(),
+                "-Zunstable-options".to_string(),
+                format!("--time-threshold={}", threshold),
+            ];
+            assert!(parse_opts(&args).unwrap().is_err());
+        }
+    }
+
+    #[test]
+    fn ensure_time_fails_slow_tests() {
+        let desc = TestDesc {
+            name: StaticTestName("whatever"),
+            ignore: false,
+            should_panic: ShouldPanic::No,
+            allow_fail: false,
+            timeout: None,
+        };
+        let mut opts = TestOpts::new();
+        opts.time_options = Some(TestTimeOptions {
+            error_on_excess: true,
+            threshold: TimeThreshold::new(Duration::from_millis(10), Duration::from_millis(20)),
+        });
+
+        let fast = check_exec_time(&opts, &desc, TrOk, Duration::from_millis(15));
+        assert!(fast == TrOk);
+        let slow = check_exec_time(&opts, &desc, TrOk, Duration::from_millis(25));
+        assert!(slow == TrFailedMsg(
+            "test ran for 0.025s, exceeding the critical time threshold of 0.020s".to_string()
+        ));
+
+        opts.time_options = opts.time_options.map(|time_opts| TestTimeOptions {
+            error_on_excess: false,
+            ..time_opts
+        });
+        let slow = check_exec_time(&opts, &desc, TrOk, Duration::from_millis(25));
+        assert!(slow == TrOk);
+    }
+
+    #[test]
+    fn parse_retries_flag() {
+        let args = vec![
+            "progname".to_string(),
+            "-Zunstable-options".to_string(),
+            "--retries=3".to_string(),
+        ];
+        let opts = parse_opts(&args).unwrap().unwrap();
+        assert_eq!(opts.retries, 3);
+
+        let args = vec!["progname".to_string(), "--retries=3".to_string()];
+        assert!(parse_opts(&args).unwrap().is_err());
+    }
+
+    #[test]
+    fn retries_report_flaky_tests() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        fn flaky() {
+            assert!(RUNS.fetch_add(1, Ordering::SeqCst) >= 2);
+        }
+        fn broken() {
+            panic!();
+        }
+        fn test(name: &'static str, f: fn()) -> TestDescAndFn {
+            TestDescAndFn {
+                desc: TestDesc {
+                    name: StaticTestName(name),
+                    ignore: false,
+                    should_panic: ShouldPanic::No,
+                    allow_fail: false,
+                    timeout: None,
+                },
+                testfn: StaticTestFn(f),
+            }
+        }
+
+        for &threads in &[1, 4] {
+            RUNS.store(0, Ordering::SeqCst);
+            let mut opts = TestOpts::new();
+            opts.run_tests = true;
+            opts.test_threads = Some(threads);
+            opts.retries = 3;
+
+            let mut started = 0;
+            let mut results = Vec::new();
+            let tests = vec![test("broken", broken), test("flaky", flaky)];
+            run_tests(&opts, tests, |event| {
+                match event {
+                    TeWait(_) => started += 1,
+                    TeResult(desc, result, ..) => results.push((desc.name.to_string(), result)),
+                    _ => {}
+                }
+                Ok(())
+            })
+            .unwrap();
+
+            assert_eq!(started, 2);
+            results.sort_by(|a, b| a.0.cmp(&b.0));
+            assert!(results == vec![
+                ("broken".to_string(), TrFailed),
+                ("flaky".to_string(), TrFlaky(2)),
+            ]);
+        }
+    }
+
+    #[test]
+    fn fixtures_run_once_around_their_tests() {
+        use std::cell::RefCell;
+
+        // With a single test thread, tests and fixtures all run on this thread.
+        thread_local!(static LOG: RefCell<Vec<&'static str>> = RefCell::new(Vec::new()));
+        fn log(event: &'static str) {
+            LOG.with(|log| log.borrow_mut().push(event));
+        }
+        fn desc(name: &'static str, ignore: bool) -> TestDesc {
+            TestDesc {
+                name: StaticTestName(name),
+                ignore,
+                should_panic: ShouldPanic::No,
+                allow_fail: false,
+                timeout: None,
+            }
+        }
+
+        let tests = vec![
+            TestDescAndFn {
+                desc: desc("a::t1", false),
+                testfn: StaticTestFn(|| log("a::t1")),
+            },
+            TestDescAndFn {
+                desc: desc("a::t2", false),
+                testfn: StaticTestFn(|| log("a::t2")),
+            },
+            TestDescAndFn {
+                desc: desc("a::b::t3", true),
+                testfn: StaticTestFn(|| log("a::b::t3")),
+            },
+            TestDescAndFn {
+                desc: desc("c::t4", false),
+                testfn: StaticTestFn(|| log("c::t4")),
+            },
+            TestDescAndFn {
+                desc: desc("setup", false),
+                testfn: StaticSetupFn(|| log("setup")),
+            },
+            TestDescAndFn {
+                desc: desc("a::setup", false),
+                testfn: StaticSetupFn(|| log("a::setup")),
+            },
+            TestDescAndFn {
+                desc: desc("a::teardown", false),
+                testfn: StaticTeardownFn(|| log("a::teardown")),
+            },
+            TestDescAndFn {
+                desc: desc("a::b::setup", false),
+                testfn: StaticSetupFn(|| log("a::b::setup")),
+            },
+            TestDescAndFn {
+                desc: desc("c::setup", false),
+                testfn: StaticSetupFn(|| panic!()),
+            },
+            TestDescAndFn {
+                desc: desc("c::teardown", false),
+                testfn: StaticTeardownFn(|| log("c::teardown")),
+            },
+            TestDescAndFn {
+                desc: desc("d::t5", false),
+                testfn: StaticTestFn(|| log("d::t5")),
+            },
+            TestDescAndFn {
+                desc: desc("d::open", false),
+                testfn: StaticSetupFn(|| log("d::open")),
+            },
+            TestDescAndFn {
+                desc: desc("d::lock", false),
+                testfn: StaticSetupFn(|| panic!()),
+            },
+            TestDescAndFn {
+                desc: desc("d::close", false),
+                testfn: StaticTeardownFn(|| log("d::close")),
+            },
+        ];
+
+        let mut opts = TestOpts::new();
+        opts.run_tests = true;
+        opts.test_threads = Some(1);
+
+        let mut results = Vec::new();
+        let mut failed_fixtures = Vec::new();
+        run_tests(&opts, tests, |event| {
+            match event {
+                TeResult(desc, result, ..) => results.push((desc.name.to_string(), result)),
+                TeFixtureFailed(desc, ..) => failed_fixtures.push(desc.name.to_string()),
+                _ => {}
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        LOG.with(|log| {
+            assert_eq!(
+                *log.borrow(),
+                [
+                    "setup",
+                    "a::setup",
+                    "a::t1",
+                    "a::t2",
+                    "a::teardown",
+                    "d::open",
+                    "d::close",
+                ]
+            );
+        });
+        assert_eq!(failed_fixtures, ["c::setup", "d::lock"]);
+        assert!(results == vec![
+            ("a::b::t3".to_string(), TrIgnored),
+            ("a::t1".to_string(), TrOk),
+            ("a::t2".to_string(), TrOk),
+            (
+                "c::t4".to_string(),
+                TrFailedMsg("not run because setup `c::setup` failed".to_string())
+            ),
+            (
+                "d::t5".to_string(),
+                TrFailedMsg("not run because setup `d::lock` failed".to_string())
+            ),
+        ]);
+    }
+
     #[test]
     pub fn test_metricmap_compare() {
         let mut m1 = MetricMap::new();
@@ -2220,6 +3907,7 @@ mod tests {
             ignore: false,
             should_panic: ShouldPanic::No,
             allow_fail: false,
+            timeout: None,
         };
 
         crate::bench::benchmark(desc, tx, true, f);
@@ -2239,6 +3927,7 @@ mod tests {
             ignore: false,
             should_panic: ShouldPanic::No,
             allow_fail: false,
+            timeout: None,
         };
 
         crate::bench::benchmark(desc, tx, true, f);
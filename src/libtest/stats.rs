@@ -315,10 +315,32 @@ pub fn winsorize(samples: &mut [f64], pct: f64) {
     }
 }
 
+/// Welch's t statistic for the difference between the means of two sample sets of `n1` and
+/// `n2` samples, summarized by `s1` and `s2`. It is positive when the mean of `s2` is larger, and
+/// its magnitude is large when the difference is unlikely to be explained by the variance of the
+/// samples alone; for large sample sets, a magnitude above 2 means the difference is significant
+/// at roughly the 95% level.
+///
+/// See: <https://en.wikipedia.org/wiki/Welch%27s_t-test>
+pub fn welch_t(s1: &Summary, n1: usize, s2: &Summary, n2: usize) -> f64 {
+    let diff = s2.mean - s1.mean;
+    let std_err = (s1.var / n1 as f64 + s2.var / n2 as f64).sqrt();
+    if std_err == 0.0 {
+        if diff == 0.0 {
+            0.0
+        } else {
+            diff.signum() * ::std::f64::INFINITY
+        }
+    } else {
+        diff / std_err
+    }
+}
+
 // Test vectors generated from R, using the script src/etc/stat-test-vectors.r.
 
 #[cfg(test)]
 mod tests {
+    use crate::stats::welch_t;
     use crate::stats::Stats;
     use crate::stats::Summary;
     use std::f64;
@@ -885,6 +907,20 @@ mod tests {
         check(val, summ);
     }
 
+    #[test]
+    fn test_welch_t() {
+        let s1 = Summary::new(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let s2 = Summary::new(&[2.0, 3.0, 4.0, 5.0, 6.0]);
+        // The means differ by 1 and both variances are 2.5.
+        assert_approx_eq!(welch_t(&s1, 5, &s2, 5), 1.0);
+        assert_approx_eq!(welch_t(&s2, 5, &s1, 5), -1.0);
+        assert_eq!(welch_t(&s1, 5, &s1, 5), 0.0);
+
+        let c1 = Summary::new(&[1.0, 1.0]);
+        let c2 = Summary::new(&[2.0, 2.0]);
+        assert_eq!(welch_t(&c1, 2, &c2, 2), ::std::f64::INFINITY);
+    }
+
     #[test]
     fn test_sum_f64s() {
         assert_eq!([0.5f64, 3.2321f64, 1.5678f64].sum(), 5.2999);
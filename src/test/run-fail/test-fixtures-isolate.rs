@@ -0,0 +1,24 @@
+// error-pattern:test `sees_setup_state` has fixtures, which cannot be combined with --isolate
+// compile-flags: --test
+// run-flags: -Zunstable-options --isolate
+// ignore-emscripten no processes
+// ignore-wasm32-bare no processes
+
+// Fixtures only run in the main process, so a test run in a subprocess could
+// not see the state they set up.
+
+#![feature(test_fixtures)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READY: AtomicBool = AtomicBool::new(false);
+
+#[test_setup]
+fn set_up() {
+    READY.store(true, Ordering::SeqCst);
+}
+
+#[test]
+fn sees_setup_state() {
+    assert!(READY.load(Ordering::SeqCst));
+}
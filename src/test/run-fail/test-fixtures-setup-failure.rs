@@ -0,0 +1,19 @@
+// check-stdout
+// error-pattern:not run because setup `db::connect` failed
+// compile-flags: --test
+// ignore-emscripten
+
+#![feature(test_fixtures)]
+
+mod db {
+    #[test_setup]
+    fn connect() {
+        panic!("no database");
+    }
+
+    #[test]
+    fn query() {}
+}
+
+#[test]
+fn unaffected() {}
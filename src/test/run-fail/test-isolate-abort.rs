@@ -0,0 +1,14 @@
+// check-stdout
+// error-pattern:test result: FAILED. 1 passed; 1 failed
+// compile-flags: --test
+// run-flags: -Zunstable-options --isolate
+// ignore-emscripten no processes
+// ignore-wasm32-bare no processes
+
+#[test]
+fn aborts() {
+    std::process::abort();
+}
+
+#[test]
+fn passes() {}
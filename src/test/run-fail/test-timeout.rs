@@ -0,0 +1,22 @@
+// check-stdout
+// error-pattern:test hangs ... FAILED (time limit exceeded)
+// compile-flags: --test
+// ignore-emscripten no processes
+// ignore-wasm32-bare no processes
+
+#![feature(test_timeout)]
+
+use std::thread;
+use std::time::Duration;
+
+#[test]
+#[test_timeout = "1"]
+fn hangs() {
+    loop {
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+#[test_timeout = "60"]
+fn finishes() {}
@@ -0,0 +1,6 @@
+#![crate_type = "lib"]
+
+pub fn foo() -> u32 {
+    let x = (1);
+    x + "2"
+}
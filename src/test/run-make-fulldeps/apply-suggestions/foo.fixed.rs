@@ -0,0 +1,7 @@
+#![crate_type = "lib"]
+
+pub fn foo() {
+    let _x = 1;
+    let y = 2;
+    drop(y);
+}
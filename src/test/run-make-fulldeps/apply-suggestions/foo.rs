@@ -0,0 +1,7 @@
+#![crate_type = "lib"]
+
+pub fn foo() {
+    let x = 1;
+    let mut y = 2;
+    drop(y);
+}
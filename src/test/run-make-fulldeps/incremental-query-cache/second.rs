@@ -0,0 +1,13 @@
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+pub fn make(x: u32, y: u32) -> Point {
+    Point { x, y }
+}
+
+fn main() {
+    let p = make(2, 2);
+    println!("{}", p.x * p.y);
+}
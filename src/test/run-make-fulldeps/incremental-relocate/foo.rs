@@ -0,0 +1,7 @@
+pub fn add(a: u32, b: u32) -> u32 {
+    a.checked_add(b).expect("overflow")
+}
+
+pub fn generic<T: Clone>(x: &T) -> (T, T) {
+    (x.clone(), x.clone())
+}
@@ -0,0 +1,5 @@
+/// New documentation.
+pub fn changed() {}
+
+/// Unchanged documentation.
+pub fn unchanged() {}
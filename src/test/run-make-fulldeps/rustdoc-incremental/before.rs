@@ -0,0 +1,5 @@
+/// Old documentation.
+pub fn changed() {}
+
+/// Unchanged documentation.
+pub fn unchanged() {}
@@ -0,0 +1,4 @@
+#![crate_type = "lib"]
+
+/// Links to [Nope].
+pub fn bar() {}
@@ -0,0 +1,5 @@
+#![crate_type = "lib"]
+
+pub fn foo() {
+    let x = 1;
+}
@@ -0,0 +1,45 @@
+// compile-flags: --test
+// ignore-wasm32-bare compiled with panic=abort by default
+
+#![feature(test_fixtures)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static SETUPS: AtomicUsize = AtomicUsize::new(0);
+
+#[test_setup]
+fn set_up_binary() {
+    SETUPS.fetch_add(1, Ordering::SeqCst);
+}
+
+mod server {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+
+    #[test_setup]
+    fn start() {
+        assert!(!RUNNING.swap(true, Ordering::SeqCst));
+    }
+
+    #[test_teardown]
+    fn stop() {
+        assert!(RUNNING.swap(false, Ordering::SeqCst));
+    }
+
+    #[test]
+    fn first() {
+        assert!(RUNNING.load(Ordering::SeqCst));
+        assert_eq!(super::SETUPS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn second() {
+        assert!(RUNNING.load(Ordering::SeqCst));
+    }
+}
+
+#[test]
+fn outside() {
+    assert_eq!(SETUPS.load(Ordering::SeqCst), 1);
+}
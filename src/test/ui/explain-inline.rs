@@ -0,0 +1,5 @@
+// compile-flags: --explain-inline -Z unstable-options
+
+fn main() {
+    let x: u32 = "x"; //~ ERROR mismatched types
+}
@@ -0,0 +1,9 @@
+// check that #[test_setup] and #[test_teardown] are feature-gated
+
+#[test_setup] //~ ERROR `#[test_setup]` and `#[test_teardown]` are an unstable feature
+fn setup() {}
+
+#[test_teardown] //~ ERROR `#[test_setup]` and `#[test_teardown]` are an unstable feature
+fn teardown() {}
+
+fn main() {}
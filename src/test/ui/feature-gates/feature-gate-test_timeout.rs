@@ -0,0 +1,6 @@
+// check that #[test_timeout] is feature-gated
+// compile-flags: --test
+
+#[test]
+#[test_timeout = "10"] //~ ERROR the test_timeout attribute is currently unstable
+fn slow() {}
@@ -0,0 +1,9 @@
+// compile-flags: -Z group-similar-diagnostics
+
+// Type mismatches with the same label are not grouped if their notes differ.
+
+fn main() {
+    let x = 0u8;
+    let a: u32 = "x"; //~ ERROR mismatched types
+    let b: u32 = &x; //~ ERROR mismatched types
+}
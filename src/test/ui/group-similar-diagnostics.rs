@@ -0,0 +1,8 @@
+// compile-flags: -Z group-similar-diagnostics
+
+fn main() {
+    let a: u32 = "x"; //~ ERROR mismatched types
+    let b: u32 = "x";
+    let c: u32 = "x";
+    let d: u32 = 'x'; //~ ERROR mismatched types
+}
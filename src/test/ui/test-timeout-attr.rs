@@ -0,0 +1,14 @@
+// compile-flags: --test
+
+#![feature(test_timeout)]
+#![allow(dead_code)]
+
+#[test]
+#[test_timeout = "10"]
+fn slow() {}
+
+#[test_timeout = "10"] //~ ERROR can only be applied to `#[test]` functions
+fn helper() {}
+
+#[test_timeout = "10"] //~ ERROR can only be applied to `#[test]` functions
+struct Fixture;
@@ -0,0 +1,8 @@
+// compile-flags: -Z treat-diagnostic=expand::empty_derive:deny
+
+// Diagnostics without a code can be given a slug where they are created.
+
+#[derive()] //~ ERROR empty trait list in `derive`
+struct Bar;
+
+fn main() {}
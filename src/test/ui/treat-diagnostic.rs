@@ -0,0 +1,10 @@
+// compile-flags: -Z treat-diagnostic=lint::unused_variables:deny
+// compile-flags: -Z treat-diagnostic=lint::dead_code:allow
+
+#![warn(unused_variables, dead_code)]
+
+fn unused() {}
+
+fn main() {
+    let x = 1; //~ ERROR unused variable: `x`
+}
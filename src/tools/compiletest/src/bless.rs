@@ -0,0 +1,55 @@
+//! Bookkeeping for `--bless`, which overwrites the expected output of tests
+//! with their actual output.
+//!
+//! Test output is captured by libtest and only shown for failing tests, so the
+//! files that were rewritten are collected here and summarized once the whole
+//! run has finished.
+
+use diff;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+struct Blessed {
+    path: PathBuf,
+    added: usize,
+    removed: usize,
+}
+
+lazy_static! {
+    static ref BLESSED: Mutex<Vec<Blessed>> = Mutex::new(Vec::new());
+}
+
+/// Records that the expected output in `path` was changed from `expected` to
+/// `actual`.
+pub fn record(path: &Path, expected: &str, actual: &str) {
+    let mut added = 0;
+    let mut removed = 0;
+    for line in diff::lines(expected, actual) {
+        match line {
+            diff::Result::Left(_) => removed += 1,
+            diff::Result::Right(_) => added += 1,
+            diff::Result::Both(..) => {}
+        }
+    }
+
+    BLESSED.lock().unwrap().push(Blessed {
+        path: path.to_path_buf(),
+        added,
+        removed,
+    });
+}
+
+/// Prints the files that were blessed during this run, if any.
+pub fn print_summary() {
+    let mut blessed = BLESSED.lock().unwrap();
+    if blessed.is_empty() {
+        return;
+    }
+
+    blessed.sort_by(|a, b| a.path.cmp(&b.path));
+    blessed.dedup_by(|a, b| a.path == b.path);
+    println!("\nblessed {} file(s):", blessed.len());
+    for file in blessed.iter() {
+        println!("    {} (+{} -{})", file.path.display(), file.added, file.removed);
+    }
+}
@@ -116,7 +116,8 @@ impl CompareMode {
 
 #[derive(Clone)]
 pub struct Config {
-    /// `true` to to overwrite stderr/stdout files instead of complaining about changes in output.
+    /// `true` to overwrite the expected output of tests instead of complaining about changes
+    /// in output.
     pub bless: bool,
 
     /// The library paths required for running the compiler.
@@ -175,6 +176,9 @@ pub struct Config {
     /// Write out a parseable log of tests that were run
     pub logfile: Option<PathBuf>,
 
+    /// Write out a JSON report of the outcome of each test, see `report.rs`
+    pub report: Option<PathBuf>,
+
     /// A command line to prefix program execution with,
     /// for running under valgrind
     pub runtool: Option<String>,
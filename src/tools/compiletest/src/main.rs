@@ -32,19 +32,28 @@ use getopts;
 
 use self::header::{EarlyProps, Ignore};
 
+mod bless;
 pub mod common;
 pub mod errors;
 pub mod header;
 mod json;
+mod minimize;
 mod raise_fd_limit;
 mod read2;
+mod report;
 pub mod runtest;
 pub mod util;
 
 fn main() {
     env_logger::init();
 
-    let config = parse_config(env::args().collect());
+    let args = env::args().collect::<Vec<_>>();
+    if args.get(1).map(|s| &s[..]) == Some("minimize") {
+        minimize::main(&args[2..]);
+        return;
+    }
+
+    let config = parse_config(args);
 
     if config.valgrind_path.is_none() && config.force_valgrind {
         panic!("Can't find Valgrind to run Valgrind tests");
@@ -159,7 +168,8 @@ pub fn parse_config(args: Vec<String>) -> Config {
         .optflag(
             "",
             "bless",
-            "overwrite stderr/stdout files instead of complaining about a mismatch",
+            "overwrite the expected output (ui stderr/stdout, pretty pp-exact files \
+             and mir-opt START/END sections) instead of complaining about a mismatch",
         )
         .optflag(
             "",
@@ -168,6 +178,12 @@ pub fn parse_config(args: Vec<String>) -> Config {
         )
         .optopt("", "color", "coloring: auto, always, never", "WHEN")
         .optopt("", "logfile", "file to log test execution to", "FILE")
+        .optopt(
+            "",
+            "report",
+            "write a JSON report of the outcome of each test to FILE",
+            "FILE",
+        )
         .optopt("", "target", "the target to build for", "TARGET")
         .optopt("", "host", "the host to build for", "HOST")
         .optopt(
@@ -312,6 +328,7 @@ pub fn parse_config(args: Vec<String>) -> Config {
         filter: matches.free.first().cloned(),
         filter_exact: matches.opt_present("exact"),
         logfile: matches.opt_str("logfile").map(|s| PathBuf::from(&s)),
+        report: matches.opt_str("report").map(|s| PathBuf::from(&s)),
         runtool: matches.opt_str("runtool"),
         host_rustcflags: matches.opt_str("host-rustcflags"),
         target_rustcflags: matches.opt_str("target-rustcflags"),
@@ -491,6 +508,12 @@ pub fn run_tests(config: &Config) {
     env::set_var("TARGET", &config.target);
 
     let res = test::run_tests_console(&opts, tests);
+    if config.bless {
+        bless::print_summary();
+    }
+    if let Err(e) = report::write(config) {
+        println!("failed to write test report: {}", e);
+    }
     match res {
         Ok(true) => {}
         Ok(false) => panic!("Some tests failed"),
@@ -653,14 +676,25 @@ pub fn make_test(config: &Config, testpaths: &TestPaths) -> Vec<test::TestDescAn
                     && config.target.contains("emscripten"))
                 || (config.mode == DebugInfoGdb && !early_props.ignore.can_run_gdb())
                 || (config.mode == DebugInfoLldb && !early_props.ignore.can_run_lldb());
+            let name = make_test_name(config, testpaths, revision);
+            if ignore {
+                report::record_ignored(config, testpaths, revision.map(|s| s.as_str()), &name);
+            }
+            let should_fail = should_panic == test::ShouldPanic::Yes;
             test::TestDescAndFn {
                 desc: test::TestDesc {
-                    name: make_test_name(config, testpaths, revision),
+                    name,
                     ignore,
                     should_panic,
                     allow_fail: false,
                 },
-                testfn: make_test_closure(config, early_props.ignore, testpaths, revision),
+                testfn: make_test_closure(
+                    config,
+                    early_props.ignore,
+                    testpaths,
+                    revision,
+                    should_fail,
+                ),
             }
         })
         .collect()
@@ -789,6 +823,7 @@ fn make_test_closure(
     ignore: Ignore,
     testpaths: &TestPaths,
     revision: Option<&String>,
+    should_fail: bool,
 ) -> test::TestFn {
     let mut config = config.clone();
     if config.mode == DebugInfoBoth {
@@ -804,7 +839,10 @@ fn make_test_closure(
     let testpaths = testpaths.clone();
     let revision = revision.cloned();
     test::DynTestFn(Box::new(move || {
-        runtest::run(config, &testpaths, revision.as_ref().map(|s| s.as_str()))
+        let revision = revision.as_ref().map(|s| s.as_str());
+        report::run_test(&config, &testpaths, revision, should_fail, || {
+            runtest::run(config.clone(), &testpaths, revision)
+        })
     }))
 }
 
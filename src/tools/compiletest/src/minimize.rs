@@ -0,0 +1,384 @@
+//! Reduction of a failing test to a minimal reproducer.
+//!
+//! `compiletest minimize` takes a test file and the signature of its failure
+//! (an ICE message, an error code or a line of the stderr diff), and keeps
+//! deleting parts of the source for as long as rustc still fails with that
+//! signature. Candidates for deletion are taken from the pre-expansion AST,
+//! which rustc dumps with `-Z ast-json-noexpand`: whole items first, then
+//! statements, and finally single tokens. Every candidate must still parse,
+//! so the reduced test stays a well-formed Rust file.
+//!
+//! The `compile-flags` and `edition` headers of the test are honored.
+//! Auxiliary crates are not built, and the test is only compiled, not run.
+
+use crate::header;
+use getopts::Options;
+use serde_json::{self, Value};
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process::{self, Command, Output};
+
+/// How the test is expected to fail.
+enum Signature {
+    /// rustc panics, and stderr contains this text.
+    Ice(String),
+    /// rustc emits an error or warning with this code.
+    ErrorCode(String),
+    /// stderr contains this text.
+    Stderr(String),
+}
+
+impl Signature {
+    fn matches(&self, output: &Output) -> bool {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match *self {
+            Signature::Ice(ref msg) => output.status.code() == Some(101) && stderr.contains(msg),
+            Signature::ErrorCode(ref code) => stderr.contains(&format!("[{}]", code)),
+            Signature::Stderr(ref text) => stderr.contains(text),
+        }
+    }
+}
+
+pub fn main(args: &[String]) {
+    let mut opts = Options::new();
+    opts.reqopt("", "rustc-path", "path to rustc to use for compiling", "PATH")
+        .optopt("", "target", "the target to build for", "TARGET")
+        .optopt("", "revision", "the revision of the test to minimize", "REV")
+        .optopt("", "ice", "the test makes rustc panic with a message containing TEXT", "TEXT")
+        .optopt("", "error-code", "the test fails with the error CODE", "CODE")
+        .optopt("", "stderr", "the stderr of rustc contains TEXT", "TEXT")
+        .optopt("o", "output", "where to write the minimized test", "FILE")
+        .optflag("v", "verbose", "print every candidate that is tried")
+        .optflag("h", "help", "show this message");
+
+    let usage = || {
+        let message = "Usage: compiletest minimize [OPTIONS] TEST\n\n\
+                       Exactly one of `--ice`, `--error-code` and `--stderr` must be given.";
+        println!("{}", opts.usage(message));
+    };
+
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(f) => {
+            usage();
+            panic!("{}", f)
+        }
+    };
+    if matches.opt_present("h") {
+        usage();
+        return;
+    }
+
+    let signature = match (
+        matches.opt_str("ice"),
+        matches.opt_str("error-code"),
+        matches.opt_str("stderr"),
+    ) {
+        (Some(msg), None, None) => Signature::Ice(msg),
+        (None, Some(code), None) => Signature::ErrorCode(code),
+        (None, None, Some(text)) => Signature::Stderr(text),
+        _ => {
+            usage();
+            panic!("expected exactly one of `--ice`, `--error-code` and `--stderr`")
+        }
+    };
+    let test = match &matches.free[..] {
+        [test] => PathBuf::from(test),
+        _ => {
+            usage();
+            panic!("expected a single test file")
+        }
+    };
+
+    let revision = matches.opt_str("revision");
+    let mut flags = Vec::new();
+    header::iter_header(&test, revision.as_ref().map(|s| &s[..]), &mut |ln| {
+        if ln.starts_with("compile-flags:") {
+            flags.extend(ln["compile-flags:".len()..].split_whitespace().map(str::to_owned));
+        } else if ln.starts_with("edition:") {
+            flags.push(format!("--edition={}", ln["edition:".len()..].trim()));
+        }
+    });
+    if let Some(ref revision) = revision {
+        flags.push("--cfg".to_owned());
+        flags.push(revision.to_lowercase().replace("-", "_"));
+    }
+    if let Some(target) = matches.opt_str("target") {
+        flags.push(format!("--target={}", target));
+    }
+
+    let src = fs::read_to_string(&test)
+        .unwrap_or_else(|e| panic!("failed to read `{}`: {}", test.display(), e));
+    // rustc ignores a byte order mark, which would shift all the spans.
+    let src = src.trim_start_matches('\u{feff}').to_owned();
+
+    let work_dir = env::temp_dir().join(format!("compiletest-minimize-{}", process::id()));
+    fs::create_dir_all(&work_dir).unwrap();
+    let mut minimizer = Minimizer {
+        rustc: PathBuf::from(matches.opt_str("rustc-path").unwrap()),
+        flags,
+        signature,
+        file: work_dir.join(test.file_name().unwrap()),
+        work_dir,
+        verbose: matches.opt_present("v"),
+        runs: 0,
+    };
+
+    if !minimizer.reproduces(&src) {
+        panic!("`{}` does not fail with the given signature", test.display());
+    }
+    let minimized = minimizer.minimize(src.clone());
+    let _ = fs::remove_dir_all(&minimizer.work_dir);
+
+    let output = matches.opt_str("o").map(PathBuf::from).unwrap_or_else(|| {
+        let stem = test.file_stem().unwrap().to_string_lossy();
+        PathBuf::from(format!("{}.min.rs", stem))
+    });
+    fs::write(&output, &minimized)
+        .unwrap_or_else(|e| panic!("failed to write `{}`: {}", output.display(), e));
+    println!(
+        "minimized `{}` from {} to {} bytes in {} runs of rustc, written to `{}`",
+        test.display(),
+        src.len(),
+        minimized.len(),
+        minimizer.runs,
+        output.display()
+    );
+}
+
+struct Minimizer {
+    rustc: PathBuf,
+    flags: Vec<String>,
+    signature: Signature,
+    /// The file candidates are written to before running rustc on them.
+    file: PathBuf,
+    work_dir: PathBuf,
+    verbose: bool,
+    runs: usize,
+}
+
+impl Minimizer {
+    fn rustc(&mut self, src: &str, extra_args: &[&str]) -> Output {
+        fs::write(&self.file, src).unwrap();
+        self.runs += 1;
+        Command::new(&self.rustc)
+            .arg(&self.file)
+            .arg("--out-dir")
+            .arg(&self.work_dir)
+            .args(&self.flags)
+            .args(extra_args)
+            .output()
+            .unwrap_or_else(|e| panic!("failed to exec `{}`: {}", self.rustc.display(), e))
+    }
+
+    /// Returns the pre-expansion AST of `src`, or `None` if it does not parse.
+    fn parse(&mut self, src: &str) -> Option<Value> {
+        let output = self.rustc(src, &["-Z", "ast-json-noexpand"]);
+        if !output.status.success() {
+            return None;
+        }
+        serde_json::from_slice(&output.stdout).ok()
+    }
+
+    fn reproduces(&mut self, src: &str) -> bool {
+        let output = self.rustc(src, &[]);
+        self.signature.matches(&output)
+    }
+
+    /// Deletes `range` from `src` if the result still parses and fails the
+    /// same way.
+    fn try_delete(&mut self, src: &str, range: Range<usize>) -> Option<String> {
+        let mut candidate = src.to_owned();
+        candidate.replace_range(range.clone(), "");
+        if self.verbose {
+            println!("trying to delete {:?}: {:?}", range, &src[range.clone()]);
+        }
+        if self.parse(&candidate).is_some() && self.reproduces(&candidate) {
+            Some(candidate)
+        } else {
+            None
+        }
+    }
+
+    fn minimize(&mut self, mut src: String) -> String {
+        loop {
+            let before = src.len();
+            src = self.delete_ast_nodes(src);
+            src = self.delete_tokens(src);
+            if src.len() == before {
+                return src;
+            }
+        }
+    }
+
+    /// Deletes items and statements, largest first, until none can be
+    /// deleted anymore.
+    fn delete_ast_nodes(&mut self, mut src: String) -> String {
+        'reduce: loop {
+            let ast = match self.parse(&src) {
+                Some(ast) => ast,
+                None => return src,
+            };
+            for range in ast_candidates(&ast, &src) {
+                if let Some(reduced) = self.try_delete(&src, range) {
+                    src = reduced;
+                    continue 'reduce;
+                }
+            }
+            return src;
+        }
+    }
+
+    /// Deletes single tokens, starting from the end of the file so that the
+    /// positions of the remaining tokens stay valid.
+    fn delete_tokens(&mut self, mut src: String) -> String {
+        for range in tokens(&src).into_iter().rev() {
+            if let Some(reduced) = self.try_delete(&src, range) {
+                src = reduced;
+            }
+        }
+        src
+    }
+}
+
+/// Returns the byte ranges of the items and statements in `ast`, items first
+/// and larger nodes before smaller ones.
+fn ast_candidates(ast: &Value, src: &str) -> Vec<Range<usize>> {
+    let mut items = Vec::new();
+    let mut stmts = Vec::new();
+    collect_nodes(ast, &mut items, &mut stmts);
+
+    // Spans outside of `src` belong to out-of-line modules.
+    let in_src = |range: &Range<usize>| {
+        range.start < range.end
+            && range.end <= src.len()
+            && src.is_char_boundary(range.start)
+            && src.is_char_boundary(range.end)
+    };
+    let mut candidates = Vec::new();
+    for mut nodes in vec![items, stmts] {
+        nodes.retain(|range| in_src(range));
+        nodes.sort_by_key(|range| (range.start, range.end));
+        nodes.dedup();
+        nodes.sort_by(|a, b| (b.end - b.start).cmp(&(a.end - a.start)));
+        candidates.extend(nodes);
+    }
+    candidates
+}
+
+fn collect_nodes(value: &Value, items: &mut Vec<Range<usize>>, stmts: &mut Vec<Range<usize>>) {
+    match *value {
+        Value::Object(ref fields) => {
+            // `Item`, `TraitItem`, `ImplItem` and `ForeignItem` all have these.
+            let is_item = ["ident", "attrs", "node", "span"]
+                .iter()
+                .all(|field| fields.contains_key(*field));
+            if is_item {
+                if let Some(range) = node_range(value) {
+                    items.push(range);
+                }
+            }
+            if let Some(&Value::Array(ref nodes)) = fields.get("stmts") {
+                stmts.extend(nodes.iter().filter_map(node_range));
+            }
+            for field in fields.values() {
+                collect_nodes(field, items, stmts);
+            }
+        }
+        Value::Array(ref elements) => {
+            for element in elements {
+                collect_nodes(element, items, stmts);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the byte range of an AST node, including its outer attributes.
+fn node_range(node: &Value) -> Option<Range<usize>> {
+    let span = |value: &Value| -> Option<Range<usize>> {
+        let lo = value.get("span")?.get("lo")?.as_u64()? as usize;
+        let hi = value.get("span")?.get("hi")?.as_u64()? as usize;
+        Some(lo..hi)
+    };
+    let mut range = span(node)?;
+    if let Some(&Value::Array(ref attrs)) = node.get("attrs") {
+        for attr in attrs.iter().filter_map(span) {
+            range.start = range.start.min(attr.start);
+        }
+    }
+    Some(range)
+}
+
+/// Splits `src` into rough tokens: comments, string literals, identifiers
+/// and numbers, and single punctuation characters.
+fn tokens(src: &str) -> Vec<Range<usize>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        if c.is_whitespace() {
+            continue;
+        } else if c == '/' && src[end..].starts_with('/') {
+            end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+        } else if c == '"' {
+            let mut escaped = false;
+            while let Some((i, c)) = chars.next() {
+                end = i + c.len_utf8();
+                if c == '"' && !escaped {
+                    break;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+        } else if c.is_alphanumeric() || c == '_' {
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+        }
+        while chars.peek().map_or(false, |&(i, _)| i < end) {
+            chars.next();
+        }
+        tokens.push(start..end);
+    }
+    tokens
+}
+
+#[test]
+fn test_tokens() {
+    let src = "fn main() { // hi\n    let s = \"a \\\" b\"; }";
+    let tokens = tokens(src).into_iter().map(|r| &src[r]).collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        ["fn", "main", "(", ")", "{", "// hi", "let", "s", "=", "\"a \\\" b\"", ";", "}"]
+    );
+}
+
+#[test]
+fn test_ast_candidates() {
+    // A trimmed down `-Z ast-json-noexpand` dump of:
+    //
+    //     #[inline] fn f() {}
+    //     fn main() { f(); }
+    let src = "#[inline] fn f() {}\nfn main() { f(); }";
+    let ast: Value = serde_json::from_str(
+        r#"{"module": {"items": [
+            {"ident": "f", "attrs": [{"span": {"lo": 0, "hi": 9}}],
+             "node": {}, "span": {"lo": 10, "hi": 19}},
+            {"ident": "main", "attrs": [],
+             "node": {"fields": [{"stmts": [{"span": {"lo": 32, "hi": 36}}]}]},
+             "span": {"lo": 20, "hi": 38}}
+        ]}}"#,
+    )
+    .unwrap();
+    let candidates = ast_candidates(&ast, src)
+        .into_iter()
+        .map(|r| &src[r])
+        .collect::<Vec<_>>();
+    assert_eq!(candidates, ["#[inline] fn f() {}", "fn main() { f(); }", "f();"]);
+}
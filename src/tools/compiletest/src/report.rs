@@ -0,0 +1,210 @@
+//! A machine-readable report of a test run, written with `--report FILE`.
+//!
+//! The report is a JSON object listing every test that was run, e.g.:
+//!
+//! ```json
+//! {
+//!   "mode": "ui",
+//!   "tests": [
+//!     {
+//!       "file": "ui/issues/issue-12345.rs",
+//!       "revision": null,
+//!       "mode": "ui",
+//!       "compare_mode": null,
+//!       "outcome": "failed",
+//!       "duration": 0.412,
+//!       "commands": ["\"rustc\" \"ui/issues/issue-12345.rs\" ..."],
+//!       "diffs": ["build/.../issue-12345.stderr.diff"]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `outcome` is `passed`, `failed` or `ignored`; a `// should-fail` test passes
+//! when it fails. `commands` holds the exact command lines compiletest ran for
+//! the test, in order, and `diffs` the files holding the difference between the
+//! actual and the expected output of a test whose output did not match.
+//!
+//! With `--compare-mode`, the name of the compare mode is added to the file
+//! name of the report (e.g. `report.nll.json`), so that the report of the
+//! normal run is not overwritten.
+
+use crate::common::{Config, TestPaths};
+use serde_json;
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+#[derive(Serialize)]
+struct TestReport {
+    file: PathBuf,
+    revision: Option<String>,
+    mode: String,
+    compare_mode: Option<String>,
+    outcome: Outcome,
+    /// In seconds.
+    duration: f64,
+    commands: Vec<String>,
+    diffs: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    mode: String,
+    tests: &'a [TestReport],
+}
+
+/// What was recorded for the test running on the current thread.
+#[derive(Default)]
+struct Current {
+    commands: Vec<String>,
+    diffs: Vec<PathBuf>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Current>> = RefCell::new(None);
+}
+
+lazy_static! {
+    static ref TESTS: Mutex<Vec<TestReport>> = Mutex::new(Vec::new());
+}
+
+/// Runs a test, recording its outcome if a report was requested. A test that
+/// `should_fail` passes if `f` panics.
+pub fn run_test<F: FnOnce()>(
+    config: &Config,
+    testpaths: &TestPaths,
+    revision: Option<&str>,
+    should_fail: bool,
+    f: F,
+) {
+    if config.report.is_none() {
+        return f();
+    }
+
+    CURRENT.with(|current| *current.borrow_mut() = Some(Current::default()));
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    let elapsed = start.elapsed();
+    let current = CURRENT.with(|current| current.borrow_mut().take().unwrap());
+
+    let outcome = if result.is_ok() != should_fail {
+        Outcome::Passed
+    } else {
+        Outcome::Failed
+    };
+    TESTS.lock().unwrap().push(TestReport {
+        file: relative_file(config, testpaths),
+        revision: revision.map(str::to_owned),
+        mode: config.mode.to_string(),
+        compare_mode: config.compare_mode.as_ref().map(|m| m.to_str().to_owned()),
+        outcome,
+        duration: elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9,
+        commands: current.commands,
+        diffs: current.diffs,
+    });
+
+    if let Err(payload) = result {
+        panic::resume_unwind(payload);
+    }
+}
+
+/// Records that the test `name` is ignored, unless it is filtered out or
+/// ignored tests are run anyway.
+pub fn record_ignored(
+    config: &Config,
+    testpaths: &TestPaths,
+    revision: Option<&str>,
+    name: &test::TestName,
+) {
+    if config.report.is_none() || config.run_ignored {
+        return;
+    }
+    // The same filtering libtest does.
+    if let Some(ref filter) = config.filter {
+        let name = name.to_string();
+        let matches = if config.filter_exact {
+            &name[..] == &filter[..]
+        } else {
+            name.contains(&filter[..])
+        };
+        if !matches {
+            return;
+        }
+    }
+
+    TESTS.lock().unwrap().push(TestReport {
+        file: relative_file(config, testpaths),
+        revision: revision.map(str::to_owned),
+        mode: config.mode.to_string(),
+        compare_mode: config.compare_mode.as_ref().map(|m| m.to_str().to_owned()),
+        outcome: Outcome::Ignored,
+        duration: 0.0,
+        commands: Vec::new(),
+        diffs: Vec::new(),
+    });
+}
+
+fn relative_file(config: &Config, testpaths: &TestPaths) -> PathBuf {
+    let src_root = config.src_base.parent().unwrap_or(&config.src_base);
+    testpaths
+        .file
+        .strip_prefix(src_root)
+        .unwrap_or(&testpaths.file)
+        .to_path_buf()
+}
+
+/// Records a command line run for the current test.
+pub fn record_command(cmdline: &str) {
+    CURRENT.with(|current| {
+        if let Some(ref mut current) = *current.borrow_mut() {
+            current.commands.push(cmdline.to_owned());
+        }
+    });
+}
+
+/// Records a file holding a diff of the current test's output.
+pub fn record_diff(path: &Path) {
+    CURRENT.with(|current| {
+        if let Some(ref mut current) = *current.borrow_mut() {
+            current.diffs.push(path.to_path_buf());
+        }
+    });
+}
+
+/// Writes the report to the file given with `--report`, if any.
+pub fn write(config: &Config) -> io::Result<()> {
+    let path = match (&config.report, &config.compare_mode) {
+        (Some(path), Some(mode)) => {
+            let extension = path.extension().map_or("json".into(), |ext| ext.to_string_lossy());
+            path.with_extension(format!("{}.{}", mode.to_str(), extension))
+        }
+        (Some(path), None) => path.clone(),
+        (None, _) => return Ok(()),
+    };
+
+    let mut tests = TESTS.lock().unwrap();
+    tests.sort_by(|a, b| (&a.file, &a.revision).cmp(&(&b.file, &b.revision)));
+    let report = Report {
+        mode: config.mode.to_string(),
+        tests: &tests,
+    };
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, json)
+}
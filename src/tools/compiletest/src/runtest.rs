@@ -1,3 +1,4 @@
+use crate::bless;
 use crate::common::CompareMode;
 use crate::common::{expected_output_path, UI_EXTENSIONS, UI_FIXED, UI_STDERR, UI_STDOUT};
 use crate::common::{output_base_dir, output_base_name, output_testname_unique};
@@ -10,6 +11,7 @@ use crate::errors::{self, Error, ErrorKind};
 use filetime::FileTime;
 use crate::header::TestProps;
 use crate::json;
+use crate::report;
 use regex::Regex;
 use rustfix::{apply_suggestions, get_suggestions_from_json, Filter};
 use crate::util::{logv, PathBufExt};
@@ -156,6 +158,31 @@ pub fn make_diff(expected: &str, actual: &str, context_size: usize) -> Vec<Misma
     results
 }
 
+// Formats a diff between the expected output and actual output.
+fn format_diff(expected: &str, actual: &str) -> String {
+    let mut out = String::new();
+    for result in make_diff(expected, actual, 3) {
+        let mut line_number = result.line_number;
+        for line in result.lines {
+            match line {
+                DiffLine::Expected(e) => {
+                    out.push_str(&format!("-\t{}\n", e));
+                    line_number += 1;
+                }
+                DiffLine::Context(c) => {
+                    out.push_str(&format!("{}\t{}\n", line_number, c));
+                    line_number += 1;
+                }
+                DiffLine::Resulting(r) => {
+                    out.push_str(&format!("+\t{}\n", r));
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
 pub fn run(config: Config, testpaths: &TestPaths, revision: Option<&str>) {
     match &*config.target {
         "arm-linux-androideabi"
@@ -488,11 +515,13 @@ impl<'test> TestCx<'test> {
             round += 1;
         }
 
-        let mut expected = match self.props.pp_exact {
-            Some(ref file) => {
-                let filepath = self.testpaths.file.parent().unwrap().join(file);
-                fs::read_to_string(&filepath).unwrap()
-            }
+        let pp_exact_path = self
+            .props
+            .pp_exact
+            .as_ref()
+            .map(|file| self.testpaths.file.parent().unwrap().join(file));
+        let mut expected = match pp_exact_path {
+            Some(ref filepath) => fs::read_to_string(filepath).unwrap(),
             None => srcs[srcs.len() - 2].clone(),
         };
         let mut actual = srcs[srcs.len() - 1].clone();
@@ -504,7 +533,12 @@ impl<'test> TestCx<'test> {
             expected = expected.replace(&cr, "").to_owned();
         }
 
-        self.compare_source(&expected, &actual);
+        match pp_exact_path {
+            Some(ref filepath) if self.config.bless && expected != actual => {
+                self.bless_file(filepath, &expected, &actual);
+            }
+            _ => self.compare_source(&expected, &actual),
+        }
 
         // If we're only making sure that the output matches then just stop here
         if self.props.pretty_compare_only {
@@ -574,6 +608,11 @@ impl<'test> TestCx<'test> {
 
     fn compare_source(&self, expected: &str, actual: &str) {
         if expected != actual {
+            self.save_diff(
+                &self.output_base_name().with_extension("pretty.diff"),
+                expected,
+                actual,
+            );
             self.fatal(&format!(
                 "pretty-printed source does not match expected source\n\
                  expected:\n\
@@ -1663,6 +1702,7 @@ impl<'test> TestCx<'test> {
         let cmdline = {
             let cmdline = self.make_cmdline(&command, lib_path);
             logv(self.config, format!("executing {}", cmdline));
+            report::record_command(&cmdline);
             cmdline
         };
 
@@ -2131,6 +2171,15 @@ impl<'test> TestCx<'test> {
 
         proc_res = self.check_ir_with_filecheck();
         if !proc_res.status.success() {
+            if self.config.bless {
+                // `CHECK` lines are hand-written patterns, not a copy of the
+                // output, so there is nothing sensible to overwrite them with.
+                println!(
+                    "codegen tests cannot be blessed, update the FileCheck directives \
+                     by hand using the IR in `{}`",
+                    self.output_base_name().with_extension("ll").display()
+                );
+            }
             self.fatal_proc_rec("verification with 'FileCheck' failed", &proc_res);
         }
     }
@@ -2675,6 +2724,7 @@ impl<'test> TestCx<'test> {
             }
         }
 
+        report::record_command(&format!("{:?}", cmd));
         let output = cmd
             .spawn()
             .and_then(read2_abbreviated)
@@ -2879,32 +2929,105 @@ impl<'test> TestCx<'test> {
     fn check_mir_dump(&self) {
         let test_file_contents = fs::read_to_string(&self.testpaths.file).unwrap();
         if let Some(idx) = test_file_contents.find("// END RUST SOURCE") {
-            let (_, tests_text) = test_file_contents.split_at(idx + "// END_RUST SOURCE".len());
+            let (head, tests_text) =
+                test_file_contents.split_at(idx + "// END_RUST SOURCE".len());
             let tests_text_str = String::from(tests_text);
             let mut curr_test: Option<&str> = None;
             let mut curr_test_contents = vec![ExpectedLine::Elision];
+            // Whether the current section elides lines with `...`.
+            let mut curr_test_elides = false;
+            // The lines of the expected sections, rewritten if we are blessing.
+            let mut blessed_lines = Vec::new();
+            let mut curr_test_lines = Vec::new();
             for l in tests_text_str.lines() {
                 debug!("line: {:?}", l);
+                if curr_test.is_some() && !l.starts_with("// END") {
+                    curr_test_lines.push(l.to_owned());
+                }
                 if l.starts_with("// START ") {
                     let (_, t) = l.split_at("// START ".len());
                     curr_test = Some(t);
+                    blessed_lines.push(l.to_owned());
                 } else if l.starts_with("// END") {
                     let (_, t) = l.split_at("// END ".len());
                     if Some(t) != curr_test {
                         panic!("mismatched START END test name");
                     }
-                    self.compare_mir_test_output(curr_test.unwrap(), &curr_test_contents);
+                    let dumped_string = self.read_mir_dump(t);
+                    let result =
+                        self.compare_mir_test_output(t, &dumped_string, &curr_test_contents);
+                    match result {
+                        Ok(()) => blessed_lines.extend(curr_test_lines.drain(..)),
+                        Err(ref msg) => {
+                            // Sections are excerpts of the dump, so only the lines between
+                            // their first and last line are replaced. Sections that elide
+                            // lines are left for a human.
+                            let window = if self.config.bless && !curr_test_elides {
+                                mir_dump_window(&dumped_string, &curr_test_contents)
+                            } else {
+                                None
+                            };
+                            if let Some(window) = window {
+                                curr_test_lines.clear();
+                                blessed_lines.extend(window);
+                            } else {
+                                let expected = curr_test_lines
+                                    .iter()
+                                    .map(|l| l.trim_start_matches("//").trim_start_matches(' '))
+                                    .filter(|l| !l.is_empty())
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                let actual = dumped_string
+                                    .lines()
+                                    .map(nocomment_mir_line)
+                                    .filter(|l| !l.is_empty())
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                let diff_file =
+                                    self.get_mir_dump_dir().join(format!("{}.diff", t));
+                                self.save_diff(&diff_file, &expected, &actual);
+                                if self.config.bless {
+                                    let reason = if curr_test_elides {
+                                        "its expected MIR elides lines with `...`"
+                                    } else {
+                                        "its first or last line is no longer in the MIR dump"
+                                    };
+                                    panic!(
+                                        "{}\n\nnot blessing `{}` as {}, it has to be updated \
+                                         by hand",
+                                        msg, t, reason
+                                    );
+                                }
+                                panic!("{}", msg)
+                            }
+                        }
+                    }
                     curr_test = None;
                     curr_test_contents.clear();
                     curr_test_contents.push(ExpectedLine::Elision);
+                    curr_test_elides = false;
                 } else if l.is_empty() {
                     // ignore
                 } else if l.starts_with("//") && l.split_at("//".len()).1.trim() == "..." {
-                    curr_test_contents.push(ExpectedLine::Elision)
+                    curr_test_contents.push(ExpectedLine::Elision);
+                    curr_test_elides = true;
                 } else if l.starts_with("// ") {
                     let (_, test_content) = l.split_at("// ".len());
                     curr_test_contents.push(ExpectedLine::Text(test_content));
                 }
+                if curr_test.is_none() {
+                    blessed_lines.push(l.to_owned());
+                }
+            }
+
+            if self.config.bless {
+                let mut blessed = format!("{}{}", head, blessed_lines.join("\n"));
+                if test_file_contents.ends_with('\n') {
+                    blessed.push('\n');
+                }
+                if blessed != test_file_contents {
+                    self.bless_file(&self.testpaths.file, &test_file_contents, &blessed);
+                }
             }
         }
     }
@@ -2927,12 +3050,11 @@ impl<'test> TestCx<'test> {
         }
     }
 
-    fn compare_mir_test_output(&self, test_name: &str, expected_content: &[ExpectedLine<&str>]) {
+    fn read_mir_dump(&self, test_name: &str) -> String {
         let mut output_file = PathBuf::new();
         output_file.push(self.get_mir_dump_dir());
         output_file.push(test_name);
-        debug!("comparing the contests of: {:?}", output_file);
-        debug!("with: {:?}", expected_content);
+        debug!("reading the contents of: {:?}", output_file);
         if !output_file.exists() {
             panic!(
                 "Output file `{}` from test does not exist",
@@ -2941,7 +3063,18 @@ impl<'test> TestCx<'test> {
         }
         self.check_mir_test_timestamp(test_name, &output_file);
 
-        let dumped_string = fs::read_to_string(&output_file).unwrap();
+        fs::read_to_string(&output_file).unwrap()
+    }
+
+    fn compare_mir_test_output(
+        &self,
+        test_name: &str,
+        dumped_string: &str,
+        expected_content: &[ExpectedLine<&str>],
+    ) -> Result<(), String> {
+        debug!("comparing the contents of: {:?}", test_name);
+        debug!("with: {:?}", expected_content);
+
         let mut dumped_lines = dumped_string
             .lines()
             .map(|l| nocomment_mir_line(l))
@@ -2981,14 +3114,14 @@ impl<'test> TestCx<'test> {
                 .map(|l| f(l))
                 .collect::<Vec<_>>()
                 .join("\n");
-            panic!(
+            format!(
                 "Did not find expected line, error: {}\n\
                  Expected Line: {:?}\n\
                  Test Name: {}\n\
                  Expected:\n{}\n\
                  Actual:\n{}",
                 extra_msg, expected_line, test_name, expected_content, normalize_all
-            );
+            )
         };
 
         // We expect each non-empty line to appear consecutively, non-consecutive lines
@@ -3004,7 +3137,7 @@ impl<'test> TestCx<'test> {
 
                     if !compare(expected_line, dumped_line) {
                         error!("{:?}", start_block_line);
-                        error(
+                        return Err(error(
                             expected_line,
                             format!(
                                 "Mismatch in lines\n\
@@ -3013,7 +3146,7 @@ impl<'test> TestCx<'test> {
                                 start_block_line.unwrap_or("None"),
                                 dumped_line
                             ),
-                        );
+                        ));
                     }
                 }
                 Some(&ExpectedLine::Elision) => {
@@ -3033,13 +3166,17 @@ impl<'test> TestCx<'test> {
                             }
                         }
                         if !found {
-                            error(expected_line, "ran out of mir dump to match against".into());
+                            return Err(error(
+                                expected_line,
+                                "ran out of mir dump to match against".into(),
+                            ));
                         }
                     }
                 }
                 None => {}
             }
         }
+        Ok(())
     }
 
     fn get_mir_dump_dir(&self) -> PathBuf {
@@ -3174,37 +3311,39 @@ impl<'test> TestCx<'test> {
         }
     }
 
+    /// Overwrites the expected output in `path` with `actual`, printing what
+    /// changed. Only used with `--bless`.
+    fn bless_file(&self, path: &Path, expected: &str, actual: &str) {
+        println!("blessing `{}`:\n", path.display());
+        print!("{}", format_diff(expected, actual));
+        if let Err(err) = fs::write(path, actual) {
+            self.fatal(&format!("failed to write `{}`: {}", path.display(), err));
+        }
+        bless::record(path, expected, actual);
+    }
+
+    /// Saves a diff between `expected` and `actual` to `path` for the test
+    /// report. Does nothing if no report was requested.
+    fn save_diff(&self, path: &Path, expected: &str, actual: &str) {
+        if self.config.report.is_none() {
+            return;
+        }
+        if let Err(err) = fs::write(path, format_diff(expected, actual)) {
+            self.fatal(&format!("failed to write `{}`: {}", path.display(), err));
+        }
+        report::record_diff(path);
+    }
+
     fn compare_output(&self, kind: &str, actual: &str, expected: &str) -> usize {
         if actual == expected {
             return 0;
         }
 
-        if !self.config.bless {
-            if expected.is_empty() {
-                println!("normalized {}:\n{}\n", kind, actual);
-            } else {
-                println!("diff of {}:\n", kind);
-                let diff_results = make_diff(expected, actual, 3);
-                for result in diff_results {
-                    let mut line_number = result.line_number;
-                    for line in result.lines {
-                        match line {
-                            DiffLine::Expected(e) => {
-                                println!("-\t{}", e);
-                                line_number += 1;
-                            }
-                            DiffLine::Context(c) => {
-                                println!("{}\t{}", line_number, c);
-                                line_number += 1;
-                            }
-                            DiffLine::Resulting(r) => {
-                                println!("+\t{}", r);
-                            }
-                        }
-                    }
-                    println!("");
-                }
-            }
+        if expected.is_empty() {
+            println!("normalized {}:\n{}\n", kind, actual);
+        } else {
+            println!("diff of {}:\n", kind);
+            print!("{}", format_diff(expected, actual));
         }
 
         let mode = self.config.compare_mode.as_ref().map_or("", |m| m.to_str());
@@ -3214,6 +3353,10 @@ impl<'test> TestCx<'test> {
             .with_extra_extension(mode)
             .with_extra_extension(kind);
 
+        if !self.config.bless {
+            self.save_diff(&output_file.with_extra_extension("diff"), expected, actual);
+        }
+
         let mut files = vec![output_file];
         if self.config.bless {
             files.push(expected_output_path(
@@ -3236,6 +3379,9 @@ impl<'test> TestCx<'test> {
                 ));
             }
         }
+        if self.config.bless {
+            bless::record(&files[1], expected, actual);
+        }
 
         println!("\nThe actual {0} differed from the expected {0}.", kind);
         for output_file in files {
@@ -3354,6 +3500,52 @@ fn normalize_mir_line(line: &str) -> String {
     nocomment_mir_line(line).replace(char::is_whitespace, "")
 }
 
+/// Returns the lines of a MIR dump to bless a failing expected section with, as comments: those
+/// from the first line matching the section's first line up to the line matching its last line
+/// that gives the window closest in length to the section. The blank lines between blocks are
+/// kept as empty comments.
+fn mir_dump_window(dumped: &str, expected: &[ExpectedLine<&str>]) -> Option<Vec<String>> {
+    let expected = expected
+        .iter()
+        .filter_map(|l| match *l {
+            ExpectedLine::Text(l) if !l.is_empty() => Some(l),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let first = normalize_mir_line(expected.first()?);
+    let last = normalize_mir_line(expected.last()?);
+    // Lines that only hold a comment are dropped, blank lines are kept.
+    let lines = dumped
+        .lines()
+        .filter(|l| l.is_empty() || !nocomment_mir_line(l).is_empty())
+        .map(nocomment_mir_line)
+        .collect::<Vec<_>>();
+    let start = lines.iter().position(|l| normalize_mir_line(l) == first)?;
+
+    let distance = |len: usize| (len as isize - expected.len() as isize).abs();
+    let mut end: Option<(usize, usize)> = None;
+    let mut len = 0;
+    for (i, l) in lines.iter().enumerate().skip(start) {
+        if l.is_empty() {
+            continue;
+        }
+        len += 1;
+        if normalize_mir_line(l) == last
+            && end.map_or(true, |(_, best)| distance(len) < distance(best))
+        {
+            end = Some((i, len));
+        }
+    }
+    let (end, _) = end?;
+
+    Some(
+        lines[start..=end]
+            .iter()
+            .map(|l| if l.is_empty() { "//".to_owned() } else { format!("// {}", l) })
+            .collect(),
+    )
+}
+
 fn nocomment_mir_line(line: &str) -> &str {
     if let Some(idx) = line.find("//") {
         let (l, _) = line.split_at(idx);
@@ -0,0 +1,243 @@
+#![deny(rust_2018_idioms)]
+
+//! Summarizes the event stream written by `rustc -Z self-profile`.
+//!
+//! The stream consists of `<crate>-<pid>.profile_index` and one
+//! `<crate>-<pid>-<thread id>.events` file per thread; see
+//! `SelfProfiler::write_event_stream` in `src/librustc/util/profiling.rs` for
+//! the format. Given `<crate>-<pid>`, this prints a table with the self time,
+//! the number of executions and cache hits, and the time spent loading
+//! results from the incremental cache and blocked on other threads for every
+//! query and generic activity.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+use std::time::Duration;
+
+const EVENTS_MAGIC: &[u8; 8] = b"RSPEVT\0\0";
+const INDEX_MAGIC: &[u8; 8] = b"RSPIDX\0\0";
+const FORMAT_VERSION: u32 = 1;
+const EVENT_SIZE: usize = 24;
+
+const QUERY_START: u8 = 0;
+const QUERY_END: u8 = 1;
+const GENERIC_ACTIVITY_START: u8 = 2;
+const GENERIC_ACTIVITY_END: u8 = 3;
+const INCREMENTAL_LOAD_RESULT_START: u8 = 4;
+const INCREMENTAL_LOAD_RESULT_END: u8 = 5;
+const QUERY_CACHE_HIT: u8 = 6;
+const QUERY_BLOCKED_START: u8 = 8;
+const QUERY_BLOCKED_END: u8 = 9;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("usage: {} <CRATE>-<PID>", args[0]);
+        process::exit(1);
+    }
+    let prefix = args[1].trim_end_matches(".profile_index");
+
+    match summarize(prefix) {
+        Ok(summary) => summary.print(),
+        Err(e) => {
+            eprintln!("error: failed to read the profile `{}`: {}", prefix, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A cursor over the bytes of a file.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(invalid_data("unexpected end of file"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| invalid_data("invalid string"))
+    }
+
+    fn header(&mut self, magic: &[u8; 8]) -> io::Result<()> {
+        if self.bytes(8)? != magic {
+            return Err(invalid_data("not a self-profile file"));
+        }
+        let version = self.u32()?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(&format!("unsupported format version {}", version)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    self_time: u64,
+    executions: u64,
+    cache_hits: u64,
+    incremental_load_time: u64,
+    blocked_time: u64,
+}
+
+struct Summary {
+    crate_name: String,
+    stats: HashMap<String, Stats>,
+}
+
+/// A span that has started but not ended yet.
+struct Frame {
+    kind: u8,
+    name: u32,
+    start: u64,
+    /// The time spent in nested spans.
+    child_time: u64,
+}
+
+fn summarize(prefix: &str) -> io::Result<Summary> {
+    let index = fs::read(format!("{}.profile_index", prefix))?;
+    let mut index = Reader { data: &index };
+    index.header(INDEX_MAGIC)?;
+    let _start_time = index.u64()?;
+    let crate_name = index.string()?;
+    let strings = (0..index.u32()?)
+        .map(|_| index.string())
+        .collect::<io::Result<Vec<_>>>()?;
+    let threads = (0..index.u32()?)
+        .map(|_| index.u64())
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut stats: HashMap<u32, Stats> = HashMap::new();
+    for thread in threads {
+        let events = fs::read(format!("{}-{}.events", prefix, thread))?;
+        let mut events = Reader { data: &events };
+        events.header(EVENTS_MAGIC)?;
+        let _padding = events.u32()?;
+        let count = events.u64()? as usize;
+        let events = events.bytes(count * EVENT_SIZE)?;
+
+        let mut stack: Vec<Frame> = Vec::new();
+        for event in events.chunks(EVENT_SIZE) {
+            let mut event = Reader { data: event };
+            let kind = event.bytes(4)?[0];
+            let name = event.u32()?;
+            let time = event.u64()?;
+
+            match kind {
+                QUERY_START |
+                GENERIC_ACTIVITY_START |
+                INCREMENTAL_LOAD_RESULT_START |
+                QUERY_BLOCKED_START => {
+                    stack.push(Frame { kind, name, start: time, child_time: 0 });
+                }
+                QUERY_END |
+                GENERIC_ACTIVITY_END |
+                INCREMENTAL_LOAD_RESULT_END |
+                QUERY_BLOCKED_END => {
+                    let frame = match stack.pop() {
+                        Some(frame) => frame,
+                        None => continue,
+                    };
+                    let duration = time.saturating_sub(frame.start);
+                    let stats = stats.entry(frame.name).or_default();
+                    match frame.kind {
+                        INCREMENTAL_LOAD_RESULT_START => stats.incremental_load_time += duration,
+                        QUERY_BLOCKED_START => stats.blocked_time += duration,
+                        _ => {
+                            stats.self_time += duration.saturating_sub(frame.child_time);
+                            stats.executions += 1;
+                        }
+                    }
+                    if let Some(parent) = stack.last_mut() {
+                        parent.child_time += duration;
+                    }
+                }
+                QUERY_CACHE_HIT => stats.entry(name).or_default().cache_hits += 1,
+                _ => {}
+            }
+        }
+    }
+
+    let stats = stats
+        .into_iter()
+        .map(|(name, stats)| {
+            let name = strings
+                .get(name as usize)
+                .cloned()
+                .ok_or_else(|| invalid_data("invalid string id"))?;
+            Ok((name, stats))
+        })
+        .collect::<io::Result<_>>()?;
+    Ok(Summary { crate_name, stats })
+}
+
+fn fmt_duration(nanos: u64) -> String {
+    let d = Duration::from_nanos(nanos);
+    format!("{}.{:03}ms", d.as_millis(), d.subsec_micros() % 1000)
+}
+
+impl Summary {
+    fn print(&self) {
+        let mut stats: Vec<_> = self.stats.iter().collect();
+        stats.sort_by(|a, b| b.1.self_time.cmp(&a.1.self_time).then(a.0.cmp(b.0)));
+        let total_time: u64 = stats.iter().map(|(_, s)| s.self_time).sum();
+        let name_width = stats.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(4);
+
+        println!("self-profile of `{}`, total time {}", self.crate_name, fmt_duration(total_time));
+        println!(
+            "{:<width$} | {:>12} | {:>6} | {:>10} | {:>10} | {:>14} | {:>12}",
+            "Item",
+            "Self time",
+            "% time",
+            "Executions",
+            "Cache hits",
+            "Incr. loading",
+            "Blocked",
+            width = name_width
+        );
+        for (name, s) in stats {
+            let percent = if total_time == 0 {
+                0.0
+            } else {
+                s.self_time as f64 * 100.0 / total_time as f64
+            };
+            println!(
+                "{:<width$} | {:>12} | {:>6.2} | {:>10} | {:>10} | {:>14} | {:>12}",
+                name,
+                fmt_duration(s.self_time),
+                percent,
+                s.executions,
+                s.cache_hits,
+                fmt_duration(s.incremental_load_time),
+                fmt_duration(s.blocked_time),
+                width = name_width
+            );
+        }
+    }
+}